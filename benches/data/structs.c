@@ -0,0 +1,57 @@
+/* Input for the benchmarks, built with `gcc -g -O0 -nostdlib -static -o structs structs.c`
+ * and checked in so the numbers don't depend on the local compiler. */
+
+typedef unsigned char u8;
+typedef unsigned short u16;
+typedef unsigned int u32;
+typedef unsigned long u64;
+
+struct list_head { struct list_head *next, *prev; };
+
+enum state { STATE_IDLE, STATE_RUNNING, STATE_STOPPED };
+
+struct holey { char a; long b; char c; int d; short e; };
+
+struct packed_hdr { u8 kind; u32 len; } __attribute__((packed));
+
+struct bits { u32 valid:1, dirty:1, :6, order:4, count:20; u8 flags; };
+
+struct inner { u64 key; void *value; };
+
+struct node {
+    struct list_head list;
+    struct inner entries[8];
+    union { u64 raw; struct { u32 lo, hi; }; } id;
+    enum state state;
+    const char *name;
+    int (*compare)(const struct node *, const struct node *);
+    struct node *parent;
+    struct bits bits;
+    struct packed_hdr hdr;
+    char tail[];
+};
+
+struct table {
+    struct node **buckets;
+    u64 nr_buckets;
+    u64 nr_entries;
+    struct holey stats[4];
+    volatile u32 lock;
+    void (*destroy)(struct table *);
+};
+
+struct task {
+    struct list_head tasks;
+    struct table *files;
+    struct node root;
+    char comm[16];
+    u16 prio;
+    u8 policy;
+    long long utime, stime;
+    double load;
+};
+
+struct task init_task;
+struct table global_table;
+
+void _start(void) { for (;;) ; }
@@ -0,0 +1,51 @@
+use std::fs;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs");
+
+fn load_structs(c: &mut Criterion) {
+    let data = fs::read(INPUT).unwrap();
+    c.bench_function("load_structs", |b| {
+        b.iter_batched(
+            || rshole::Parser::from_bytes(&data).unwrap(),
+            |mut parser| parser.load_structs().unwrap(),
+            BatchSize::SmallInput
+        )
+    });
+}
+
+// the checked-in binary has no name index, rebuild it with -gpubnames to
+// measure the index path
+fn load_structs_indexed(c: &mut Criterion) {
+    let data = fs::read(INPUT).unwrap();
+    c.bench_function("load_structs_indexed", |b| {
+        b.iter_batched(
+            || rshole::Parser::from_bytes(&data).unwrap(),
+            |mut parser| parser.load_structs_indexed().unwrap(),
+            BatchSize::SmallInput
+        )
+    });
+}
+
+fn struct_members(c: &mut Criterion) {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = rshole::Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    c.bench_function("get_struct + members", |b| {
+        b.iter(|| {
+            let task = parser.get_struct("task").unwrap();
+            task.members_by_offset(&parser).unwrap()
+        })
+    });
+}
+
+fn padding_report(c: &mut Criterion) {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = rshole::Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    c.bench_function("padding_report", |b| b.iter(|| parser.padding_report().unwrap()));
+}
+
+criterion_group!(benches, load_structs, load_structs_indexed, struct_members, padding_report);
+criterion_main!(benches);
@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rshole::StructMemberIter;
+
+// parse an arbitrary object file, index it and resolve every member type
+fuzz_target!(|data: &[u8]| {
+    let mut parser = match rshole::Parser::from_bytes(data) {
+        Ok(parser) => parser,
+        Err(_) => return,
+    };
+    if parser.load_structs().is_err() {
+        return;
+    }
+    for dw_struct in parser.struct_dict.values() {
+        for member in StructMemberIter::new(dw_struct, &parser) {
+            let member = match member {
+                Ok(member) => member,
+                Err(_) => break,
+            };
+            if let Some(mb_type) = member.mb_type {
+                let _ = parser.get_type(mb_type);
+            }
+        }
+    }
+});
@@ -0,0 +1,245 @@
+// loading structs through the name index sections instead of every DIE, see
+// Parser::load_structs_indexed
+
+use alloc::vec::Vec;
+
+use gimli::Reader;
+
+use crate::{Error, Map, Parser, Set, R};
+
+/// The name index sections of an object, empty when it has none.
+pub(crate) struct NameIndexes {
+    pub(crate) debug_names: R,
+    pub(crate) debug_pubtypes: R,
+}
+
+// units an index describes, by their offset in .debug_info, and the struct
+// DIEs it lists in them
+#[derive(Default)]
+struct Indexed {
+    units: Vec<usize>,
+    structs: Vec<(usize, gimli::UnitOffset)>,
+}
+
+impl Indexed {
+    // record the units and structs by header index, leaving out `skip`
+    fn add_to(self, units: &Map<usize, usize>, skip: &Set<usize>, covered: &mut Set<usize>,
+              structs: &mut Map<usize, Vec<gimli::UnitOffset>>) {
+        for unit_offset in self.units {
+            match units.get(&unit_offset) {
+                Some(header_idx) if !skip.contains(header_idx) => { covered.insert(*header_idx); }
+                _ => {}
+            }
+        }
+        for (unit_offset, offset) in self.structs {
+            match units.get(&unit_offset) {
+                Some(header_idx) if !skip.contains(header_idx) => structs.entry(*header_idx).or_default().push(offset),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Parser {
+    /// Same as `load_structs`, but finds the struct DIEs of a unit through
+    /// `.debug_names` (dwarf 5) or `.debug_pubtypes` when one of them
+    /// covers it, instead of walking all of its DIEs. Units without an
+    /// index, or with one that can't be read, are walked as usual. Units
+    /// indexed by an earlier call are skipped like in `load_structs`.
+    ///
+    /// The indexes only list what the producer put in them, e.g. gcc's
+    /// `.debug_pubtypes` leaves out types declared inside a function, so the
+    /// result can miss structs `load_structs` would find.
+    pub fn load_structs_indexed(&mut self) -> Result<(), Error> {
+        let mut structs: Map<usize, Vec<gimli::UnitOffset>> = Map::new();
+        let mut covered = Set::new();
+        for object_idx in 0..self.objects.len() {
+            // .debug_info offsets of the object's units
+            let mut units = Map::new();
+            for (header_idx, (idx, header)) in self.headers.iter().enumerate() {
+                if *idx != object_idx {
+                    continue;
+                }
+                if let gimli::UnitSectionOffset::DebugInfoOffset(offset) = header.offset() {
+                    units.insert(offset.0, header_idx);
+                }
+            }
+            let names = &self.objects[object_idx].names;
+            let debug_names = debug_names_structs(names.debug_names.clone());
+            let debug_pubtypes = debug_pubtypes_structs(names.debug_pubtypes.clone()).unwrap_or_default();
+            let by_names = debug_names.units.iter().filter_map(|offset| units.get(offset).copied()).collect();
+            debug_names.add_to(&units, &Set::new(), &mut covered, &mut structs);
+            // taking units from both would count their structs twice
+            debug_pubtypes.add_to(&units, &by_names, &mut covered, &mut structs);
+        }
+
+        // same unit order as load_structs, so the same definition wins
+        for header_idx in self.indexed_units..self.headers.len() {
+            let unit = self.unit(header_idx)?;
+            if !covered.contains(&header_idx) {
+                let mut entries = unit.entries();
+                while let Some((_delta_depth, entry)) = entries.next_dfs()? {
+                    if entry.tag() == gimli::DW_TAG_structure_type {
+                        self.load_struct(header_idx, entry)?;
+                    }
+                }
+                continue;
+            }
+            let mut offsets = structs.remove(&header_idx).unwrap_or_default();
+            offsets.sort();
+            offsets.dedup();
+            for offset in offsets {
+                let entry = unit.entry(offset)?;
+                if entry.tag() == gimli::DW_TAG_structure_type {
+                    self.load_struct(header_idx, &entry)?;
+                }
+            }
+        }
+        self.indexed_units = self.headers.len();
+        Ok(())
+    }
+}
+
+// struct entries of every name index in .debug_names, see section 6.1.1 of
+// the dwarf 5 spec. Foreign type units, which live in another file, are
+// skipped. An index that can't be read is left out, so its units are walked
+fn debug_names_structs(mut input: R) -> Indexed {
+    let mut indexed = Indexed::default();
+    while !input.is_empty() {
+        // without the length the following indexes can't be found either
+        let (index, format) = match input.read_initial_length().and_then(|(length, format)| Ok((input.split(length)?, format))) {
+            Ok(index) => index,
+            Err(_) => break
+        };
+        if let Ok(index) = name_index_structs(index, format) {
+            indexed.units.extend(index.units);
+            indexed.structs.extend(index.structs);
+        }
+    }
+    indexed
+}
+
+fn name_index_structs(mut index: R, format: gimli::Format) -> Result<Indexed, Error> {
+    let mut indexed = Indexed::default();
+    let version = index.read_u16()?;
+    if version != 5 {
+        return Err(gimli::Error::UnknownVersion(version.into()).into());
+    }
+    index.skip(2)?;
+    let cu_count = index.read_u32()? as usize;
+    let local_tu_count = index.read_u32()? as usize;
+    let foreign_tu_count = index.read_u32()? as usize;
+    let bucket_count = index.read_u32()? as usize;
+    let name_count = index.read_u32()? as usize;
+    let abbrev_size = index.read_u32()? as usize;
+    let augmentation_size = index.read_u32()? as usize;
+    index.skip(augmentation_size)?;
+
+    // compile units first, then local type units
+    let mut units = Vec::new();
+    for _ in 0..cu_count.saturating_add(local_tu_count) {
+        units.push(index.read_offset(format)?);
+    }
+    index.skip(foreign_tu_count.saturating_mul(8))?;
+    // the hash table, only useful to look up a single name
+    index.skip(bucket_count.saturating_mul(4))?;
+    if bucket_count != 0 {
+        index.skip(name_count.saturating_mul(4))?;
+    }
+    // string offsets
+    index.skip(name_count.saturating_mul(format.word_size() as usize))?;
+    let mut entry_offsets = Vec::new();
+    for _ in 0..name_count {
+        entry_offsets.push(index.read_offset(format)?);
+    }
+    let abbrevs = name_abbrevs(index.split(abbrev_size)?)?;
+    let pool = index;
+
+    for entry_offset in entry_offsets {
+        let mut entries = pool.clone();
+        entries.skip(entry_offset)?;
+        loop {
+            let code = entries.read_uleb128()?;
+            if code == 0 {
+                break;
+            }
+            let (tag, attrs) = abbrevs.get(&code).ok_or(gimli::Error::UnknownAbbreviation)?;
+            let mut unit_idx = None;
+            let mut die_offset = None;
+            for (idx, form) in attrs {
+                let value = index_value(&mut entries, *form)?;
+                match *idx {
+                    gimli::DW_IDX_compile_unit => unit_idx = Some(value as usize),
+                    gimli::DW_IDX_type_unit => unit_idx = Some(cu_count.saturating_add(value as usize)),
+                    gimli::DW_IDX_die_offset => die_offset = Some(value as usize),
+                    _ => {}
+                }
+            }
+            if *tag != gimli::DW_TAG_structure_type {
+                continue;
+            }
+            // the unit may be left out when the index has just one
+            let unit_idx = match unit_idx {
+                Some(unit_idx) => Some(unit_idx),
+                None if units.len() == 1 => Some(0),
+                None => None
+            };
+            if let (Some(&unit), Some(die_offset)) = (unit_idx.and_then(|unit_idx| units.get(unit_idx)), die_offset) {
+                indexed.structs.push((unit, gimli::UnitOffset(die_offset)));
+            }
+        }
+    }
+    indexed.units.extend(units);
+    Ok(indexed)
+}
+
+type NameAbbrevs = Map<u64, (gimli::DwTag, Vec<(gimli::DwIdx, gimli::DwForm)>)>;
+
+fn name_abbrevs(mut input: R) -> Result<NameAbbrevs, Error> {
+    let mut abbrevs = Map::new();
+    loop {
+        let code = input.read_uleb128()?;
+        if code == 0 {
+            break;
+        }
+        let tag = gimli::DwTag(input.read_uleb128_u16()?);
+        let mut attrs = Vec::new();
+        loop {
+            let idx = input.read_uleb128_u16()?;
+            let form = input.read_uleb128_u16()?;
+            if idx == 0 && form == 0 {
+                break;
+            }
+            attrs.push((gimli::DwIdx(idx), gimli::DwForm(form)));
+        }
+        abbrevs.insert(code, (tag, attrs));
+    }
+    Ok(abbrevs)
+}
+
+fn index_value(input: &mut R, form: gimli::DwForm) -> Result<u64, Error> {
+    let value = match form {
+        gimli::DW_FORM_flag_present => 1,
+        gimli::DW_FORM_data1 | gimli::DW_FORM_ref1 | gimli::DW_FORM_flag => input.read_u8()?.into(),
+        gimli::DW_FORM_data2 | gimli::DW_FORM_ref2 => input.read_u16()?.into(),
+        gimli::DW_FORM_data4 | gimli::DW_FORM_ref4 => input.read_u32()?.into(),
+        gimli::DW_FORM_data8 | gimli::DW_FORM_ref8 | gimli::DW_FORM_ref_sig8 => input.read_u64()?,
+        gimli::DW_FORM_udata | gimli::DW_FORM_ref_udata => input.read_uleb128()?,
+        gimli::DW_FORM_sdata => input.read_sleb128()? as u64,
+        _ => return Err(gimli::Error::UnknownForm.into())
+    };
+    Ok(value)
+}
+
+// .debug_pubtypes lists every type, the tag is only known from the DIE. An
+// unreadable table is left out like an unreadable name index
+fn debug_pubtypes_structs(input: R) -> Result<Indexed, Error> {
+    let mut indexed = Indexed::default();
+    let mut items = gimli::DebugPubTypes::from(input).items();
+    while let Some(item) = items.next()? {
+        let unit = item.unit_header_offset().0;
+        indexed.units.push(unit);
+        indexed.structs.push((unit, item.die_offset()));
+    }
+    Ok(indexed)
+}
@@ -0,0 +1,411 @@
+// BTF, the type format of the linux kernel and eBPF, see Parser::to_btf and
+// Documentation/bpf/btf.rst in the kernel tree
+
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{DwTypeMeta, EnumValue, Error, Map, Parser, Pointer, Reference, RvalueReference, StructMember, Type};
+
+const BTF_MAGIC: u16 = 0xeb9f;
+const BTF_VERSION: u8 = 1;
+const HEADER_LEN: u32 = 24;
+
+const KIND_INT: u32 = 1;
+const KIND_PTR: u32 = 2;
+const KIND_ARRAY: u32 = 3;
+const KIND_STRUCT: u32 = 4;
+const KIND_UNION: u32 = 5;
+const KIND_ENUM: u32 = 6;
+const KIND_FWD: u32 = 7;
+const KIND_TYPEDEF: u32 = 8;
+const KIND_VOLATILE: u32 = 9;
+const KIND_CONST: u32 = 10;
+const KIND_RESTRICT: u32 = 11;
+const KIND_FUNC_PROTO: u32 = 13;
+const KIND_FLOAT: u32 = 16;
+const KIND_ENUM64: u32 = 19;
+
+const INT_SIGNED: u32 = 1;
+const INT_CHAR: u32 = 2;
+const INT_BOOL: u32 = 4;
+
+impl Parser {
+    /// Convert the indexed structs, every enum and every base type, and the
+    /// types they refer to, into the BTF format eBPF tooling reads, like
+    /// `pahole -J` does. The result is the contents of a `.BTF` section in
+    /// the byte order of the first object. Call `load_structs` first.
+    ///
+    /// Structs, unions, enums, base types, pointers, arrays, typedefs, the
+    /// C qualifiers and function prototypes are converted. BTF has no
+    /// counterpart for everything else:
+    ///
+    /// - C++ references become pointers.
+    /// - `_Atomic` is dropped, the type it qualifies is used instead.
+    /// - Types without a BTF kind, such as `_Complex` base types, Fortran
+    ///   strings, `decltype(nullptr)` or unknown tags, become arrays of
+    ///   `unsigned char` of the same size, so the layout of the structs
+    ///   holding them is kept.
+    /// - Static members, variables and functions are left out.
+    pub fn to_btf(&self) -> Result<Vec<u8>, Error> {
+        let endian = match self.headers.is_empty() {
+            true => gimli::RunTimeEndian::Little,
+            false => self.endian(0)
+        };
+        let mut writer = BtfWriter::new(self, endian);
+
+        let mut structs: Vec<_> = self.struct_dict.values().collect();
+        structs.sort_by(|a, b| a.name.cmp(&b.name));
+        for dw_struct in structs {
+            writer.type_id(&Type::Struct(dw_struct.clone()), 0)?;
+        }
+        for dw_enum in self.enums()? {
+            writer.type_id(&Type::Enum(dw_enum), 0)?;
+        }
+        for base in self.base_types()? {
+            writer.type_id(&Type::Base(base), 0)?;
+        }
+        writer.finish()
+    }
+}
+
+struct BtfWriter<'a> {
+    parser: &'a Parser,
+    endian: gimli::RunTimeEndian,
+    // encoded types by id - 1, id 0 is void. Types are given an id when
+    // first referred to and encoded later, so cycles through pointers
+    // don't recurse
+    types: Vec<Vec<u8>>,
+    pending: VecDeque<(u32, Type)>,
+    ids: Map<DwTypeMeta, u32>,
+    strings: Vec<u8>,
+    string_offsets: Map<String, u32>,
+    // `unsigned char` for placeholder arrays and the index type of arrays
+    byte_id: Option<u32>,
+    index_id: Option<u32>,
+}
+
+impl<'a> BtfWriter<'a> {
+    fn new(parser: &'a Parser, endian: gimli::RunTimeEndian) -> BtfWriter<'a> {
+        BtfWriter {
+            parser,
+            endian,
+            types: Vec::new(),
+            pending: VecDeque::new(),
+            ids: Map::new(),
+            strings: vec![0],
+            string_offsets: Map::new(),
+            byte_id: None,
+            index_id: None,
+        }
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, Error> {
+        self.flush()?;
+        let type_data: Vec<u8> = self.types.concat();
+        let type_len = u32::try_from(type_data.len()).map_err(|_| gimli::Error::UnsupportedOffset)?;
+        let str_len = u32::try_from(self.strings.len()).map_err(|_| gimli::Error::UnsupportedOffset)?;
+        let mut out = Vec::new();
+        self.push_u16(&mut out, BTF_MAGIC);
+        out.push(BTF_VERSION);
+        out.push(0);
+        for value in [HEADER_LEN, 0, type_len, type_len, str_len] {
+            self.push_u32(&mut out, value);
+        }
+        out.extend(type_data);
+        out.extend(self.strings);
+        Ok(out)
+    }
+
+    // encode every type that was given an id but not encoded yet
+    fn flush(&mut self) -> Result<(), Error> {
+        while let Some((id, type_inst)) = self.pending.pop_front() {
+            let encoded = self.encode(type_inst)?;
+            self.types[id as usize - 1] = encoded;
+        }
+        Ok(())
+    }
+
+    fn new_id(&mut self) -> u32 {
+        self.types.push(Vec::new());
+        self.types.len() as u32
+    }
+
+    // the id `type_inst` has or gets, 0 for void
+    fn type_id(&mut self, type_inst: &Type, depth: usize) -> Result<u32, Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let type_inst = match type_inst {
+            Type::Void => return Ok(0),
+            Type::Unknown(t) if t.tag == gimli::DwTag(0) => return Ok(0),
+            // members of a forward declaration live in the definition
+            Type::Struct(t) => Type::Struct(self.parser.struct_definition(t).clone()),
+            Type::Unknown(t) if t.tag == gimli::DW_TAG_atomic_type => {
+                return match self.parser.resolve_meta(&t.meta)? {
+                    Some(inner) => self.type_id(&inner, depth + 1),
+                    None => Ok(0)
+                };
+            }
+            _ => type_inst.clone()
+        };
+        // the dimensions of a multi-dimensional array share their DIE
+        if let Type::Array(_) = type_inst {
+            let id = self.new_id();
+            self.pending.push_back((id, type_inst));
+            return Ok(id);
+        }
+        let meta = match type_inst.meta() {
+            Some(meta) => meta.clone(),
+            None => return Ok(0)
+        };
+        if let Some(id) = self.ids.get(&meta) {
+            return Ok(*id);
+        }
+        let id = self.new_id();
+        self.ids.insert(meta, id);
+        self.pending.push_back((id, type_inst));
+        Ok(id)
+    }
+
+    fn encode(&mut self, type_inst: Type) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        match type_inst {
+            Type::Struct(t) if t.declared_size().is_none() && !t.name.is_empty() => {
+                let name = self.string(&t.name);
+                self.push_type(&mut out, name, KIND_FWD, 0, false, 0);
+            }
+            Type::Struct(t) => {
+                let name = self.string(&t.name);
+                let members = t.members_by_offset(self.parser)?;
+                self.aggregate(&mut out, name, KIND_STRUCT, t.size, members)?;
+            }
+            Type::Union(t) => {
+                let name = match &t.name {
+                    Some(name) => self.string(name),
+                    None => 0
+                };
+                let members = self.parser.members(&t.meta)?.into_iter().filter(|member| !member.is_static).collect();
+                self.aggregate(&mut out, name, KIND_UNION, t.size, members)?;
+            }
+            Type::Enum(t) => {
+                let name = match &t.name {
+                    Some(name) => self.string(name),
+                    None => 0
+                };
+                let signed = self.parser.enum_encoding(&t.meta)? == gimli::DW_ATE_signed;
+                let enumerators = t.enumerators(self.parser)?;
+                let values: Vec<(u32, i64)> = enumerators.iter().map(|(name, value)| {
+                    let value = match value {
+                        EnumValue::Int(value) => *value,
+                        EnumValue::Big(bytes) => bytes.iter().take(8).rev().fold(0u64, |acc, byte| (acc << 8) | *byte as u64) as i64
+                    };
+                    (self.string(name), value)
+                }).collect();
+                let fits = values.iter().all(|(_, value)| match signed {
+                    true => i32::try_from(*value).is_ok(),
+                    false => u32::try_from(*value as u64).is_ok()
+                });
+                let kind = if fits { KIND_ENUM } else { KIND_ENUM64 };
+                self.push_type(&mut out, name, kind, values.len() as u32, signed, t.size as u32);
+                for (name, value) in values {
+                    self.push_u32(&mut out, name);
+                    self.push_u32(&mut out, value as u32);
+                    if kind == KIND_ENUM64 {
+                        self.push_u32(&mut out, ((value as u64) >> 32) as u32);
+                    }
+                }
+            }
+            Type::Base(t) => {
+                let name = self.string(&t.name);
+                let bits = t.bit_size.unwrap_or(t.size.saturating_mul(8)) as u32;
+                let encoding = match t.encoding {
+                    gimli::DW_ATE_signed => Some(INT_SIGNED),
+                    gimli::DW_ATE_signed_char => Some(INT_SIGNED | INT_CHAR),
+                    gimli::DW_ATE_unsigned_char => Some(INT_CHAR),
+                    gimli::DW_ATE_boolean => Some(INT_BOOL),
+                    gimli::DW_ATE_unsigned | gimli::DW_ATE_UTF | gimli::DW_ATE_address => Some(0),
+                    _ => None
+                };
+                match encoding {
+                    // BTF ints are 1 to 16 bytes
+                    Some(encoding) if matches!(t.size, 1 | 2 | 4 | 8 | 16) && bits <= t.size as u32 * 8 => {
+                        self.push_type(&mut out, name, KIND_INT, 0, false, t.size as u32);
+                        self.push_u32(&mut out, (encoding << 24) | bits);
+                    }
+                    _ if t.encoding == gimli::DW_ATE_float && matches!(t.size, 2 | 4 | 8 | 12 | 16) => {
+                        self.push_type(&mut out, name, KIND_FLOAT, 0, false, t.size as u32);
+                    }
+                    _ => self.placeholder(&mut out, t.size)?
+                }
+            }
+            Type::Pointer(Pointer { ref meta, .. }) |
+            Type::Reference(Reference { ref meta, .. }) |
+            Type::RvalueReference(RvalueReference { ref meta, .. }) => {
+                let target = self.target_id(meta)?;
+                self.push_type(&mut out, 0, KIND_PTR, 0, false, target);
+            }
+            Type::Typedef(t) => {
+                let name = self.string(&t.name);
+                let target = self.target_id(&t.meta)?;
+                self.push_type(&mut out, name, KIND_TYPEDEF, 0, false, target);
+            }
+            Type::Const(t) => {
+                let target = self.target_id(&t.meta)?;
+                self.push_type(&mut out, 0, KIND_CONST, 0, false, target);
+            }
+            Type::Unknown(t) if t.tag == gimli::DW_TAG_volatile_type || t.tag == gimli::DW_TAG_restrict_type => {
+                let kind = if t.tag == gimli::DW_TAG_volatile_type { KIND_VOLATILE } else { KIND_RESTRICT };
+                let target = self.target_id(&t.meta)?;
+                self.push_type(&mut out, 0, kind, 0, false, target);
+            }
+            Type::Array(t) => {
+                let element = self.type_id(&t.element, 0)?;
+                let index = self.index_id();
+                self.push_type(&mut out, 0, KIND_ARRAY, 0, false, 0);
+                self.push_u32(&mut out, element);
+                self.push_u32(&mut out, index);
+                self.push_u32(&mut out, t.element_count as u32);
+            }
+            Type::Subroutine(t) => {
+                let ret = match t.return_type(self.parser)? {
+                    Some(ret) => self.type_id(&ret, 0)?,
+                    None => 0
+                };
+                let mut params = Vec::new();
+                for param in t.params(self.parser)? {
+                    params.push(self.type_id(&param, 0)?);
+                }
+                // a trailing parameter of type void marks varargs
+                if t.is_variadic(self.parser)? {
+                    params.push(0);
+                }
+                self.push_type(&mut out, 0, KIND_FUNC_PROTO, params.len() as u32, false, ret);
+                for param in params {
+                    self.push_u32(&mut out, 0);
+                    self.push_u32(&mut out, param);
+                }
+            }
+            _ => {
+                let size = type_inst.size(self.parser)?;
+                self.placeholder(&mut out, size)?
+            }
+        }
+        Ok(out)
+    }
+
+    // struct or union with its members, bitfields need kind_flag set
+    fn aggregate(&mut self, out: &mut Vec<u8>, name: u32, kind: u32, size: u64, members: Vec<StructMember>) -> Result<(), Error> {
+        let mut encoded = Vec::new();
+        for member in members {
+            // BTF has no zero-width bitfields, the offsets already reflect them
+            if member.is_zero_width_bitfield() {
+                continue;
+            }
+            let mb_type = match &member.mb_type {
+                Some(mb_type) => mb_type,
+                None => continue
+            };
+            let member_name = match &member.name {
+                Some(member_name) => self.string(member_name),
+                None => 0
+            };
+            let type_id = self.type_id(mb_type, 0)?;
+            encoded.push((member_name, type_id, member.bit_position(), member.bit_size));
+        }
+        let bitfields = encoded.iter().any(|(_, _, _, bit_size)| bit_size.is_some());
+        self.push_type(out, name, kind, encoded.len() as u32, bitfields, size as u32);
+        for (member_name, type_id, bit_position, bit_size) in encoded {
+            self.push_u32(out, member_name);
+            self.push_u32(out, type_id);
+            match bit_size {
+                Some(bit_size) => self.push_u32(out, ((bit_size as u32) << 24) | (bit_position as u32 & 0xff_ffff)),
+                None => self.push_u32(out, bit_position as u32)
+            }
+        }
+        Ok(())
+    }
+
+    // the type a pointer, typedef or qualifier refers to
+    fn target_id(&mut self, meta: &DwTypeMeta) -> Result<u32, Error> {
+        match self.parser.resolve_meta(meta)? {
+            Some(target) => self.type_id(&target, 0),
+            None => Ok(0)
+        }
+    }
+
+    // `unsigned char[size]` standing in for a type BTF can't describe
+    fn placeholder(&mut self, out: &mut Vec<u8>, size: u64) -> Result<(), Error> {
+        let byte = match self.byte_id {
+            Some(byte) => byte,
+            None => {
+                let byte = self.new_id();
+                let name = self.string("unsigned char");
+                let mut encoded = Vec::new();
+                self.push_type(&mut encoded, name, KIND_INT, 0, false, 1);
+                self.push_u32(&mut encoded, (INT_CHAR << 24) | 8);
+                self.types[byte as usize - 1] = encoded;
+                self.byte_id = Some(byte);
+                byte
+            }
+        };
+        let index = self.index_id();
+        self.push_type(out, 0, KIND_ARRAY, 0, false, 0);
+        self.push_u32(out, byte);
+        self.push_u32(out, index);
+        self.push_u32(out, size as u32);
+        Ok(())
+    }
+
+    // the int arrays are indexed by, named like pahole does
+    fn index_id(&mut self) -> u32 {
+        if let Some(index) = self.index_id {
+            return index;
+        }
+        let index = self.new_id();
+        let name = self.string("__ARRAY_SIZE_TYPE__");
+        let mut encoded = Vec::new();
+        self.push_type(&mut encoded, name, KIND_INT, 0, false, 4);
+        self.push_u32(&mut encoded, 32);
+        self.types[index as usize - 1] = encoded;
+        self.index_id = Some(index);
+        index
+    }
+
+    fn string(&mut self, name: &str) -> u32 {
+        if name.is_empty() {
+            return 0;
+        }
+        if let Some(offset) = self.string_offsets.get(name) {
+            return *offset;
+        }
+        let offset = self.strings.len() as u32;
+        self.strings.extend_from_slice(name.as_bytes());
+        self.strings.push(0);
+        self.string_offsets.insert(name.to_string(), offset);
+        offset
+    }
+
+    // struct btf_type: name_off, info and size or type
+    fn push_type(&self, out: &mut Vec<u8>, name: u32, kind: u32, vlen: u32, kind_flag: bool, size_or_type: u32) {
+        let info = ((kind_flag as u32) << 31) | (kind << 24) | (vlen & 0xffff);
+        self.push_u32(out, name);
+        self.push_u32(out, info);
+        self.push_u32(out, size_or_type);
+    }
+
+    fn push_u32(&self, out: &mut Vec<u8>, value: u32) {
+        match self.endian {
+            gimli::RunTimeEndian::Little => out.extend_from_slice(&value.to_le_bytes()),
+            gimli::RunTimeEndian::Big => out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+
+    fn push_u16(&self, out: &mut Vec<u8>, value: u16) {
+        match self.endian {
+            gimli::RunTimeEndian::Little => out.extend_from_slice(&value.to_le_bytes()),
+            gimli::RunTimeEndian::Big => out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+}
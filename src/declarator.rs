@@ -0,0 +1,88 @@
+// structured breakdown of a member's type, see StructMember::declarator
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{canonical_type_name, Error, Parser, StructMember, Type};
+
+/// A type qualifier found between the member and its base type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Qualifier {
+    Const,
+    Volatile,
+    Restrict,
+    Atomic,
+}
+
+/// The parts of a member declaration, e.g. `const char *argv[4]` gives
+/// `base: "char"`, `pointer_levels: 1`, `array_dims: [4]` and
+/// `qualifiers: [Const]`.
+///
+/// Qualifiers are listed outermost first without recording which pointer
+/// level they apply to. Typedefs, structs, unions, enums and function types
+/// end the walk and make up the `base`, named as by `canonical_type_name`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Declarator {
+    pub base: String,
+    pub pointer_levels: u8,
+    /// Element counts from the outermost dimension in, 0 for flexible arrays.
+    pub array_dims: Vec<u64>,
+    pub qualifiers: Vec<Qualifier>,
+}
+
+impl StructMember {
+    /// Break the member's type down into its base type, pointers, array
+    /// dimensions and qualifiers, for consumers that build their own output.
+    pub fn declarator(&self, parser: &Parser) -> Result<Declarator, Error> {
+        let mut declarator = Declarator {
+            base: String::from("void"),
+            pointer_levels: 0,
+            array_dims: Vec::new(),
+            qualifiers: Vec::new(),
+        };
+        if let Some(mb_type) = &self.mb_type {
+            walk(parser, mb_type, &mut declarator, 0)?;
+        }
+        Ok(declarator)
+    }
+}
+
+fn walk(parser: &Parser, type_inst: &Type, declarator: &mut Declarator, depth: usize) -> Result<(), Error> {
+    if depth > parser.max_depth {
+        return Err(gimli::Error::TypeMismatch.into()) // FIXME
+    }
+    let (qualifier, meta) = match type_inst {
+        Type::Array(t) => {
+            declarator.array_dims.push(t.element_count);
+            return walk(parser, &t.element, declarator, depth + 1);
+        }
+        Type::Pointer(t) => {
+            declarator.pointer_levels = declarator.pointer_levels.saturating_add(1);
+            (None, &t.meta)
+        }
+        Type::Const(t) => (Some(Qualifier::Const), &t.meta),
+        Type::Unknown(t) => match t.tag {
+            gimli::DW_TAG_volatile_type => (Some(Qualifier::Volatile), &t.meta),
+            gimli::DW_TAG_restrict_type => (Some(Qualifier::Restrict), &t.meta),
+            gimli::DW_TAG_atomic_type => (Some(Qualifier::Atomic), &t.meta),
+            _ => {
+                declarator.base = canonical_type_name(type_inst, parser);
+                return Ok(());
+            }
+        },
+        _ => {
+            declarator.base = canonical_type_name(type_inst, parser);
+            return Ok(());
+        }
+    };
+    if let Some(qualifier) = qualifier {
+        declarator.qualifiers.push(qualifier);
+    }
+    match parser.resolve_meta(meta)? {
+        Some(inner) => walk(parser, &inner, declarator, depth + 1),
+        None => {
+            declarator.base = String::from("void");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,141 @@
+// structs with the same layout under different names, see
+// Parser::structural_duplicates
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{canonical_type_name, Const, DwTypeMeta, Error, Map, Parser, Type, Typedef};
+
+impl Parser {
+    /// Groups of structs in `struct_dict` that have the same size and the
+    /// same members at the same offsets, ignoring the names of the structs
+    /// and of their members. Each group is sorted and has at least two
+    /// names, structs without members are left out.
+    ///
+    /// Members are compared by their resolved type: typedefs are looked
+    /// through and structs and unions held by value are compared member by
+    /// member. Pointers to the struct being compared, or to one containing
+    /// it, match by nesting level so that e.g. two list nodes with a `next`
+    /// pointer to their own type are grouped; other pointers compare the
+    /// name of what they point to.
+    pub fn structural_duplicates(&self) -> Result<Vec<Vec<String>>, Error> {
+        let mut groups: Map<String, Vec<String>> = Map::new();
+        for (name, dw_struct) in &self.struct_dict {
+            let mut signer = Signer { parser: self, visiting: Vec::new() };
+            let signature = match signer.aggregate(&dw_struct.meta, &dw_struct.name, dw_struct.size, 0)? {
+                Some(signature) => signature,
+                None => continue
+            };
+            groups.entry(signature).or_default().push(name.clone());
+        }
+        let mut duplicates: Vec<Vec<String>> = groups.into_values().filter(|names| names.len() > 1).collect();
+        for names in duplicates.iter_mut() {
+            names.sort();
+        }
+        duplicates.sort();
+        Ok(duplicates)
+    }
+}
+
+struct Signer<'a> {
+    parser: &'a Parser,
+    // the aggregates being signed, outermost first, to recognize pointers
+    // back into them
+    visiting: Vec<(DwTypeMeta, String)>,
+}
+
+impl Signer<'_> {
+    // `size{offset:bits:type;...}`, None for aggregates without members
+    fn aggregate(&mut self, meta: &DwTypeMeta, name: &str, size: u64, depth: usize) -> Result<Option<String>, Error> {
+        let mut members = self.parser.members(meta)?;
+        members.retain(|member| !member.is_static);
+        if members.is_empty() {
+            return Ok(None);
+        }
+        members.sort_by_key(|member| (member.offset, member.bit_position()));
+        self.visiting.push((meta.clone(), name.to_string()));
+        let mut signature = format!("{}{{", size);
+        for member in members {
+            let type_signature = match &member.mb_type {
+                Some(mb_type) => self.type_signature(mb_type, depth + 1)?,
+                None => String::from("?")
+            };
+            let bits = match member.bit_size {
+                Some(bit_size) => format!("{}+{}", member.bit_position(), bit_size),
+                None => String::new()
+            };
+            signature.push_str(&format!("{}:{}:{};", member.offset, bits, type_signature));
+        }
+        signature.push('}');
+        self.visiting.pop();
+        Ok(Some(signature))
+    }
+
+    fn type_signature(&mut self, type_inst: &Type, depth: usize) -> Result<String, Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let signature = match type_inst {
+            Type::Struct(t) => {
+                let definition = self.parser.struct_definition(t);
+                match self.aggregate(&definition.meta, &t.name, definition.size, depth + 1)? {
+                    Some(signature) => format!("struct {}", signature),
+                    None => canonical_type_name(type_inst, self.parser)
+                }
+            }
+            Type::Union(t) => match self.aggregate(&t.meta, "", t.size, depth + 1)? {
+                Some(signature) => format!("union {}", signature),
+                None => canonical_type_name(type_inst, self.parser)
+            },
+            Type::Typedef(Typedef { meta, .. }) | Type::Const(Const { meta, .. }) => match self.parser.resolve_meta(meta)? {
+                Some(inner) => {
+                    let inner = self.type_signature(&inner, depth + 1)?;
+                    if matches!(type_inst, Type::Const(_)) { format!("const {}", inner) } else { inner }
+                }
+                None => String::from("void")
+            },
+            Type::Array(t) => format!("{}[{}]", self.type_signature(&t.element, depth + 1)?, t.element_count),
+            Type::Pointer(t) => match self.parser.resolve_meta(&t.meta)? {
+                Some(target) => format!("{}*", self.pointee_signature(&target, depth + 1)?),
+                None => String::from("void*")
+            },
+            _ => canonical_type_name(type_inst, self.parser)
+        };
+        Ok(signature)
+    }
+
+    // pointers aren't followed into other structs, which could pull in the
+    // whole type graph, except to tell whether they point back into one of
+    // the aggregates being signed
+    fn pointee_signature(&mut self, target: &Type, depth: usize) -> Result<String, Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        match target {
+            Type::Struct(t) => {
+                let level = self.visiting.iter().position(|(meta, name)| {
+                    *meta == t.meta || (!t.name.is_empty() && *name == t.name)
+                });
+                if let Some(level) = level {
+                    return Ok(format!("<enclosing {}>", level));
+                }
+            }
+            Type::Typedef(Typedef { meta, .. }) | Type::Const(Const { meta, .. }) => {
+                if let Some(inner) = self.parser.resolve_meta(meta)? {
+                    let inner = self.pointee_signature(&inner, depth + 1)?;
+                    return Ok(if matches!(target, Type::Const(_)) { format!("const {}", inner) } else { inner });
+                }
+                return Ok(String::from("void"));
+            }
+            Type::Pointer(t) => {
+                return match self.parser.resolve_meta(&t.meta)? {
+                    Some(inner) => Ok(format!("{}*", self.pointee_signature(&inner, depth + 1)?)),
+                    None => Ok(String::from("void*"))
+                };
+            }
+            _ => {}
+        }
+        Ok(canonical_type_name(target, self.parser))
+    }
+}
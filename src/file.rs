@@ -0,0 +1,156 @@
+// reading object files from disk or memory, needs the `std-object` feature
+
+use std::borrow::Cow;
+use std::format;
+use std::fs::File;
+use std::path::Path;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use memmap2::Mmap;
+use object::{Object, ObjectSection};
+
+use crate::accel::NameIndexes;
+use crate::{Error, Parser, R};
+
+impl Parser {
+    pub fn new(file: File) -> Result<Parser, Error> {
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_bytes(&map)
+    }
+
+    /// Like `new`, but for looking up a few structs by name: instead of
+    /// indexing every struct with `load_structs`, `get_struct` searches the
+    /// dwarf for the struct asked for, stopping at its first definition.
+    pub fn new_lazy(file: File) -> Result<Parser, Error> {
+        let mut parser = Self::new(file)?;
+        parser.lazy = true;
+        Ok(parser)
+    }
+
+    /// Parse an object file that is already in memory.
+    ///
+    /// Malformed input is reported as an error rather than a panic, this is
+    /// the entry point used by the fuzz targets.
+    pub fn from_bytes(data: &[u8]) -> Result<Parser, Error> {
+        let (sections, names, endian) = Self::load_object(data)?;
+        Self::from_sections(sections, names, endian)
+    }
+
+    fn load_object(data: &[u8]) -> Result<(gimli::Dwarf<R>, NameIndexes, gimli::RunTimeEndian), Error> {
+        let object = object::File::parse(data)?;
+        let endian = if object.is_little_endian() {
+            gimli::RunTimeEndian::Little
+        } else {
+            gimli::RunTimeEndian::Big
+        };
+        let sections = Self::load_sections(&object, endian)?;
+        let names = NameIndexes {
+            debug_names: section_data(&object, ".debug_names", endian),
+            debug_pubtypes: section_data(&object, ".debug_pubtypes", endian),
+        };
+        Ok((sections, names, endian))
+    }
+
+    /// Parse an object file that may be compressed as a whole with xz, zstd
+    /// or gzip, e.g. a packaged `vmlinux.xz`. Each format needs its feature
+    /// (`xz`, `zstd`, `gzip`), uncompressed files are always accepted.
+    pub fn from_compressed(path: &Path) -> Result<Parser, Error> {
+        let data = std::fs::read(path)?;
+        let data = decompress(data)?;
+        Self::from_bytes(&data)
+    }
+
+    /// Add the dwarf of another object file, e.g. a shared library the
+    /// first file links against, so types spanning both can be resolved.
+    ///
+    /// The new units are numbered after the existing ones. Structs are not
+    /// loaded, call `load_structs` again afterwards, it only walks the new
+    /// units; a struct defined in several objects is counted in its `refcnt`
+    /// like one defined in several units. DIE offsets are only unique per
+    /// object.
+    pub fn add_object(&mut self, file: File) -> Result<(), Error> {
+        let map = unsafe { Mmap::map(&file)? };
+        let (sections, names, endian) = Self::load_object(&map)?;
+        self.add_sections(sections, names, endian)
+    }
+
+    fn load_sections(object: &object::File, endian: gimli::RunTimeEndian) -> Result<gimli::Dwarf<R>, Error> {
+        // src: https://github.com/tchajed/rdb/blob/main/src/dwarf.rs#L252
+
+        let load_section = |id: gimli::SectionId| -> Result<R, gimli::Error> {
+            Ok(section_data(object, id.name(), endian))
+        };
+
+        Ok(gimli::Dwarf::load(&load_section)?)
+    }
+}
+
+// missing sections are empty
+fn section_data(object: &object::File, name: &str, endian: gimli::RunTimeEndian) -> R {
+    let data = object
+        .section_by_name(name)
+        .and_then(|section| section.uncompressed_data().ok())
+        .unwrap_or(Cow::Borrowed(&[][..]));
+    R::new(Rc::from(&*data), endian)
+}
+
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+// undo whole-file compression detected by its magic, anything else is
+// returned as is
+fn decompress(data: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if data.starts_with(XZ_MAGIC) {
+        return decompress_xz(&data);
+    }
+    if data.starts_with(ZSTD_MAGIC) {
+        return decompress_zstd(&data);
+    }
+    if data.starts_with(GZIP_MAGIC) {
+        return decompress_gzip(&data);
+    }
+    Ok(data)
+}
+
+fn unsupported_compression(feature: &str) -> Error {
+    let msg = format!("input is compressed, enable the `{}` feature to read it", feature);
+    Error::Io(std::io::Error::new(std::io::ErrorKind::Unsupported, msg))
+}
+
+#[cfg(feature = "xz")]
+fn decompress_xz(data: &[u8]) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+    let mut out = Vec::new();
+    xz2::read::XzDecoder::new(data).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "xz"))]
+fn decompress_xz(_data: &[u8]) -> Result<Vec<u8>, Error> {
+    Err(unsupported_compression("xz"))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(zstd::stream::decode_all(data)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8]) -> Result<Vec<u8>, Error> {
+    Err(unsupported_compression("zstd"))
+}
+
+#[cfg(feature = "gzip")]
+fn decompress_gzip(data: &[u8]) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+    let mut out = Vec::new();
+    flate2::read::MultiGzDecoder::new(data).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decompress_gzip(_data: &[u8]) -> Result<Vec<u8>, Error> {
+    Err(unsupported_compression("gzip"))
+}
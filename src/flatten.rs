@@ -0,0 +1,273 @@
+// scalar leaf fields of a struct, see Struct::flatten_fields
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{is_qualifier, DwTypeMeta, Error, Parser, Set, Struct, StructMember, Type};
+
+/// How `Struct::flatten_fields_with` treats arrays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArrayFields {
+    /// One field per element, `buf[0]` to `buf[N-1]`.
+    Expand,
+    /// The fields of the first element only, `buf[]`, with `count` set to
+    /// the number of elements.
+    Collapse,
+}
+
+/// Options for `Struct::flatten_fields_with`.
+#[derive(Clone, Debug)]
+pub struct FlattenOptions {
+    arrays: ArrayFields,
+    follow_pointers_depth: u8,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> FlattenOptions {
+        FlattenOptions {
+            arrays: ArrayFields::Expand,
+            follow_pointers_depth: 0,
+        }
+    }
+}
+
+impl FlattenOptions {
+    pub fn new() -> FlattenOptions {
+        FlattenOptions::default()
+    }
+
+    /// How arrays are flattened, `ArrayFields::Expand` by default.
+    pub fn arrays(mut self, arrays: ArrayFields) -> FlattenOptions {
+        self.arrays = arrays;
+        self
+    }
+
+    /// How many pointers deep to also flatten the struct or union a pointer
+    /// points to, e.g. 1 lists `next->prev` after `next`. 0, the default,
+    /// keeps pointers as leaves. A struct is never expanded again through a
+    /// pointer inside its own expansion, so self-referential pointers stop
+    /// after one level whatever the depth.
+    pub fn follow_pointers_depth(mut self, follow_pointers_depth: u8) -> FlattenOptions {
+        self.follow_pointers_depth = follow_pointers_depth;
+        self
+    }
+}
+
+/// A scalar reachable from a struct, by default without following pointers.
+#[derive(Clone, Debug)]
+pub struct FlatField {
+    /// Dotted path from the struct, e.g. `hdr.flags` or `slots[3].ptr`,
+    /// with `->` for followed pointers as in `next->prev`. Members of
+    /// anonymous structs and unions are reached without a component for
+    /// the anonymous member, as in C.
+    pub path: String,
+    /// Byte offset from the start of the struct, or from the start of the
+    /// pointed to object when `pointer_depth` is not 0.
+    pub offset: u64,
+    pub size: u64,
+    /// Encoding of the scalar, `DW_ATE_address` for pointers, `None` for
+    /// types without one such as function types.
+    pub base_encoding: Option<gimli::DwAte>,
+    /// Width and bit position from the start of the struct for bitfields.
+    pub bit_size: Option<u64>,
+    pub bit_position: Option<u64>,
+    /// Number of repetitions `stride` bytes apart, 1 unless arrays are
+    /// collapsed. Nested collapsed arrays multiply their counts and keep the
+    /// innermost stride.
+    pub count: u64,
+    pub stride: u64,
+    /// Number of pointers followed to reach the field, see
+    /// `FlattenOptions::follow_pointers_depth`.
+    pub pointer_depth: u8,
+}
+
+impl Struct {
+    /// Every scalar leaf of the struct, expanding nested structs, unions and
+    /// arrays. Pointers are leaves and are not followed, see
+    /// `FlattenOptions::follow_pointers_depth` to follow them.
+    pub fn flatten_fields(&self, parser: &Parser) -> Result<Vec<FlatField>, Error> {
+        self.flatten_fields_with(parser, &FlattenOptions::default())
+    }
+
+    pub fn flatten_fields_with(&self, parser: &Parser, options: &FlattenOptions) -> Result<Vec<FlatField>, Error> {
+        let mut flattener = Flattener {
+            parser,
+            arrays: options.arrays,
+            follow_pointers_depth: options.follow_pointers_depth,
+            pointer_depth: 0,
+            visited: Set::new(),
+            fields: Vec::new()
+        };
+        flattener.aggregate(&self.meta, "", 0, 1, 0, 0)?;
+        Ok(flattener.fields)
+    }
+}
+
+struct Flattener<'a> {
+    parser: &'a Parser,
+    arrays: ArrayFields,
+    follow_pointers_depth: u8,
+    pointer_depth: u8,
+    // aggregates being expanded through a pointer on the way to the current
+    // field, following a pointer into one of them again would loop
+    visited: Set<DwTypeMeta>,
+    fields: Vec<FlatField>,
+}
+
+impl Flattener<'_> {
+    fn aggregate(&mut self, meta: &DwTypeMeta, path: &str, offset: u64, count: u64, stride: u64, depth: usize) -> Result<(), Error> {
+        for member in self.parser.members(meta)? {
+            if member.is_static {
+                continue;
+            }
+            self.member(member, path, offset, count, stride, depth)?;
+        }
+        Ok(())
+    }
+
+    fn member(&mut self, member: StructMember, path: &str, offset: u64, count: u64, stride: u64, depth: usize) -> Result<(), Error> {
+        let member_offset = offset.saturating_add(member.offset);
+        let bit_position = offset.saturating_mul(8).saturating_add(member.bit_position());
+        let mb_type = match member.mb_type {
+            Some(mb_type) => mb_type,
+            None => return Ok(())
+        };
+        if let Some(bit_size) = member.bit_size {
+            // unnamed bitfields are padding
+            let name = match member.name {
+                Some(name) => name,
+                None => return Ok(())
+            };
+            let base_encoding = self.parser.scalar_encoding(&mb_type, depth)?;
+            self.fields.push(FlatField {
+                path: join(path, &name),
+                offset: member_offset,
+                size: member.size,
+                base_encoding,
+                bit_size: Some(bit_size),
+                bit_position: Some(bit_position),
+                count,
+                stride: if count == 1 { member.size } else { stride },
+                pointer_depth: self.pointer_depth,
+            });
+            return Ok(());
+        }
+        let path = match &member.name {
+            Some(name) => join(path, name),
+            None => path.to_string()
+        };
+        self.value(mb_type, &path, member_offset, count, stride, depth)
+    }
+
+    fn value(&mut self, type_inst: Type, path: &str, offset: u64, count: u64, stride: u64, depth: usize) -> Result<(), Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        match type_inst {
+            Type::Struct(ref t) => {
+                let meta = self.parser.struct_definition(t).meta.clone();
+                return self.aggregate(&meta, path, offset, count, stride, depth + 1);
+            }
+            Type::Union(ref t) => return self.aggregate(&t.meta, path, offset, count, stride, depth + 1),
+            Type::Typedef(_) | Type::Const(_) => {
+                if let Some(inner) = self.parser.get_type(type_inst)? {
+                    return self.value(inner, path, offset, count, stride, depth + 1);
+                }
+                return Ok(());
+            }
+            Type::Unknown(ref t) if is_qualifier(t.tag) => {
+                if let Some(inner) = self.parser.resolve_meta(&t.meta)? {
+                    return self.value(inner, path, offset, count, stride, depth + 1);
+                }
+                return Ok(());
+            }
+            Type::Array(t) => {
+                let element_size = t.element.size(self.parser)?;
+                let element_stride = t.stride.unwrap_or(element_size);
+                match self.arrays {
+                    ArrayFields::Expand => {
+                        for idx in 0..t.element_count {
+                            let element = (*t.element).clone();
+                            let element_offset = offset.saturating_add(idx.saturating_mul(element_stride));
+                            self.value(element, &format!("{}[{}]", path, idx), element_offset, count, stride, depth + 1)?;
+                        }
+                    }
+                    ArrayFields::Collapse => {
+                        let count = count.saturating_mul(t.element_count);
+                        self.value(*t.element, &format!("{}[]", path), offset, count, element_stride, depth + 1)?;
+                    }
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+        let size = type_inst.size(self.parser)?;
+        let base_encoding = self.parser.scalar_encoding(&type_inst, depth)?;
+        self.fields.push(FlatField {
+            path: path.to_string(),
+            offset,
+            size,
+            base_encoding,
+            bit_size: None,
+            bit_position: None,
+            count,
+            stride: if count == 1 { size } else { stride },
+            pointer_depth: self.pointer_depth,
+        });
+        if self.pointer_depth < self.follow_pointers_depth {
+            self.follow(&type_inst, path, depth)?;
+        }
+        Ok(())
+    }
+
+    // the fields of the aggregate a pointer points to, after the pointer
+    fn follow(&mut self, type_inst: &Type, path: &str, depth: usize) -> Result<(), Error> {
+        let meta = match type_inst {
+            Type::Pointer(t) => &t.meta,
+            Type::Reference(t) => &t.meta,
+            Type::RvalueReference(t) => &t.meta,
+            _ => return Ok(())
+        };
+        let meta = match self.parser.resolve_meta(meta)? {
+            Some(target) => self.pointee(target, depth + 1)?,
+            None => None
+        };
+        let meta = match meta {
+            Some(meta) if !self.visited.contains(&meta) => meta,
+            _ => return Ok(())
+        };
+        self.visited.insert(meta.clone());
+        self.pointer_depth += 1;
+        let result = self.aggregate(&meta, &format!("{}->", path), 0, 1, 0, depth + 1);
+        self.pointer_depth -= 1;
+        self.visited.remove(&meta);
+        result
+    }
+
+    // the struct or union behind typedefs and qualifiers
+    fn pointee(&self, type_inst: Type, depth: usize) -> Result<Option<DwTypeMeta>, Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let meta = match type_inst {
+            Type::Struct(t) => return Ok(Some(self.parser.struct_definition(&t).meta.clone())),
+            Type::Union(t) => return Ok(Some(t.meta)),
+            Type::Typedef(t) => t.meta,
+            Type::Const(t) => t.meta,
+            Type::Unknown(t) if is_qualifier(t.tag) => t.meta,
+            _ => return Ok(None)
+        };
+        match self.parser.resolve_meta(&meta)? {
+            Some(inner) => self.pointee(inner, depth + 1),
+            None => Ok(None)
+        }
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() || path.ends_with("->") {
+        return format!("{}{}", path, name);
+    }
+    format!("{}.{}", path, name)
+}
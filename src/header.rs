@@ -0,0 +1,285 @@
+// standalone C headers for a set of structs, see Parser::emit_header
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::render::{enum_body, get_member_string, member_decl, FlexibleArrayStyle, RenderOptions};
+use crate::{canonical_type_name, is_qualifier, DwTypeMeta, Error, Parser, Set, Struct, Type};
+
+struct HeaderWriter<'a> {
+    parser: &'a Parser,
+    options: RenderOptions,
+    // canonical names of the types already defined or being defined
+    done: Set<String>,
+    visiting: Set<String>,
+    forward: Vec<String>,
+    // types only used through pointers, defined after everything else
+    pending: Vec<Type>,
+    definitions: Vec<String>,
+}
+
+impl Parser {
+    /// Render the structs called `names` together with the structs, unions,
+    /// enums and typedefs they depend on as a standalone C header.
+    ///
+    /// Types are defined before they are used by value, types that are only
+    /// reached through pointers get a forward declaration so recursive types
+    /// work. Names that are not in `struct_dict` are left out with a comment.
+    pub fn emit_header(&self, names: &[&str]) -> Result<String, Error> {
+        let mut writer = HeaderWriter {
+            parser: self,
+            options: RenderOptions::compilable(),
+            done: Set::new(),
+            visiting: Set::new(),
+            forward: Vec::new(),
+            pending: Vec::new(),
+            definitions: Vec::new(),
+        };
+        let mut missing = Vec::new();
+        for name in names {
+            match self.struct_dict.get(*name) {
+                Some(dw_struct) => writer.emit(Type::Struct(dw_struct.clone()), 0)?,
+                None => missing.push(*name)
+            }
+        }
+        while let Some(type_inst) = writer.pending.pop() {
+            writer.emit(type_inst, 0)?;
+        }
+
+        let mut out = String::new();
+        for name in missing {
+            out.push_str(&format!("/* struct {} not found */\n", name));
+        }
+        for forward in &writer.forward {
+            out.push_str(&format!("{};\n", forward));
+        }
+        for definition in &writer.definitions {
+            out.push('\n');
+            out.push_str(definition);
+        }
+        Ok(out)
+    }
+}
+
+impl HeaderWriter<'_> {
+    fn emit(&mut self, type_inst: Type, depth: usize) -> Result<(), Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let key = canonical_type_name(&type_inst, self.parser);
+        if self.done.contains(&key) || self.visiting.contains(&key) {
+            return Ok(());
+        }
+        self.visiting.insert(key.clone());
+
+        let mut deps = Vec::new();
+        let definition = match type_inst {
+            Type::Struct(t) => {
+                let definition = self.parser.struct_dict.get(&t.name).cloned().unwrap_or(t);
+                let members = self.parser.members(&definition.meta)?;
+                if members.is_empty() && definition.declared_size().is_none() {
+                    // only declared, a forward declaration is all we have
+                    self.forward_declare(key.clone());
+                    None
+                } else {
+                    self.members_deps(&definition.meta, &mut deps)?;
+                    let packed = definition.is_packed(self.parser)?;
+                    let attributes = if packed { " __attribute__((packed))" } else { "" };
+                    Some(format!("{} {}{};\n", key, self.struct_body(&definition, packed)?, attributes))
+                }
+            }
+            Type::Union(t) => {
+                self.members_deps(&t.meta, &mut deps)?;
+                if self.parser.members(&t.meta)?.is_empty() && t.size == 0 {
+                    self.forward_declare(key.clone());
+                    None
+                } else {
+                    Some(format!("{} {};\n", key, self.body(&t.meta)?))
+                }
+            }
+            Type::Enum(t) => {
+                Some(format!("{} {};\n", key, enum_body(self.parser, &t.meta)?))
+            }
+            Type::Typedef(t) => {
+                let target = self.parser.resolve_meta(&t.meta)?.unwrap_or(Type::Void);
+                // a typedef can name an incomplete struct or union, anything
+                // else has to be defined first
+                let by_value = match &target {
+                    Type::Struct(s) => s.name.is_empty(),
+                    Type::Union(u) => u.name.is_none(),
+                    _ => true
+                };
+                self.type_deps(target, by_value, &mut deps, 0)?;
+                let target = self.parser.resolve_meta(&t.meta)?.unwrap_or(Type::Void);
+                Some(format!("typedef {};\n", get_member_string(self.parser, target, &t.name, 0, &self.options)?))
+            }
+            _ => None
+        };
+
+        for (dep, by_value) in deps {
+            if by_value {
+                self.emit(dep, depth + 1)?;
+            } else {
+                self.forward_declare(canonical_type_name(&dep, self.parser));
+                self.pending.push(dep);
+            }
+        }
+        if let Some(definition) = definition {
+            self.definitions.push(definition);
+        }
+        self.visiting.remove(&key);
+        self.done.insert(key);
+        Ok(())
+    }
+
+    fn forward_declare(&mut self, key: String) {
+        if !self.forward.contains(&key) {
+            self.forward.push(key);
+        }
+    }
+
+    // like `body`, with unnamed bitfields filling the gaps the compiler
+    // wouldn't leave by itself, such as those of the unnamed bitfields dwarf
+    // doesn't record
+    fn struct_body(&self, definition: &Struct, packed: bool) -> Result<String, Error> {
+        let members: Vec<_> = definition.members_by_offset(self.parser)?.into_iter().filter(|member| !member.is_static).collect();
+        // `[]` has to be last, zero length arrays elsewhere are `[0]`
+        let inner_options = self.options.clone().flexible_array_style(FlexibleArrayStyle::Zero);
+        let mut body = String::from("{\n");
+        let mut end = 0;
+        let mut align = 1;
+        for (idx, member) in members.iter().enumerate() {
+            let start = member.bit_position();
+            let member_align = if packed { 1 } else { member.natural_alignment(self.parser)? };
+            let natural = match member.bit_size {
+                // `int : 0` is placed wherever the next unit starts
+                Some(0) => start,
+                // bitfields don't cross a storage unit boundary unless packed
+                Some(bit_size) => {
+                    let unit = member.size.saturating_mul(8).max(1);
+                    if packed || end / unit == end.saturating_add(bit_size - 1) / unit { end } else { align_up(end, unit) }
+                }
+                None => align_up(end, member_align.saturating_mul(8))
+            };
+            padding(&mut body, natural, start);
+            if !member.is_zero_width_bitfield() {
+                align = align.max(member_align);
+            }
+            end = start.saturating_add(match member.bit_size {
+                Some(bit_size) => bit_size,
+                None => member.size.saturating_mul(8)
+            });
+            let options = if idx + 1 == members.len() { &self.options } else { &inner_options };
+            body.push_str(&format!("\t{}\n", member_decl(self.parser, member.clone(), options)?));
+        }
+        // tail padding beyond what the alignment asks for
+        if definition.size.saturating_mul(8) > align_up(end, align.saturating_mul(8)) {
+            padding(&mut body, end, definition.size.saturating_mul(8));
+        }
+        body.push('}');
+        Ok(body)
+    }
+
+    // `{\n\tint a;\n};` for the members of a struct or union
+    fn body(&self, meta: &DwTypeMeta) -> Result<String, Error> {
+        let mut body = String::from("{\n");
+        for member in self.parser.members(meta)? {
+            if member.is_static {
+                continue;
+            }
+            body.push_str(&format!("\t{}\n", member_decl(self.parser, member, &self.options)?));
+        }
+        body.push('}');
+        Ok(body)
+    }
+
+    fn members_deps(&self, meta: &DwTypeMeta, deps: &mut Vec<(Type, bool)>) -> Result<(), Error> {
+        for member in self.parser.members(meta)? {
+            if member.is_static {
+                continue;
+            }
+            if let Some(mb_type) = member.mb_type {
+                self.type_deps(mb_type, true, deps, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    // named types `type_inst` refers to, and whether they are needed by value
+    // or only through a pointer
+    fn type_deps(&self, type_inst: Type, by_value: bool, deps: &mut Vec<(Type, bool)>, depth: usize) -> Result<(), Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        match type_inst {
+            // anonymous aggregates are rendered inline
+            Type::Struct(ref t) if t.name.is_empty() => self.members_deps(&t.meta, deps)?,
+            Type::Union(ref t) if t.name.is_none() => self.members_deps(&t.meta, deps)?,
+            Type::Enum(ref t) if t.name.is_none() => {}
+            Type::Struct(_) | Type::Union(_) => deps.push((type_inst, by_value)),
+            // typedefs and enums can't be forward declared
+            Type::Enum(_) => deps.push((type_inst, true)),
+            Type::Typedef(ref t) => {
+                // using a typedef by value needs what it names to be complete
+                if by_value {
+                    if let Some(target) = self.parser.resolve_meta(&t.meta)? {
+                        self.type_deps(target, true, deps, depth + 1)?;
+                    }
+                }
+                deps.push((type_inst, true));
+            }
+            Type::Pointer(_) | Type::Reference(_) | Type::RvalueReference(_) => {
+                if let Some(inner) = self.parser.get_type(type_inst)? {
+                    self.type_deps(inner, false, deps, depth + 1)?;
+                }
+            }
+            Type::Const(_) => {
+                if let Some(inner) = self.parser.get_type(type_inst)? {
+                    self.type_deps(inner, by_value, deps, depth + 1)?;
+                }
+            }
+            Type::Unknown(ref t) if is_qualifier(t.tag) => {
+                if let Some(inner) = self.parser.resolve_meta(&t.meta)? {
+                    self.type_deps(inner, by_value, deps, depth + 1)?;
+                }
+            }
+            Type::Array(t) => self.type_deps(*t.element, by_value, deps, depth + 1)?,
+            Type::Subroutine(t) => {
+                if let Some(ret) = t.return_type(self.parser)? {
+                    self.type_deps(ret, false, deps, depth + 1)?;
+                }
+                for param in t.params(self.parser)? {
+                    self.type_deps(param, false, deps, depth + 1)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn align_up(bits: u64, align_bits: u64) -> u64 {
+    bits.div_ceil(align_bits.max(1)).saturating_mul(align_bits.max(1))
+}
+
+// unnamed bitfields covering the bits from `from` up to `to`: the rest of a
+// partly used byte, then the widest aligned units that fit
+fn padding(body: &mut String, from: u64, to: u64) {
+    let mut pos = from;
+    while pos < to {
+        let bits = if !pos.is_multiple_of(8) || to - pos < 8 {
+            (8 - pos % 8).min(to - pos)
+        } else {
+            [64, 32, 16, 8].into_iter().find(|bits| pos.is_multiple_of(*bits) && pos.saturating_add(*bits) <= to).unwrap_or(8)
+        };
+        let unit = match bits {
+            64 => "unsigned long long",
+            32 => "unsigned int",
+            16 => "unsigned short",
+            _ => "unsigned char"
+        };
+        body.push_str(&format!("\t{} : {};\n", unit, bits));
+        pos += bits;
+    }
+}
@@ -0,0 +1,66 @@
+// one JSON object per line for every struct, see Parser::write_jsonl
+
+use std::format;
+use std::io::Write;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::{canonical_type_name, Error, Parser, Struct};
+
+impl Parser {
+    /// Write every struct in `struct_dict` as one JSON object per line,
+    /// sorted by name so the output is deterministic:
+    ///
+    /// `{"name":"foo","size":16,"members":[{"name":"a","offset":0,"size":4,"type":"int"}]}`
+    ///
+    /// Bitfields also get `bit_size` and `bit_position`, anonymous members
+    /// have a `null` name. Static members are left out. Structs are written
+    /// as they are serialized, wrap `w` in a `BufWriter` for large inputs.
+    pub fn write_jsonl<W: Write>(&self, mut w: W) -> Result<(), Error> {
+        let mut names: Vec<&String> = self.struct_dict.keys().collect();
+        names.sort();
+        for name in names {
+            writeln!(w, "{}", self.struct_json(&self.struct_dict[name])?)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    fn struct_json(&self, dw_struct: &Struct) -> Result<String, Error> {
+        let mut members = Vec::new();
+        for member in dw_struct.members_by_offset(self)? {
+            let name = match &member.name {
+                Some(name) => json_string(name),
+                None => String::from("null")
+            };
+            let type_name = match &member.mb_type {
+                Some(mb_type) => json_string(&canonical_type_name(mb_type, self)),
+                None => String::from("null")
+            };
+            let mut json = format!("{{\"name\":{},\"offset\":{},\"size\":{},\"type\":{}", name, member.offset, member.size, type_name);
+            if let Some(bit_size) = member.bit_size {
+                json.push_str(&format!(",\"bit_size\":{},\"bit_position\":{}", bit_size, member.bit_position()));
+            }
+            json.push('}');
+            members.push(json);
+        }
+        Ok(format!("{{\"name\":{},\"size\":{},\"members\":[{}]}}", json_string(&dw_struct.name), dw_struct.size, members.join(",")))
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
+}
@@ -1,26 +1,176 @@
 // #![deny(missing_docs)]
+#![no_std]
 #![allow(dead_code)]
+#![allow(clippy::needless_return, clippy::single_match, clippy::let_and_return)]
 
-use std::{borrow::Cow, rc::Rc, fs::File};
-use std::collections::HashMap;
+// the parsing itself only needs `alloc`, `std` adds error and io
+// conveniences and `std-object` reading object files
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use core::hash::{Hash, Hasher};
 
-use fallible_iterator::FallibleIterator;
-use object::{Object, ObjectSection};
-use memmap2::Mmap;
 use gimli::{Reader, UnitOffset};
 
-type R = gimli::EndianRcSlice<gimli::LittleEndian>;
+use accel::NameIndexes;
 
-#[derive(Clone, Debug)]
+// hash maps with std, ordered maps in `no_std` builds where there is no
+// random source to seed a hasher
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{hash_map::Entry, HashMap as Map, HashSet as Set};
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::{btree_map::Entry, BTreeMap as Map, BTreeSet as Set};
+
+mod accel;
+mod btf;
+mod dedup;
+mod declarator;
+#[cfg(feature = "std-object")]
+mod file;
+mod flatten;
+mod header;
+#[cfg(feature = "std")]
+mod jsonl;
+#[cfg(feature = "std")]
+mod memory;
+mod render;
+mod rust;
+mod value;
+pub use declarator::{Declarator, Qualifier};
+pub use flatten::{ArrayFields, FlatField, FlattenOptions};
+#[cfg(feature = "std")]
+pub use memory::{MemoryReader, SectionLayout};
+pub use render::{FlexibleArrayStyle, RenderOptions, canonical_type_name};
+pub use value::{StructValue, Value};
+
+type R = gimli::EndianRcSlice<gimli::RunTimeEndian>;
+
+/// Default bound on how many typedef/qualifier/array links are followed when
+/// resolving or rendering a type, see `Parser::set_max_depth` and
+/// `RenderOptions::max_depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Errors returned by the parser.
+#[derive(Debug)]
+pub enum Error {
+    /// Malformed or unsupported dwarf data.
+    Gimli(gimli::Error),
+    /// The input file could not be read.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The input is not an object file `object` understands.
+    #[cfg(feature = "std-object")]
+    Object(object::Error),
+    /// The input has no compilation units, e.g. a stripped binary.
+    NoDebugInfo,
+    /// A member location that can't be reduced to a constant offset, e.g. a
+    /// location list with several entries. Holds the member's DIE offset.
+    UnsupportedLocation(u64),
+    /// A byte buffer is too short for the value it should hold, which needs
+    /// this many bytes.
+    BufferTooSmall(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Gimli(err) => write!(f, "dwarf error: {}", err),
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "io error: {}", err),
+            #[cfg(feature = "std-object")]
+            Error::Object(err) => write!(f, "object error: {}", err),
+            Error::NoDebugInfo => write!(f, "no debug info found"),
+            Error::UnsupportedLocation(offset) => write!(f, "unsupported member location at DIE {:#x}", offset),
+            Error::BufferTooSmall(size) => write!(f, "buffer too small, {} bytes needed", size),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Gimli(err) => Some(err),
+            Error::Io(err) => Some(err),
+            #[cfg(feature = "std-object")]
+            Error::Object(err) => Some(err),
+            Error::NoDebugInfo | Error::UnsupportedLocation(_) | Error::BufferTooSmall(_) => None,
+        }
+    }
+}
+
+impl From<gimli::Error> for Error {
+    fn from(err: gimli::Error) -> Error {
+        Error::Gimli(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+#[cfg(feature = "std-object")]
+impl From<object::Error> for Error {
+    fn from(err: object::Error) -> Error {
+        Error::Object(err)
+    }
+}
+
+// toolchain that produced a unit, used to pick between equivalent encodings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Producer {
+    Gcc,
+    Llvm,
+    Other
+}
+
+impl Producer {
+    fn from_name(producer: &str) -> Producer {
+        if producer.contains("clang") || producer.contains("rustc") {
+            return Producer::Llvm;
+        }
+        if producer.starts_with("GNU") {
+            return Producer::Gcc;
+        }
+        Producer::Other
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct DwTypeMeta {
     offset: gimli::UnitOffset,
     header_idx: usize,
+    // section offset of the unit header, only used to report DIE offsets
+    unit_offset: usize,
+}
+
+impl DwTypeMeta {
+    fn die_offset(&self) -> u64 {
+        (self.unit_offset + self.offset.0) as u64
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Struct {
+    /// Empty for anonymous structs.
     pub name: String,
     pub size: u64,
+    // DW_AT_byte_size, absent for declarations
+    declared_size: Option<u64>,
+    // DW_AT_declaration, only indexed with include_declarations
+    declaration: bool,
     meta: DwTypeMeta,
     refcnt: u64
 }
@@ -30,103 +180,380 @@ pub struct StructIter {
     member_idx: usize
 }
 
+#[derive(Clone, Debug)]
 pub struct StructMember {
     pub name: Option<String>,
     pub size: u64,
+    pub offset: u64,
+    pub bit_size: Option<u64>,
     pub mb_type: Option<Type>,
+    /// Static data member, it has no offset and takes no space in the struct.
+    pub is_static: bool,
+    /// `DW_AT_external` is set, the member is visible outside its unit.
+    pub is_external: bool,
+    /// `DW_AT_artificial` is set, the member was added by the compiler,
+    /// e.g. a C++ vtable pointer.
+    pub is_artificial: bool,
+    /// A `DW_TAG_inheritance`, the subobject of a base class. It has no name
+    /// and the base class as its type.
+    pub is_inheritance: bool,
+    /// Where a static data member is stored, from a `DW_OP_addr` location
+    /// on the member or on its definition in the same unit.
+    pub static_address: Option<u64>,
+    bit_position: u64,
     meta: DwTypeMeta
 }
 
+/// A gap between two members, or between the last member and the end of the
+/// struct when `trailing` is set.
+#[derive(Clone, Debug)]
+pub struct Hole {
+    pub offset: u64,
+    pub size: u64,
+    pub trailing: bool,
+    /// The struct is packed, so the gap was not left for alignment and
+    /// reordering members won't close it.
+    pub packed: bool,
+    /// The gap ends at a zero-width bitfield such as `int : 0`, which moves
+    /// the next member to the next storage unit of its type on purpose.
+    pub separator: bool
+}
+
+/// The `DW_TAG_variant_part` of a struct, which is how rust enums are
+/// described: a discriminant member plus one variant per enum arm.
+#[derive(Clone, Debug)]
+pub struct VariantPart {
+    pub discriminant: Option<StructMember>,
+    pub variants: Vec<Variant>
+}
+
+#[derive(Clone, Debug)]
+pub struct Variant {
+    /// Discriminant value selecting this variant, `None` for the default
+    /// variant (e.g. the dataful variant of a niche optimized enum).
+    pub discr_value: Option<u64>,
+    pub members: Vec<StructMember>
+}
+
+/// Options for `Struct::layout_with` and `Struct::holes_with`.
+#[derive(Clone, Debug, Default)]
+pub struct LayoutOptions {
+    merge_adjacent_holes: bool,
+    skip_packed_holes: bool,
+}
+
+impl LayoutOptions {
+    pub fn new() -> LayoutOptions {
+        LayoutOptions::default()
+    }
+
+    /// Report holes that touch, e.g. the two sides of a zero sized member
+    /// placed inside a gap, as one hole. The zero sized members follow the
+    /// merged hole. Off by default so every hole ends at a member boundary.
+    pub fn merge_adjacent_holes(mut self, merge_adjacent_holes: bool) -> LayoutOptions {
+        self.merge_adjacent_holes = merge_adjacent_holes;
+        self
+    }
+
+    /// Leave out the holes of packed structs, see `Struct::is_packed`,
+    /// which are deliberate rather than padding that could be packed away.
+    /// Off by default, the holes are reported with `Hole::packed` set.
+    pub fn skip_packed_holes(mut self, skip_packed_holes: bool) -> LayoutOptions {
+        self.skip_packed_holes = skip_packed_holes;
+        self
+    }
+}
+
+/// One entry of a struct layout, see `Struct::layout`.
+#[derive(Clone, Debug)]
+pub enum LayoutItem {
+    Member(StructMember),
+    Hole(Hole)
+}
+
+/// Inconsistency found by `Struct::validate_layout`.
+#[derive(Clone, Debug)]
+pub enum LayoutWarning {
+    /// The members extend past the declared `DW_AT_byte_size`.
+    SizeMismatch { declared: u64, computed: u64 },
+    /// Two members share bits, `offset` is the byte offset of `second`.
+    Overlap { first: Option<String>, second: Option<String>, offset: u64 },
+}
+
+/// A compilation unit, see `Parser::units`.
+#[derive(Clone, Debug)]
+pub struct UnitInfo {
+    /// Index of the unit, the order units are loaded in.
+    pub header_idx: usize,
+    /// `DW_AT_name`, usually the source file.
+    pub name: Option<String>,
+    pub producer: Option<String>,
+    /// Address range of the unit's code, `high_pc` is exclusive. `None`
+    /// for units without code or with only `DW_AT_ranges`.
+    pub low_pc: Option<u64>,
+    pub high_pc: Option<u64>,
+}
+
+/// Where a struct or member is declared in the source, from
+/// `DW_AT_decl_file`, `DW_AT_decl_line` and `DW_AT_decl_column`. Any of
+/// them can be missing, e.g. gcc writes no column with `-gno-column-info`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeclLocation {
+    /// Path of the file, joined with its directory from the line table.
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+}
+
+impl UnitInfo {
+    /// Whether `address` lies within `low_pc..high_pc`.
+    pub fn contains(&self, address: u64) -> bool {
+        match (self.low_pc, self.high_pc) {
+            (Some(low_pc), Some(high_pc)) => low_pc <= address && address < high_pc,
+            _ => false
+        }
+    }
+}
+
+/// Iterates the members of a struct. A member that fails to parse is
+/// returned as an error and ends the iteration.
 pub struct StructMemberIter<'a> {
     mb_struct: &'a Struct,
     parser: &'a Parser,
-    member_idx: usize
+    member_idx: usize,
+    failed: bool
 }
 
+/// Iterates the members of a union, see `Union::members`. A member that
+/// fails to parse is returned as an error and ends the iteration.
 pub struct StructUnionIter<'a> {
-    dw_struct: &'a Struct,
+    dw_union: &'a Union,
     parser: &'a Parser,
-    member_idx: usize
+    member_idx: usize,
+    failed: bool
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct AnonStruct {
     pub size: u64,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Typedef {
     pub name: String,
     pub size: u64,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Pointer {
     pub size: u64,
+    /// `DW_AT_address_class`, the address space the pointer points into on
+    /// targets with several, e.g. global, shared or private memory of a GPU.
+    /// Its meaning depends on the target.
+    pub address_class: Option<u64>,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+pub struct Reference {
+    pub size: u64,
+    meta: DwTypeMeta
+}
+
+#[derive(Clone, Debug)]
+pub struct RvalueReference {
+    pub size: u64,
+    meta: DwTypeMeta
+}
+
+#[derive(Clone, Debug)]
 pub struct Subroutine {
     pub size: u64,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Array {
-    pub size: u64,
+    /// Number of elements from the subrange bounds, 0 when they are unknown
+    /// as for flexible array members. See `byte_size` for the size in bytes.
+    pub element_count: u64,
+    pub element: Box<Type>,
+    /// Distance between the starts of two elements in bytes when it differs
+    /// from the element size, from `DW_AT_byte_stride` or `DW_AT_bit_stride`.
+    pub stride: Option<u64>,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Union {
+    pub name: Option<String>,
     pub size: u64,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Const {
     pub size: u64,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Base {
     pub name: String,
     pub size: u64,
+    /// `DW_AT_encoding`, e.g. `DW_ATE_signed` or `DW_ATE_float`.
+    pub encoding: gimli::DwAte,
+    /// `DW_AT_bit_size`, the number of bits holding the value when fewer
+    /// than `size` bytes, e.g. a packed integer. This is a property of the
+    /// type, member bitfields use `StructMember::bit_size`.
+    pub bit_size: Option<u64>,
+    /// `DW_AT_alignment`, set for types aligned differently than their
+    /// size, e.g. an over-aligned `long double` or vector type.
+    pub alignment: Option<u64>,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Enum {
     pub name: Option<String>,
     pub size: u64,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+/// The value of an enumerator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EnumValue {
+    Int(i64),
+    /// A value that doesn't fit in an `i64`, e.g. from an `__int128` enum,
+    /// as little endian two's complement bytes.
+    Big(Vec<u8>),
+}
+
+impl EnumValue {
+    fn from_le_bytes(bytes: Vec<u8>, signed: bool) -> EnumValue {
+        // the value fits if everything past the low 8 bytes is sign (or
+        // zero) extension of bit 63
+        let low = bytes.iter().take(8).rev().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+        let low = if bytes.len() < 8 && signed && bytes.last().is_some_and(|byte| byte & 0x80 != 0) {
+            low | (u64::MAX << (bytes.len() * 8))
+        } else {
+            low
+        };
+        let negative = signed && (low as i64) < 0;
+        let extension = if negative { 0xff } else { 0 };
+        let fits = (signed || (low as i64) >= 0) && bytes.iter().skip(8).all(|byte| *byte == extension);
+        if fits {
+            return EnumValue::Int(low as i64);
+        }
+        EnumValue::Big(bytes)
+    }
+
+    // orders values of one enum, negative ones first and those by their two's
+    // complement bits
+    fn order_key(&self, signed: bool) -> (bool, u128) {
+        match self {
+            EnumValue::Int(value) => (*value >= 0, *value as i128 as u128),
+            EnumValue::Big(bytes) => {
+                let negative = signed && bytes.last().is_some_and(|byte| byte & 0x80 != 0);
+                let mut wide = if negative { [0xff; 16] } else { [0; 16] };
+                for (idx, byte) in bytes.iter().take(16).enumerate() {
+                    wide[idx] = *byte;
+                }
+                (!negative, u128::from_le_bytes(wide))
+            }
+        }
+    }
+}
+
+// decimal for Int, hex for Big so C renders it as a valid literal
+impl fmt::Display for EnumValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnumValue::Int(value) => write!(f, "{}", value),
+            EnumValue::Big(bytes) => {
+                let digits: String = bytes.iter().rev().skip_while(|byte| **byte == 0).map(|byte| format!("{:02x}", byte)).collect();
+                write!(f, "0x{}", digits.trim_start_matches('0'))
+            }
+        }
+    }
+}
+
+/// `DW_TAG_unspecified_type`, e.g. C++ `decltype(nullptr)`.
+#[derive(Clone, Debug)]
+pub struct Unspecified {
+    pub name: Option<String>,
+    pub size: u64,
+    meta: DwTypeMeta
+}
+
+/// `DW_TAG_string_type`, the character strings of Fortran and Ada.
+#[derive(Clone, Debug)]
+pub struct FortranString {
+    /// Length in bytes from `DW_AT_byte_size`, `None` when it is only known
+    /// at run time from `DW_AT_string_length`.
+    pub length: Option<u64>,
+    meta: DwTypeMeta
+}
+
+/// A type whose tag is not handled by the parser.
+#[derive(Clone, Debug)]
 pub struct Unknown {
+    /// `DwTag(0)` when the referenced DIE is missing.
+    pub tag: gimli::DwTag,
     meta: DwTypeMeta
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Type {
     Struct(Struct),
     Typedef(Typedef),
     Pointer(Pointer),
+    Reference(Reference),
+    RvalueReference(RvalueReference),
     Subroutine(Subroutine),
     Array(Array),
     Union(Union),
     Const(Const),
     Base(Base),
     Enum(Enum),
+    Unspecified(Unspecified),
+    FortranString(FortranString),
+    /// The target of a pointer, typedef or qualifier without a `DW_AT_type`.
+    Void,
     Unknown(Unknown)
 }
 
 impl Type {
-    fn get_meta(self) -> DwTypeMeta {
+    /// Size in bytes, resolving typedefs and qualifiers to their target and
+    /// multiplying arrays out. Prefer this over the per-variant `size` fields.
+    pub fn size(&self, parser: &Parser) -> Result<u64, Error> {
+        parser.type_size(self, 0)
+    }
+
+    fn meta(&self) -> Option<&DwTypeMeta> {
+        let meta = match self {
+            Type::Base(t) =>       { &t.meta }
+            Type::Array(t) =>      { &t.meta }
+            Type::Enum(t) =>       { &t.meta }
+            Type::Const(t) =>      { &t.meta }
+            Type::Typedef(t) =>    { &t.meta }
+            Type::Struct(t) =>     { &t.meta }
+            Type::Pointer(t) =>    { &t.meta }
+            Type::Reference(t) =>  { &t.meta }
+            Type::RvalueReference(t) => { &t.meta }
+            Type::Union(t) =>      { &t.meta }
+            Type::Subroutine(t) => { &t.meta }
+            Type::Unspecified(t) => { &t.meta }
+            Type::FortranString(t) => { &t.meta }
+            Type::Unknown(t) =>    { &t.meta }
+            Type::Void =>          { return None }
+        };
+        Some(meta)
+    }
+
+    fn get_meta(self) -> Option<DwTypeMeta> {
         let meta = match self {
             Type::Base(t) =>       { t.meta }
             Type::Array(t) =>      { t.meta }
@@ -135,408 +562,2676 @@ impl Type {
             Type::Typedef(t) =>    { t.meta }
             Type::Struct(t) =>     { t.meta }
             Type::Pointer(t) =>    { t.meta }
+            Type::Reference(t) =>  { t.meta }
+            Type::RvalueReference(t) => { t.meta }
             Type::Union(t) =>      { t.meta }
             Type::Subroutine(t) => { t.meta }
+            Type::Unspecified(t) => { t.meta }
+            Type::FortranString(t) => { t.meta }
             Type::Unknown(t) =>    { t.meta }
+            Type::Void =>          { return None }
         };
-        meta
+        Some(meta)
     }
 }
 
-impl StructMember {
-    fn new() -> StructMember {
-        return StructMember {
-            name: None,
-            size: 0,
-            mb_type: None,
-            meta: DwTypeMeta { offset: gimli::UnitOffset(0), header_idx: 0 }
-        }
+// types compare and hash by the DIE they were read from, not by their
+// contents, so two references to the same type are equal
+macro_rules! impl_die_identity {
+    ($($t:ty),*) => {
+        $(
+            impl PartialEq for $t {
+                fn eq(&self, other: &Self) -> bool {
+                    self.meta == other.meta
+                }
+            }
+
+            impl Eq for $t {}
+
+            impl Hash for $t {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    self.meta.hash(state);
+                }
+            }
+        )*
     }
 }
 
-impl Iterator for StructMemberIter<'_> {
-    type Item = StructMember;
+impl_die_identity!(Struct, AnonStruct, Typedef, Pointer, Reference, RvalueReference, Subroutine,
+    Array, Union, Const, Base, Enum, Unspecified, FortranString, Unknown);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        //println!("next: {:?}", self.member_idx);
-        let member = self.get_member(self.member_idx);
-        let res = match member {
-            Ok(memb) => {memb}
-            Err(_) => {None}
-        };
-        self.member_idx += 1;
-        return res
+/// Equality is identity: two `Type`s are equal when they were read from
+/// the same DIE of the same `Parser`, structurally identical types from
+/// different units are not. Comparing types of different parsers is
+/// meaningless.
+impl PartialEq for Type {
+    fn eq(&self, other: &Type) -> bool {
+        self.meta() == other.meta()
     }
 }
 
-impl StructMemberIter<'_> {
-    pub fn new<'a>(mb_struct: &'a Struct, parser: &'a Parser) -> StructMemberIter<'a> {
-        StructMemberIter { mb_struct, parser, member_idx: 0 }
-    }
+impl Eq for Type {}
 
-    pub fn get_member(&mut self, member_idx: usize) -> Result<Option<StructMember>, gimli::Error> {
-        let mut iter = self.parser.sections.units().skip(self.mb_struct.meta.header_idx);
-        while let Some(header) = iter.next()? {
-            let unit = self.parser.sections.unit(header)?;
-            let mut nested_entries = unit.entries_at_offset(self.mb_struct.meta.offset)?;
+impl Hash for Type {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.meta().hash(state);
+    }
+}
 
-            // move iterator to member index
-            for _ in 0..=member_idx { nested_entries.next_dfs()?; }
+impl Struct {
+    /// Types defined inside the body of this struct, e.g. `inner` in
+    /// `struct outer { struct inner { int a; } x; };`
+    pub fn nested_types(&self, parser: &Parser) -> Result<Vec<Type>, Error> {
+        let unit = parser.unit(self.meta.header_idx)?;
+        let mut tree = unit.entries_tree(Some(self.meta.offset))?;
+        let root = tree.root()?;
 
-            // return next member or None
-            while let Some((_delta_depth, entry)) = nested_entries.next_dfs()? {
-                if entry.tag() != gimli::DW_TAG_member {
-                    return Ok(None)
+        let mut types = Vec::new();
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            match entry.tag() {
+                gimli::DW_TAG_structure_type |
+                gimli::DW_TAG_union_type |
+                gimli::DW_TAG_enumeration_type |
+                gimli::DW_TAG_typedef => {
+                    types.push(parser.get_type_meta(self.meta.header_idx, entry.offset())?);
                 }
-                return self.parse_member(entry);
+                _ => {}
             }
         }
-        Ok(None)
+        Ok(types)
     }
 
-    fn parse_member(&mut self, entry: &gimli::DebuggingInformationEntry<R>) -> Result<Option<StructMember>, gimli::Error> {
-        let mut attrs = entry.attrs();
-        let mut member = StructMember::new();
-        // println!("    main tag: {}", entry.tag());
-        while let Some(attr) = attrs.next()? {
-            match attr.name() {
-                gimli::DW_AT_type => {
-                    match attr.value() {
-                        gimli::AttributeValue::UnitRef(offset) => {
-                            member.mb_type = Some(self.parser.get_type_meta(self.mb_struct.meta.header_idx, offset)?);
-                        },
-                        _ => ()
+    /// The variant part of a rust enum, `None` for plain structs.
+    pub fn variant_part(&self, parser: &Parser) -> Result<Option<VariantPart>, Error> {
+        let header_idx = self.meta.header_idx;
+        let unit = parser.unit(header_idx)?;
+        let mut tree = unit.entries_tree(Some(self.meta.offset))?;
+        let root = tree.root()?;
+
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            if child.entry().tag() != gimli::DW_TAG_variant_part {
+                continue;
+            }
+            let discr = match child.entry().attr_value(gimli::DW_AT_discr)? {
+                Some(gimli::AttributeValue::UnitRef(offset)) => Some(offset),
+                _ => None
+            };
+
+            let mut part = VariantPart { discriminant: None, variants: Vec::new() };
+            let mut part_children = child.children();
+            while let Some(part_child) = part_children.next()? {
+                let entry = part_child.entry();
+                match entry.tag() {
+                    gimli::DW_TAG_member if discr == Some(entry.offset()) => {
+                        part.discriminant = Some(parser.parse_member(header_idx, &unit, entry)?);
                     }
+                    gimli::DW_TAG_variant => {
+                        let discr_value = match entry.attr_value(gimli::DW_AT_discr_value)? {
+                            Some(gimli::AttributeValue::Sdata(value)) => Some(value as u64),
+                            Some(value) => value.udata_value(),
+                            None => None
+                        };
+                        let mut members = Vec::new();
+                        let mut variant_children = part_child.children();
+                        while let Some(variant_child) = variant_children.next()? {
+                            let entry = variant_child.entry();
+                            if entry.tag() == gimli::DW_TAG_member {
+                                members.push(parser.parse_member(header_idx, &unit, entry)?);
+                            }
+                        }
+                        part.variants.push(Variant { discr_value, members });
+                    }
+                    _ => {}
                 }
-                gimli::DW_AT_name => {
-                    member.name = name_attr_to_string(&self.parser.sections.debug_str, &attr)?;
-                }
-                gimli::DW_AT_byte_size => {
-                    let member_size = attr.value().udata_value();
-                    member.size = member_size.unwrap_or(0);
-                }
-                _ => {}
             }
+            return Ok(Some(part));
         }
-        Ok(Some(member))
+        Ok(None)
     }
 
-}
+    /// Members sorted by offset, bitfields sharing a storage unit by bit
+    /// position. Dwarf does not require member DIEs to be in offset order.
+    /// Static members are left out since they have no offset.
+    pub fn members_by_offset(&self, parser: &Parser) -> Result<Vec<StructMember>, Error> {
+        let mut members = parser.members(&self.meta)?;
+        members.retain(|member| !member.is_static);
+        members.sort_by_key(|member| (member.offset, member.bit_position()));
+        Ok(members)
+    }
 
-pub struct Parser {
-    sections: gimli::Dwarf<R>,
-    pub struct_dict: HashMap<String, Struct>
-}
+    /// Members in offset order with the holes between them, ending with the
+    /// trailing padding if there is any.
+    pub fn layout(&self, parser: &Parser) -> Result<Vec<LayoutItem>, Error> {
+        self.layout_with(parser, &LayoutOptions::default())
+    }
 
-impl Parser {
-    pub fn new(file: File) -> Parser {
-        let sections = Self::load_sections(file);
-        let struct_dict = HashMap::<String, Struct>::new();
-        Parser { sections, struct_dict }
+    pub fn layout_with(&self, parser: &Parser, options: &LayoutOptions) -> Result<Vec<LayoutItem>, Error> {
+        let members = self.members_by_offset(parser)?;
+        let mut items = Vec::new();
+        if members.is_empty() {
+            return Ok(items);
+        }
+
+        let mut end = 0;
+        for member in members {
+            if member.offset > end {
+                let separator = member.is_zero_width_bitfield();
+                items.push(LayoutItem::Hole(Hole { offset: end, size: member.offset - end, trailing: false, packed: false, separator }));
+            }
+            end = end.max(member.offset.saturating_add(member.size));
+            items.push(LayoutItem::Member(member));
+        }
+        if self.size > end {
+            items.push(LayoutItem::Hole(Hole { offset: end, size: self.size - end, trailing: true, packed: false, separator: false }));
+        }
+        let has_holes = items.iter().any(|item| matches!(item, LayoutItem::Hole(_)));
+        if has_holes && self.is_packed(parser)? {
+            if options.skip_packed_holes {
+                items.retain(|item| matches!(item, LayoutItem::Member(_)));
+            } else {
+                for item in items.iter_mut() {
+                    if let LayoutItem::Hole(hole) = item {
+                        hole.packed = true;
+                    }
+                }
+            }
+        }
+        if options.merge_adjacent_holes {
+            items = merge_adjacent_holes(items);
+        }
+        Ok(items)
     }
 
-    fn load_sections(file: File) -> gimli::Dwarf<R> {
-        // src: https://github.com/tchajed/rdb/blob/main/src/dwarf.rs#L252
-        let map = unsafe { Mmap::map(&file).unwrap() };
+    /// The member occupying the byte at `offset`. Zero sized members such as
+    /// flexible array members never cover an offset.
+    pub fn member_at_offset(&self, parser: &Parser, offset: u64) -> Result<Option<StructMember>, Error> {
+        let members = parser.members(&self.meta)?;
+        let member = members.into_iter().find(|member| {
+            !member.is_static && member.offset <= offset && offset < member.offset.saturating_add(member.size)
+        });
+        Ok(member)
+    }
 
-        let object = object::File::parse(&*map).unwrap();
+    /// The member holding the bit at `bit_pos`, counted from the start of
+    /// the struct like `StructMember::bit_position`. Bitfields only cover
+    /// their own bits, so bitfields sharing a byte resolve to the right one
+    /// and the unused bits of a storage unit to none.
+    pub fn member_at_bit(&self, parser: &Parser, bit_pos: u64) -> Result<Option<StructMember>, Error> {
+        let members = parser.members(&self.meta)?;
+        let member = members.into_iter().find(|member| {
+            let (start, bits) = match member.bit_size {
+                Some(bit_size) => (member.bit_position(), bit_size),
+                None => (member.offset.saturating_mul(8), member.size.saturating_mul(8))
+            };
+            !member.is_static && start <= bit_pos && bit_pos < start.saturating_add(bits)
+        });
+        Ok(member)
+    }
 
-        let load_section = |id: gimli::SectionId| -> Result<R, gimli::Error> {
-            let data = object
-                .section_by_name(id.name())
-                .and_then(|section| section.uncompressed_data().ok())
-                .unwrap_or(Cow::Borrowed(&[][..]));
-            Ok(R::new(Rc::from(&*data), gimli::LittleEndian))
-        };
+    /// Runs of consecutive bitfields that share a storage unit, e.g. `a`
+    /// and `b` in `unsigned a:4, b:4, c:30;` with `c` on its own. The
+    /// storage unit is the aligned block of the bitfield's type size that
+    /// holds its first bit. Members that aren't bitfields end a run and are
+    /// not included.
+    pub fn bitfield_groups(&self, parser: &Parser) -> Result<Vec<Vec<StructMember>>, Error> {
+        let mut groups: Vec<Vec<StructMember>> = Vec::new();
+        let mut current: Option<(u64, u64)> = None;
+        for member in self.members_by_offset(parser)? {
+            if member.bit_size.is_none() {
+                current = None;
+                continue;
+            }
+            let unit_bits = member.size.saturating_mul(8).max(1);
+            let unit = (member.bit_position() / unit_bits, member.size);
+            match groups.last_mut() {
+                Some(group) if current == Some(unit) => group.push(member),
+                _ => groups.push(vec![member])
+            }
+            current = Some(unit);
+        }
+        Ok(groups)
+    }
 
-        let dwarf = gimli::Dwarf::load(&load_section).expect("Failed to load dwarf sections");
+    /// Gaps left between members, followed by the trailing padding if any.
+    pub fn holes(&self, parser: &Parser) -> Result<Vec<Hole>, Error> {
+        self.holes_with(parser, &LayoutOptions::default())
+    }
 
-        return dwarf;
+    pub fn holes_with(&self, parser: &Parser, options: &LayoutOptions) -> Result<Vec<Hole>, Error> {
+        let holes = self.layout_with(parser, options)?.into_iter().filter_map(|item| match item {
+            LayoutItem::Hole(hole) => Some(hole),
+            LayoutItem::Member(_) => None
+        });
+        Ok(holes.collect())
     }
 
-    pub fn load_structs(&mut self) -> Result<(), gimli::Error> {
-        let mut iter = self.sections.units();
-        let mut header_idx = 0;
-        while let Some(header) = iter.next()? {
-            let unit = self.sections.unit(header)?;
-            let mut entries = unit.entries();
-            while let Some((_delta_depth, entry)) = entries.next_dfs()? {
-                if entry.tag() != gimli::DW_TAG_structure_type {
-                    continue;
-                }
-                self.load_struct(header_idx, entry)?;
-            }
-            header_idx += 1;
+    /// The biggest gap between two members, the first one if several have
+    /// that size. Trailing padding doesn't count, `None` without holes.
+    pub fn largest_hole(&self, parser: &Parser) -> Result<Option<Hole>, Error> {
+        let holes = self.holes(parser)?.into_iter().filter(|hole| !hole.trailing);
+        Ok(holes.reduce(|largest, hole| if hole.size > largest.size { hole } else { largest }))
+    }
+
+    /// Fraction of the struct's bytes used by members, 1.0 for a struct
+    /// without holes or padding. Bitfields count their width rather than
+    /// their storage unit. Empty and zero sized structs have nothing to
+    /// waste and give 1.0.
+    pub fn density(&self, parser: &Parser) -> Result<f64, Error> {
+        if self.size == 0 {
+            return Ok(1.0);
         }
-        Ok(())
+        let mut used_bits: u64 = 0;
+        for member in self.members_by_offset(parser)? {
+            let bits = member.bit_size.unwrap_or(member.size.saturating_mul(8));
+            used_bits = used_bits.saturating_add(bits);
+        }
+        Ok(used_bits as f64 / self.size.saturating_mul(8) as f64)
     }
 
-    pub fn load_struct(&mut self, header_idx: usize, entry: &gimli::DebuggingInformationEntry<R>) -> Result<(), gimli::Error> {
-        let mut attrs = entry.attrs();
-        let mut struct_name: Option<String> = None;
-        let mut struct_size: Option<u64> = None;
-        while let Some(attr) = attrs.next()? {
-            match attr.name() {
-                gimli::DW_AT_name => {
-                    struct_name = name_attr_to_string(&self.sections.debug_str, &attr)?;
-                }
-                gimli::DW_AT_byte_size => {
-                    struct_size = attr.value().udata_value();
-                }
-                gimli::DW_AT_declaration => {
-                    // just say empty set to declarations
-                    return Ok(());
-                }
-                _ => {}
+    /// Cross check the declared size against the members and look for
+    /// overlapping members. Warnings usually point at a parsing bug or at
+    /// unusual packing.
+    pub fn validate_layout(&self, parser: &Parser) -> Result<Vec<LayoutWarning>, Error> {
+        let mut warnings = Vec::new();
+        let mut end_bit: u64 = 0;
+        let mut last: Option<StructMember> = None;
+        for member in self.members_by_offset(parser)? {
+            let bits = member.bit_size.unwrap_or(member.size.saturating_mul(8));
+            if bits == 0 {
+                continue;
             }
-            if struct_name.is_some() && struct_size.is_some() {
-                break;
+            if member.bit_position() < end_bit {
+                warnings.push(LayoutWarning::Overlap {
+                    first: last.as_ref().and_then(|last| last.name.clone()),
+                    second: member.name.clone(),
+                    offset: member.offset
+                });
             }
+            end_bit = end_bit.max(member.bit_position().saturating_add(bits));
+            last = Some(member);
         }
-        if let Some(name) = &struct_name {
-            let size = struct_size.unwrap_or(0);
-            match self.struct_dict.entry(name.clone()) {
-                std::collections::hash_map::Entry::Occupied(mut dentry) => {
-                    dentry.get_mut().refcnt += 1;
-                }
-                std::collections::hash_map::Entry::Vacant(dentry) => {
-                    let meta = DwTypeMeta { offset: entry.offset(), header_idx };
-                    dentry.insert(Struct{name: name.clone(), size, meta, refcnt: 0});
-                }
-            };
+        let computed = end_bit.div_ceil(8);
+        if computed > self.size {
+            warnings.push(LayoutWarning::SizeMismatch { declared: self.size, computed });
         }
-        Ok(())
+        Ok(warnings)
     }
 
-    pub fn get_type(&self, type_inst: Type ) -> Result<Option<Type>, gimli::Error> {
-        //println!("get_type({:?})", type_inst);
-        let meta = type_inst.get_meta();
-        let mut iter = self.sections.units().skip(meta.header_idx);
-        while let Some(header) = iter.next()? {
-            let unit = self.sections.unit(header)?;
-            let mut nested_entries = unit.entries_at_offset(meta.offset)?;
-
-            if let Some((_delta_depth, entry)) = nested_entries.next_dfs()? {
-                let mut attrs = entry.attrs();
-                while let Some(attr) = attrs.next()? {
-                    match attr.name() {
-                        gimli::DW_AT_type => {
-                            match attr.value() {
-                                gimli::AttributeValue::UnitRef(offset) => {
-                                    let _type = self.get_type_meta(meta.header_idx, offset)?;
-                                    return Ok(Some(_type));
-                                },
-                                _ => ()
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                return Ok(None);
+    /// Resolve a dotted member path such as `thread.fpu.state`, looking
+    /// through anonymous structs and unions like C does. Returns the last
+    /// member and its offset from the start of this struct.
+    pub fn resolve_field_path(&self, parser: &Parser, path: &str) -> Result<Option<(StructMember, u64)>, Error> {
+        let mut meta = self.meta.clone();
+        let mut base = 0;
+        let mut fields = path.split('.').peekable();
+        while let Some(field) = fields.next() {
+            let (mut member, offset) = match parser.find_member(&meta, field, base, 0)? {
+                Some(found) => found,
+                None => return Ok(None)
+            };
+            if fields.peek().is_none() {
+                return Ok(Some((member, offset)));
             }
+            meta = match member.mb_type.take() {
+                Some(mb_type) => match parser.aggregate_meta(mb_type, 0)? {
+                    Some(meta) => meta,
+                    None => return Ok(None)
+                },
+                None => return Ok(None)
+            };
+            base = offset;
         }
-        // FIXME
-        Err(gimli::Error::TypeMismatch)
+        Ok(None)
+    }
+
+    /// `offsetof` for a dotted member path: the byte offset of the member
+    /// `resolve_field_path` finds, `None` if a component isn't found.
+    pub fn offset_of_path(&self, parser: &Parser, path: &str) -> Result<Option<u64>, Error> {
+        Ok(self.resolve_field_path(parser, path)?.map(|(_member, offset)| offset))
+    }
+
+    /// Whether the members sit tighter than their natural alignment allows,
+    /// as with `__attribute__((packed))`, or the size is not a multiple of
+    /// the struct's alignment.
+    pub fn is_packed(&self, parser: &Parser) -> Result<bool, Error> {
+        let mut align = 1;
+        for member in self.members_by_offset(parser)? {
+            // bitfields are placed on bit boundaries anyway
+            if member.bit_size.is_some() {
+                continue;
+            }
+            let member_align = member.natural_alignment(parser)?;
+            if !member.offset.is_multiple_of(member_align) {
+                return Ok(true);
+            }
+            align = align.max(member_align);
+        }
+        Ok(!self.size.is_multiple_of(align))
+    }
+
+    /// Members whose offset is not a multiple of their natural alignment,
+    /// in offset order. Bitfields are never reported since they are placed
+    /// on bit boundaries regardless of their type.
+    pub fn misaligned_members(&self, parser: &Parser) -> Result<Vec<StructMember>, Error> {
+        let mut misaligned = Vec::new();
+        for member in self.members_by_offset(parser)? {
+            if member.bit_size.is_some() {
+                continue;
+            }
+            if !member.offset.is_multiple_of(member.natural_alignment(parser)?) {
+                misaligned.push(member);
+            }
+        }
+        Ok(misaligned)
+    }
+
+    /// Members that lie entirely within the first `line_size` bytes, in
+    /// offset order, i.e. the fields that share the first cacheline.
+    /// Bitfields count by their bits rather than their storage unit.
+    pub fn first_cacheline_members(&self, parser: &Parser, line_size: u64) -> Result<Vec<StructMember>, Error> {
+        let mut members = self.members_by_offset(parser)?;
+        members.retain(|member| {
+            match member.bit_size {
+                Some(bit_size) => member.bit_position().saturating_add(bit_size) <= line_size.saturating_mul(8),
+                None => member.offset.saturating_add(member.size) <= line_size
+            }
+        });
+        Ok(members)
+    }
+
+    /// The members in each `line_size` byte cacheline of the struct, by
+    /// cacheline index and in offset order. Members straddling a boundary
+    /// are listed in every line they touch, lines holding only padding have
+    /// no members and zero sized members at the very end are left out.
+    /// Empty for a `line_size` of 0.
+    pub fn members_by_cacheline(&self, parser: &Parser, line_size: u64) -> Result<Vec<(u64, Vec<StructMember>)>, Error> {
+        if line_size == 0 {
+            return Ok(Vec::new());
+        }
+        let mut lines: Vec<(u64, Vec<StructMember>)> = (0..self.size.div_ceil(line_size)).map(|line| (line, Vec::new())).collect();
+        for member in self.members_by_offset(parser)? {
+            // in bits so bitfields only count the lines their bits are in
+            let line_bits = line_size.saturating_mul(8);
+            let (start, bits) = match member.bit_size {
+                Some(bit_size) => (member.bit_position(), bit_size),
+                None => (member.offset.saturating_mul(8), member.size.saturating_mul(8))
+            };
+            let first = start / line_bits;
+            let last = start.saturating_add(bits).saturating_sub(1).max(start) / line_bits;
+            for (_line, members) in lines.iter_mut().filter(|(line, _)| first <= *line && *line <= last) {
+                members.push(member.clone());
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Names of the members in DIE order, `None` for anonymous members.
+    /// Much cheaper than `StructMemberIter` since member types are not
+    /// resolved.
+    pub fn member_names(&self, parser: &Parser) -> Result<Vec<Option<String>>, Error> {
+        let unit = parser.unit(self.meta.header_idx)?;
+        let mut tree = unit.entries_tree(Some(self.meta.offset))?;
+        let root = tree.root()?;
+
+        let mut names = Vec::new();
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if !is_member(entry)? {
+                continue;
+            }
+            let name = match entry.attr(gimli::DW_AT_name)? {
+                Some(attr) => parser.name_attr_to_string(self.meta.header_idx, &attr)?,
+                None => None
+            };
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    /// Whether every member and the types they refer to can be parsed, see
+    /// `unresolved_members`. Rendering a struct that isn't fully resolved
+    /// leaves out or misnames the affected members.
+    pub fn is_fully_resolved(&self, parser: &Parser) -> Result<bool, Error> {
+        Ok(self.unresolved_members(parser)?.is_empty())
+    }
+
+    /// Names of the members, in DIE order and `None` when anonymous, that
+    /// fail to parse, have a type reference that can't be followed, or whose
+    /// type is or points to a type with an unsupported tag. Nested structs
+    /// and unions are not checked, ask them separately.
+    pub fn unresolved_members(&self, parser: &Parser) -> Result<Vec<Option<String>>, Error> {
+        let header_idx = self.meta.header_idx;
+        let unit = parser.unit(header_idx)?;
+        let mut tree = unit.entries_tree(Some(self.meta.offset))?;
+        let root = tree.root()?;
+
+        let mut unresolved = Vec::new();
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if !is_member(entry)? {
+                continue;
+            }
+            let resolved = match parser.parse_member(header_idx, &unit, entry) {
+                Ok(member) => match &member.mb_type {
+                    Some(mb_type) => parser.type_resolves(mb_type, 0),
+                    // a DW_AT_type that type_ref couldn't follow
+                    None => entry.attr(gimli::DW_AT_type)?.is_none()
+                },
+                Err(_) => false
+            };
+            if !resolved {
+                let name = match entry.attr(gimli::DW_AT_name)? {
+                    Some(attr) => parser.name_attr_to_string(header_idx, &attr)?,
+                    None => None
+                };
+                unresolved.push(name);
+            }
+        }
+        Ok(unresolved)
+    }
+
+    /// The attributes of the struct's DIE as `(name, value)` strings, e.g.
+    /// `("DW_AT_byte_size", "16")`, for debugging how a struct was parsed.
+    /// References are printed as section offsets like `llvm-dwarfdump`
+    /// does, forms without a readable value fall back to their debug format.
+    pub fn raw_attributes(&self, parser: &Parser) -> Result<Vec<(String, String)>, Error> {
+        let unit = parser.unit(self.meta.header_idx)?;
+        let dwarf = parser.dwarf(self.meta.header_idx);
+        let entry = unit.entry(self.meta.offset)?;
+
+        let mut attributes = Vec::new();
+        let mut attrs = entry.attrs();
+        while let Some(attr) = attrs.next()? {
+            let value = match attr.value() {
+                gimli::AttributeValue::UnitRef(offset) => {
+                    format!("{:#x}", self.meta.unit_offset + offset.0)
+                }
+                gimli::AttributeValue::DebugInfoRef(offset) => format!("{:#x}", offset.0),
+                gimli::AttributeValue::Flag(flag) => flag.to_string(),
+                gimli::AttributeValue::FileIndex(index) => index.to_string(),
+                gimli::AttributeValue::Sdata(value) => value.to_string(),
+                gimli::AttributeValue::Encoding(encoding) => encoding.to_string(),
+                gimli::AttributeValue::Language(language) => language.to_string(),
+                gimli::AttributeValue::Accessibility(accessibility) => accessibility.to_string(),
+                gimli::AttributeValue::Exprloc(expr) => format!("{:02x?}", expr.0.to_slice()?),
+                gimli::AttributeValue::Block(block) => format!("{:02x?}", block.to_slice()?),
+                value => match value.udata_value() {
+                    Some(value) => value.to_string(),
+                    None => match dwarf.attr_string(&unit, value.clone()) {
+                        Ok(string) => string.to_string_lossy()?.to_string(),
+                        Err(_) => format!("{:?}", value)
+                    }
+                }
+            };
+            attributes.push((attr.name().to_string(), value));
+        }
+        Ok(attributes)
+    }
+
+    /// `DW_AT_export_symbols` is set: the struct is an anonymous member
+    /// whose members are reached as if they were members of the parent.
+    /// Emitted by clang, gcc leaves it out.
+    pub fn exports_symbols(&self, parser: &Parser) -> Result<bool, Error> {
+        parser.exports_symbols(&self.meta)
+    }
+
+    /// Whether instances of this C++ class carry a vtable pointer, either
+    /// as a member of their own or through a base class.
+    pub fn has_vtable(&self, parser: &Parser) -> Result<bool, Error> {
+        if self.containing_type(parser)?.is_some() {
+            return Ok(true);
+        }
+        Ok(parser.members(&self.meta)?.iter().any(|member| member.is_vtable_ptr()))
+    }
+
+    /// The class holding the vtable pointer of this C++ class, from
+    /// `DW_AT_containing_type`. That is the class itself if it declares the
+    /// first virtual function, otherwise a base class. `None` for classes
+    /// without virtual functions.
+    pub fn containing_type(&self, parser: &Parser) -> Result<Option<Struct>, Error> {
+        let unit = parser.unit(self.meta.header_idx)?;
+        let entry = unit.entry(self.meta.offset)?;
+        let (header_idx, offset) = match parser.type_ref(self.meta.header_idx, entry.attr_value(gimli::DW_AT_containing_type)?) {
+            Some(target) => target,
+            None => return Ok(None)
+        };
+        match parser.get_type_meta(header_idx, offset)? {
+            Type::Struct(t) => Ok(Some(t)),
+            _ => Ok(None)
+        }
+    }
+
+    /// `DW_AT_byte_size` as written, `None` for forward declarations and
+    /// other incomplete types. Unlike `size` this tells an empty struct,
+    /// which has a size of 0 in C++ and GNU C, from one without a size.
+    pub fn declared_size(&self) -> Option<u64> {
+        self.declared_size
+    }
+
+    /// The DIE is a forward declaration (`DW_AT_declaration`) without
+    /// members, see `Parser::set_include_declarations`.
+    pub fn is_declaration(&self) -> bool {
+        self.declaration
+    }
+
+    /// Section offset of the struct's DIE, as printed by `llvm-dwarfdump`.
+    pub fn die_offset(&self) -> u64 {
+        self.meta.die_offset()
+    }
+
+    /// Where the struct is declared, `None` if the DIE doesn't say.
+    pub fn decl_location(&self, parser: &Parser) -> Result<Option<DeclLocation>, Error> {
+        parser.decl_location(&self.meta)
+    }
+
+    /// The demangled form of the name, `None` if it is not a mangled symbol
+    /// or the `demangle` feature is disabled.
+    pub fn demangled_name(&self) -> Option<String> {
+        demangle(&self.name)
+    }
+}
+
+impl Union {
+    /// The members of the union, all of them start at offset 0.
+    pub fn members(&self, parser: &Parser) -> Result<Vec<StructMember>, Error> {
+        let mut members = Vec::new();
+        let mut iter = StructUnionIter::new(self, parser);
+        let mut idx = 0;
+        while let Some(member) = iter.get_member(idx)? {
+            members.push(member);
+            idx += 1;
+        }
+        Ok(members)
+    }
+
+    /// `DW_AT_export_symbols` is set: the union is an anonymous member whose
+    /// members are reached as if they were members of the parent.
+    pub fn exports_symbols(&self, parser: &Parser) -> Result<bool, Error> {
+        parser.exports_symbols(&self.meta)
+    }
+}
+
+impl Enum {
+    /// The enumerators as `(name, value)` pairs in declaration order.
+    pub fn enumerators(&self, parser: &Parser) -> Result<Vec<(String, EnumValue)>, Error> {
+        parser.enumerators(&self.meta)
+    }
+
+    /// The smallest and largest enumerator value, `None` for an enum
+    /// without enumerators.
+    pub fn value_range(&self, parser: &Parser) -> Result<Option<(EnumValue, EnumValue)>, Error> {
+        let signed = parser.enum_encoding(&self.meta)? == gimli::DW_ATE_signed;
+        let values = self.enumerators(parser)?.into_iter().map(|(_name, value)| value);
+        let min = values.clone().min_by_key(|value| value.order_key(signed));
+        let max = values.max_by_key(|value| value.order_key(signed));
+        match (min, max) {
+            (Some(min), Some(max)) => Ok(Some((min, max))),
+            _ => Ok(None)
+        }
+    }
+
+    /// Section offset of the enum's DIE, which tells anonymous enums apart.
+    pub fn die_offset(&self) -> u64 {
+        self.meta.die_offset()
+    }
+}
+
+impl Typedef {
+    /// The members of the struct or union the typedef names, following
+    /// further typedefs and qualifiers. This is the way to reach the members
+    /// of `typedef struct { ... } foo_t;`, whose struct is anonymous and so
+    /// not in `struct_dict`. `None` if the target is not an aggregate.
+    pub fn members(&self, parser: &Parser) -> Result<Option<Vec<StructMember>>, Error> {
+        let target = match parser.resolve_meta(&self.meta)? {
+            Some(target) => target,
+            None => return Ok(None)
+        };
+        match parser.aggregate_meta(target, 0)? {
+            Some(meta) => Ok(Some(parser.members(&meta)?)),
+            None => Ok(None)
+        }
+    }
+}
+
+impl Array {
+    /// Size in bytes, `element_count` times the element size or stride.
+    pub fn byte_size(&self, parser: &Parser) -> Result<u64, Error> {
+        parser.array_size(self, 0)
+    }
+}
+
+impl Unknown {
+    /// The tag is in the range reserved for vendor extensions, of GNU or
+    /// any other producer.
+    pub fn is_vendor_extension(&self) -> bool {
+        self.tag.0 >= gimli::DW_TAG_lo_user.0
+    }
+
+    /// The tag is one of the `DW_TAG_GNU_*` extensions gcc emits, e.g. for
+    /// call sites and template parameter packs.
+    pub fn is_gnu_extension(&self) -> bool {
+        matches!(self.tag,
+            gimli::DW_TAG_GNU_BINCL | gimli::DW_TAG_GNU_EINCL
+            | gimli::DW_TAG_GNU_template_template_param | gimli::DW_TAG_GNU_template_parameter_pack
+            | gimli::DW_TAG_GNU_formal_parameter_pack
+            | gimli::DW_TAG_GNU_call_site | gimli::DW_TAG_GNU_call_site_parameter)
+    }
+}
+
+impl Pointer {
+    /// The struct this pointer points to, looked up in `struct_dict` so that
+    /// pointers to forward declarations give the full definition. `None` for
+    /// `void *`, pointers to non-structs and pointers to pointers.
+    pub fn target_struct(&self, parser: &Parser) -> Result<Option<Struct>, Error> {
+        parser.pointee_struct(&self.meta, false, 0)
+    }
+
+    /// Same as `target_struct` but follows pointers to pointers, so
+    /// `struct foo **` also gives `foo`.
+    pub fn target_struct_recursive(&self, parser: &Parser) -> Result<Option<Struct>, Error> {
+        parser.pointee_struct(&self.meta, true, 0)
+    }
+}
+
+impl Subroutine {
+    /// The return type, `None` for subroutines returning void.
+    pub fn return_type(&self, parser: &Parser) -> Result<Option<Type>, Error> {
+        parser.resolve_meta(&self.meta)
+    }
+
+    /// Parameter types in declaration order.
+    pub fn params(&self, parser: &Parser) -> Result<Vec<Type>, Error> {
+        let unit = parser.unit(self.meta.header_idx)?;
+        let mut tree = unit.entries_tree(Some(self.meta.offset))?;
+        let root = tree.root()?;
+
+        let mut params = Vec::new();
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_formal_parameter {
+                continue;
+            }
+            if let Some((header_idx, offset)) = parser.type_ref(self.meta.header_idx, entry.attr_value(gimli::DW_AT_type)?) {
+                params.push(parser.get_type_meta(header_idx, offset)?);
+            }
+        }
+        Ok(params)
+    }
+
+    /// Whether the parameter list ends in `...`.
+    pub fn is_variadic(&self, parser: &Parser) -> Result<bool, Error> {
+        let unit = parser.unit(self.meta.header_idx)?;
+        let mut tree = unit.entries_tree(Some(self.meta.offset))?;
+        let root = tree.root()?;
+
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            if child.entry().tag() == gimli::DW_TAG_unspecified_parameters {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl StructMember {
+    /// The member is the vtable pointer the compiler adds to C++ classes
+    /// with virtual functions, `_vptr.Base` with gcc or `_vptr$Base` with
+    /// clang.
+    pub fn is_vtable_ptr(&self) -> bool {
+        self.is_artificial && self.name.as_deref().is_some_and(|name| name.starts_with("_vptr"))
+    }
+
+    /// Position of the first bit of this member counted from the start of
+    /// the struct, in the target's bit numbering.
+    pub fn bit_position(&self) -> u64 {
+        self.bit_position
+    }
+
+    /// The member is an unnamed bitfield of width 0, `int : 0`, which takes
+    /// no space and aligns the next member to its type's storage unit.
+    pub fn is_zero_width_bitfield(&self) -> bool {
+        self.bit_size == Some(0)
+    }
+
+    /// Section offset of the member's DIE.
+    pub fn die_offset(&self) -> u64 {
+        self.meta.die_offset()
+    }
+
+    /// Where the member is declared, `None` if the DIE doesn't say.
+    pub fn decl_location(&self, parser: &Parser) -> Result<Option<DeclLocation>, Error> {
+        parser.decl_location(&self.meta)
+    }
+
+    /// Alignment the member's type would have without packing, assuming
+    /// scalars are aligned to their size unless their base type gives a
+    /// `DW_AT_alignment`. 1 for members without a type.
+    pub fn natural_alignment(&self, parser: &Parser) -> Result<u64, Error> {
+        match &self.mb_type {
+            Some(mb_type) => parser.type_alignment(mb_type, 0),
+            None => Ok(1)
+        }
+    }
+
+    fn new() -> StructMember {
+        return StructMember {
+            name: None,
+            size: 0,
+            offset: 0,
+            bit_size: None,
+            mb_type: None,
+            is_static: false,
+            is_external: false,
+            is_artificial: false,
+            is_inheritance: false,
+            static_address: None,
+            bit_position: 0,
+            meta: DwTypeMeta { offset: gimli::UnitOffset(0), header_idx: 0, unit_offset: 0 }
+        }
+    }
+}
+
+impl Iterator for StructMemberIter<'_> {
+    type Item = Result<StructMember, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let res = self.get_member(self.member_idx).transpose();
+        self.failed = matches!(res, Some(Err(_)));
+        self.member_idx += 1;
+        return res
+    }
+}
+
+impl StructMemberIter<'_> {
+    pub fn new<'a>(mb_struct: &'a Struct, parser: &'a Parser) -> StructMemberIter<'a> {
+        StructMemberIter { mb_struct, parser, member_idx: 0, failed: false }
+    }
+
+    pub fn get_member(&mut self, member_idx: usize) -> Result<Option<StructMember>, Error> {
+        self.parser.nth_member(&self.mb_struct.meta, member_idx)
+    }
+}
+
+impl Iterator for StructUnionIter<'_> {
+    type Item = Result<StructMember, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let res = self.get_member(self.member_idx).transpose();
+        self.failed = matches!(res, Some(Err(_)));
+        self.member_idx += 1;
+        return res
+    }
+}
+
+impl StructUnionIter<'_> {
+    pub fn new<'a>(dw_union: &'a Union, parser: &'a Parser) -> StructUnionIter<'a> {
+        StructUnionIter { dw_union, parser, member_idx: 0, failed: false }
+    }
+
+    pub fn get_member(&mut self, member_idx: usize) -> Result<Option<StructMember>, Error> {
+        self.parser.nth_member(&self.dw_union.meta, member_idx)
+    }
+}
+
+/// Owned copy of the struct metadata extracted by `Parser::into_index`.
+///
+/// It holds no dwarf data, so it is much smaller than the `Parser` it came
+/// from, but nothing beyond names, sizes and member offsets can be looked up
+/// from it anymore.
+#[derive(Clone, Debug, Default)]
+pub struct StructIndex {
+    pub structs: Map<String, IndexedStruct>
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexedStruct {
+    pub name: String,
+    pub size: u64,
+    pub members: Vec<IndexedMember>
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexedMember {
+    pub name: Option<String>,
+    pub offset: u64,
+    pub size: u64,
+    pub bit_size: Option<u64>
+}
+
+impl StructIndex {
+    pub fn get(&self, name: &str) -> Option<&IndexedStruct> {
+        self.structs.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.structs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.structs.is_empty()
+    }
+}
+
+// the dwarf of one object file, see Parser::add_object
+struct DwarfObject {
+    sections: gimli::Dwarf<R>,
+    names: NameIndexes,
+    endian: gimli::RunTimeEndian,
+}
+
+// attributes of a member DIE and its abstract origins that are only needed
+// until the member is built, see Parser::parse_member
+#[derive(Default)]
+struct MemberAttrs {
+    seen: Vec<gimli::DwAt>,
+    location: Option<u64>,
+    data_bit_offset: Option<u64>,
+    bit_offset: Option<u64>,
+    declaration: bool,
+    // DW_OP_addr in DW_AT_location or DW_AT_data_member_location
+    address: Option<u64>,
+}
+
+pub struct Parser {
+    objects: Vec<DwarfObject>,
+    // units of all objects, header_idx indexes into this together with the
+    // index of the object the unit belongs to
+    headers: Vec<(usize, gimli::UnitHeader<R>)>,
+    // where the type of each type unit is, by DW_AT_signature
+    type_signatures: Map<u64, (usize, UnitOffset)>,
+    pub struct_dict: Map<String, Struct>,
+    // struct_dict keys by their demangled form without whitespace, what
+    // get_struct falls back to
+    demangled_names: Map<String, String>,
+    // canonical names of the member types of each struct, filled lazily by
+    // structs_containing_type
+    member_types: RefCell<Map<String, Rc<Set<String>>>>,
+    // set by new_lazy, get_struct then searches the dwarf for structs that
+    // aren't loaded and keeps what it found, or didn't, here
+    lazy: bool,
+    lazy_structs: RefCell<Map<String, Option<Struct>>>,
+    // index forward declarations of structs that are never defined
+    include_declarations: bool,
+    // fail on names that aren't utf-8 instead of replacing the bad bytes
+    strict_names: bool,
+    // addresses of static members defined in each unit, filled lazily by
+    // parse_member
+    static_addresses: RefCell<Map<usize, Rc<Map<UnitOffset, u64>>>>,
+    // toolchain of each unit, filled lazily by get_array_bounds
+    producers: RefCell<Map<usize, Producer>>,
+    // units load_structs already went through, later calls only walk the
+    // units of objects added since
+    indexed_units: usize,
+    max_depth: usize
+}
+
+/// Builds a `Parser` from raw section contents instead of an object file.
+///
+/// Sections that are not supplied are treated as empty. Units carrying a
+/// `DW_AT_stmt_list` also need `.debug_line` (and `.debug_line_str` for DWARF 5).
+#[derive(Default)]
+pub struct ParserBuilder {
+    sections: Map<gimli::SectionId, Vec<u8>>,
+    // gimli has no SectionId for it
+    debug_names: Vec<u8>,
+    endian: Option<gimli::RunTimeEndian>
+}
+
+impl ParserBuilder {
+    pub fn new() -> ParserBuilder {
+        ParserBuilder::default()
+    }
+
+    pub fn section(mut self, id: gimli::SectionId, data: Vec<u8>) -> ParserBuilder {
+        self.sections.insert(id, data);
+        self
+    }
+
+    /// Byte order of the supplied sections, little endian by default.
+    pub fn endian(mut self, endian: gimli::RunTimeEndian) -> ParserBuilder {
+        self.endian = Some(endian);
+        self
+    }
+
+    pub fn debug_info(self, data: Vec<u8>) -> ParserBuilder {
+        self.section(gimli::SectionId::DebugInfo, data)
+    }
+
+    pub fn debug_abbrev(self, data: Vec<u8>) -> ParserBuilder {
+        self.section(gimli::SectionId::DebugAbbrev, data)
+    }
+
+    pub fn debug_str(self, data: Vec<u8>) -> ParserBuilder {
+        self.section(gimli::SectionId::DebugStr, data)
+    }
+
+    /// The dwarf 5 name index, see `Parser::load_structs_indexed`.
+    /// `.debug_pubtypes` is passed with `section` like the others.
+    pub fn debug_names(mut self, data: Vec<u8>) -> ParserBuilder {
+        self.debug_names = data;
+        self
+    }
+
+    pub fn build(self) -> Result<Parser, Error> {
+        let endian = self.endian.unwrap_or(gimli::RunTimeEndian::Little);
+        let load_section = |id: gimli::SectionId| -> Result<R, gimli::Error> {
+            let data = self.sections.get(&id).map(|data| &data[..]).unwrap_or(&[]);
+            Ok(R::new(Rc::from(data), endian))
+        };
+        let sections = gimli::Dwarf::load(load_section)?;
+        let names = NameIndexes {
+            debug_names: R::new(Rc::from(&self.debug_names[..]), endian),
+            debug_pubtypes: load_section(gimli::SectionId::DebugPubTypes)?,
+        };
+        Parser::from_sections(sections, names, endian)
+    }
+}
+
+impl Parser {
+    /// Bound how many typedef/qualifier/array links are followed when
+    /// resolving a type, `DEFAULT_MAX_DEPTH` unless set. Deeper chains,
+    /// e.g. cycles in malformed dwarf, fail with an error instead of
+    /// overflowing the stack.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Also index structs that are only forward declared, e.g. in a unit
+    /// that uses `struct foo *` without including the definition, as zero
+    /// sized entries whose `is_declaration` is true. A definition of the
+    /// same name always takes their place. Off by default, set it before
+    /// loading any structs.
+    pub fn set_include_declarations(&mut self, include_declarations: bool) {
+        self.include_declarations = include_declarations;
+    }
+
+    /// Fail with `gimli::Error::BadUtf8` on names that are not valid utf-8
+    /// instead of replacing the invalid bytes with U+FFFD, e.g. to check
+    /// debug info for corruption. Off by default.
+    pub fn set_strict_names(&mut self, strict_names: bool) {
+        self.strict_names = strict_names;
+    }
+
+    fn from_sections(sections: gimli::Dwarf<R>, names: NameIndexes, endian: gimli::RunTimeEndian) -> Result<Parser, Error> {
+        let mut parser = Parser {
+            objects: Vec::new(),
+            headers: Vec::new(),
+            type_signatures: Map::new(),
+            struct_dict: Map::new(),
+            demangled_names: Map::new(),
+            member_types: RefCell::new(Map::new()),
+            lazy: false,
+            lazy_structs: RefCell::new(Map::new()),
+            include_declarations: false,
+            strict_names: false,
+            static_addresses: RefCell::new(Map::new()),
+            producers: RefCell::new(Map::new()),
+            indexed_units: 0,
+            max_depth: DEFAULT_MAX_DEPTH
+        };
+        parser.add_sections(sections, names, endian)?;
+        Ok(parser)
+    }
+
+    fn add_sections(&mut self, sections: gimli::Dwarf<R>, names: NameIndexes, endian: gimli::RunTimeEndian) -> Result<(), Error> {
+        let mut headers = Vec::new();
+        let mut units = sections.units();
+        while let Some(header) = units.next()? {
+            headers.push(header);
+        }
+        if headers.is_empty() {
+            return Err(Error::NoDebugInfo);
+        }
+        // dwarf 4 type units live in .debug_types, dwarf 5 ones in
+        // .debug_info. Keep them last so the .debug_info units stay sorted
+        let mut type_units = sections.type_units();
+        while let Some(header) = type_units.next()? {
+            headers.push(header);
+        }
+
+        let object_idx = self.objects.len();
+        for header in headers {
+            match header.type_() {
+                gimli::UnitType::Type { type_signature, type_offset } |
+                gimli::UnitType::SplitType { type_signature, type_offset } => {
+                    self.type_signatures.insert(type_signature.0, (self.headers.len(), type_offset));
+                }
+                _ => {}
+            }
+            self.headers.push((object_idx, header));
+        }
+        self.objects.push(DwarfObject { sections, names, endian });
+        Ok(())
+    }
+
+    fn dwarf(&self, header_idx: usize) -> &gimli::Dwarf<R> {
+        &self.object(header_idx).sections
+    }
+
+    fn decl_location(&self, meta: &DwTypeMeta) -> Result<Option<DeclLocation>, Error> {
+        let unit = self.unit(meta.header_idx)?;
+        let entry = unit.entry(meta.offset)?;
+        let mut location = DeclLocation { file: None, line: None, column: None };
+        let mut attrs = entry.attrs();
+        while let Some(attr) = attrs.next()? {
+            match attr.name() {
+                gimli::DW_AT_decl_file => {
+                    location.file = self.decl_file(meta.header_idx, &unit, attr.value())?;
+                }
+                gimli::DW_AT_decl_line => {
+                    location.line = attr.value().udata_value();
+                }
+                gimli::DW_AT_decl_column => {
+                    location.column = attr.value().udata_value();
+                }
+                _ => {}
+            }
+        }
+        if location.file.is_none() && location.line.is_none() && location.column.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(location))
+    }
+
+    // the path of entry `value` of the unit's line table file list
+    fn decl_file(&self, header_idx: usize, unit: &gimli::Unit<R>, value: gimli::AttributeValue<R>) -> Result<Option<String>, Error> {
+        let index = match value {
+            gimli::AttributeValue::FileIndex(index) => index,
+            value => match value.udata_value() {
+                Some(index) => index,
+                None => return Ok(None)
+            }
+        };
+        let header = match &unit.line_program {
+            Some(program) => program.header(),
+            None => return Ok(None)
+        };
+        let file = match header.file(index) {
+            Some(file) => file,
+            None => return Ok(None)
+        };
+        let dwarf = self.dwarf(header_idx);
+        let name = dwarf.attr_string(unit, file.path_name())?.to_string_lossy()?.to_string();
+        let directory = match file.directory(header) {
+            Some(directory) => dwarf.attr_string(unit, directory)?.to_string_lossy()?.to_string(),
+            None => String::new()
+        };
+        if directory.is_empty() || name.starts_with('/') {
+            return Ok(Some(name));
+        }
+        Ok(Some(format!("{}/{}", directory.trim_end_matches('/'), name)))
+    }
+
+    // DW_AT_name and friends, invalid utf-8 is replaced or an error
+    // depending on strict_names
+    fn name_attr_to_string(&self, header_idx: usize, attr: &gimli::Attribute<R>) -> Result<Option<String>, Error> {
+        let name = match attr.value() {
+            gimli::AttributeValue::String(val) => val,
+            gimli::AttributeValue::DebugStrRef(val) => self.dwarf(header_idx).debug_str.get_str(val)?,
+            _ => return Ok(None)
+        };
+        if self.strict_names {
+            return Ok(Some(name.to_string()?.to_string()));
+        }
+        Ok(Some(name.to_string_lossy()?.to_string()))
+    }
+
+    fn endian(&self, header_idx: usize) -> gimli::RunTimeEndian {
+        self.object(header_idx).endian
+    }
+
+    // the unit and offset a DW_AT_type value of a DIE in unit `header_idx`
+    // points to. DW_FORM_ref_addr references, as left by LTO and dwz, can
+    // point into any unit of the same object, DW_FORM_ref_sig8 ones into a
+    // type unit
+    fn type_ref(&self, header_idx: usize, value: Option<gimli::AttributeValue<R>>) -> Option<(usize, UnitOffset)> {
+        match value? {
+            gimli::AttributeValue::UnitRef(offset) => Some((header_idx, offset)),
+            gimli::AttributeValue::DebugInfoRef(offset) => self.debug_info_ref(header_idx, offset),
+            gimli::AttributeValue::DebugTypesRef(signature) => self.type_signatures.get(&signature.0).copied(),
+            _ => None
+        }
+    }
+
+    fn debug_info_ref(&self, header_idx: usize, offset: gimli::DebugInfoOffset) -> Option<(usize, UnitOffset)> {
+        let object_idx = self.headers.get(header_idx)?.0;
+        // the units of an object are contiguous and in section order
+        let start = self.headers.partition_point(|(idx, _)| *idx < object_idx);
+        let end = self.headers.partition_point(|(idx, _)| *idx <= object_idx);
+        let units = &self.headers[start..end];
+        let pos = units.partition_point(|(_, header)| match header.offset() {
+            gimli::UnitSectionOffset::DebugInfoOffset(unit_offset) => unit_offset.0 <= offset.0,
+            gimli::UnitSectionOffset::DebugTypesOffset(_) => false
+        });
+        let idx = pos.checked_sub(1)?;
+        let unit_offset = offset.to_unit_offset(&units[idx].1)?;
+        Some((start + idx, unit_offset))
+    }
+
+    // out of range indices fall back to the first object, the unit lookup
+    // reports those
+    fn object(&self, header_idx: usize) -> &DwarfObject {
+        let object_idx = self.headers.get(header_idx).map_or(0, |(object_idx, _)| *object_idx);
+        &self.objects[object_idx]
+    }
+
+    fn unit(&self, header_idx: usize) -> Result<gimli::Unit<R>, Error> {
+        let (object_idx, header) = self.headers.get(header_idx).ok_or(gimli::Error::NoEntryAtGivenOffset)?;
+        Ok(self.objects[*object_idx].sections.unit(header.clone())?)
+    }
+
+    fn meta(&self, header_idx: usize, offset: UnitOffset) -> DwTypeMeta {
+        let unit_offset = match self.headers.get(header_idx).map(|(_, header)| header.offset()) {
+            Some(gimli::UnitSectionOffset::DebugInfoOffset(unit_offset)) => unit_offset.0,
+            Some(gimli::UnitSectionOffset::DebugTypesOffset(unit_offset)) => unit_offset.0,
+            None => 0
+        };
+        DwTypeMeta { offset, header_idx, unit_offset }
+    }
+
+    // all member and base class children of the aggregate at `meta`, in DIE
+    // order
+    fn members(&self, meta: &DwTypeMeta) -> Result<Vec<StructMember>, Error> {
+        let unit = self.unit(meta.header_idx)?;
+        let mut tree = unit.entries_tree(Some(meta.offset))?;
+        let root = tree.root()?;
+
+        let mut members = Vec::new();
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if is_member(entry)? {
+                members.push(self.parse_member(meta.header_idx, &unit, entry)?);
+            }
+        }
+        Ok(members)
+    }
+
+    fn nth_member(&self, meta: &DwTypeMeta, member_idx: usize) -> Result<Option<StructMember>, Error> {
+        let unit = self.unit(meta.header_idx)?;
+        let mut tree = unit.entries_tree(Some(meta.offset))?;
+        let root = tree.root()?;
+
+        // only direct children are members, nested type definitions and
+        // methods are skipped rather than ending the iteration
+        let mut children = root.children();
+        let mut idx = 0;
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if !is_member(entry)? {
+                continue;
+            }
+            if idx == member_idx {
+                let member = self.parse_member(meta.header_idx, &unit, entry)?;
+                return Ok(Some(member));
+            }
+            idx += 1;
+        }
+        Ok(None)
+    }
+
+    // member `name` of the struct or union at `meta`, also searching the
+    // members of anonymous structs and unions, with its offset added to `base`
+    fn find_member(&self, meta: &DwTypeMeta, name: &str, base: u64, depth: usize) -> Result<Option<(StructMember, u64)>, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        for mut member in self.members(meta)? {
+            if member.is_static {
+                continue;
+            }
+            let offset = base.saturating_add(member.offset);
+            if member.name.as_deref() == Some(name) {
+                return Ok(Some((member, offset)));
+            }
+            if member.name.is_some() {
+                continue;
+            }
+            if let Some(mb_type) = member.mb_type.take() {
+                if let Some(inner) = self.aggregate_meta(mb_type, depth + 1)? {
+                    if let Some(found) = self.find_member(&inner, name, offset, depth + 1)? {
+                        return Ok(Some(found));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn pointee_struct(&self, meta: &DwTypeMeta, follow_pointers: bool, depth: usize) -> Result<Option<Struct>, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let mut target = match self.resolve_meta_depth(meta, depth)? {
+            Some(target) => target,
+            None => return Ok(None)
+        };
+        // look through typedefs and qualifiers
+        for _ in depth..self.max_depth {
+            target = match target {
+                Type::Typedef(_) | Type::Const(_) => match self.get_type(target)? {
+                    Some(inner) => inner,
+                    None => return Ok(None)
+                },
+                _ => break
+            };
+        }
+        match target {
+            Type::Struct(t) => {
+                if let Some(definition) = self.struct_dict.get(&t.name) {
+                    return Ok(Some(definition.clone()));
+                }
+                Ok(Some(t))
+            }
+            Type::Pointer(t) if follow_pointers => self.pointee_struct(&t.meta, true, depth + 1),
+            _ => Ok(None)
+        }
+    }
+
+    // name and value of each DW_TAG_enumerator of the enum at `meta`
+    fn enumerators(&self, meta: &DwTypeMeta) -> Result<Vec<(String, EnumValue)>, Error> {
+        let unit = self.unit(meta.header_idx)?;
+        let mut tree = unit.entries_tree(Some(meta.offset))?;
+        let root = tree.root()?;
+        let signed = self.enum_is_signed(meta.header_idx, root.entry())?;
+
+        let mut enumerators = Vec::new();
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_enumerator {
+                continue;
+            }
+            let mut name = String::new();
+            let mut value = EnumValue::Int(0);
+            let mut attrs = entry.attrs();
+            while let Some(attr) = attrs.next()? {
+                match attr.name() {
+                    gimli::DW_AT_name => {
+                        name = self.name_attr_to_string(meta.header_idx, &attr)?.unwrap_or_default();
+                    }
+                    gimli::DW_AT_const_value => {
+                        value = enum_value(attr.value(), signed, self.endian(meta.header_idx))?;
+                    }
+                    _ => {}
+                }
+            }
+            enumerators.push((name, value));
+        }
+        Ok(enumerators)
+    }
+
+    // DW_ATE_signed or DW_ATE_unsigned depending on the enum's underlying type
+    fn enum_encoding(&self, meta: &DwTypeMeta) -> Result<gimli::DwAte, Error> {
+        let unit = self.unit(meta.header_idx)?;
+        let entry = unit.entry(meta.offset)?;
+        if self.enum_is_signed(meta.header_idx, &entry)? {
+            return Ok(gimli::DW_ATE_signed);
+        }
+        Ok(gimli::DW_ATE_unsigned)
+    }
+
+    // the encoding a value of `type_inst` is read with, through typedefs and
+    // qualifiers; pointers and references read as addresses
+    pub(crate) fn scalar_encoding(&self, type_inst: &Type, depth: usize) -> Result<Option<gimli::DwAte>, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let meta = match type_inst {
+            Type::Base(t) => return Ok(Some(t.encoding)),
+            Type::Enum(t) => return Ok(Some(self.enum_encoding(&t.meta)?)),
+            Type::Pointer(_) | Type::Reference(_) | Type::RvalueReference(_) => return Ok(Some(gimli::DW_ATE_address)),
+            Type::Typedef(t) => &t.meta,
+            Type::Const(t) => &t.meta,
+            Type::Unknown(t) if is_qualifier(t.tag) => &t.meta,
+            _ => return Ok(None)
+        };
+        match self.resolve_meta_depth(meta, depth + 1)? {
+            Some(inner) => self.scalar_encoding(&inner, depth + 1),
+            None => Ok(None)
+        }
+    }
+
+    // members of a forward declaration live in the definition
+    pub(crate) fn struct_definition<'a>(&'a self, t: &'a Struct) -> &'a Struct {
+        match self.struct_dict.get(&t.name) {
+            Some(definition) if t.declared_size().is_none() && !t.name.is_empty() => definition,
+            _ => t
+        }
+    }
+
+    // enums without an underlying type are C enums, i.e. int
+    fn enum_is_signed(&self, header_idx: usize, entry: &gimli::DebuggingInformationEntry<R>) -> Result<bool, Error> {
+        let encoding = match entry.attr_value(gimli::DW_AT_encoding)? {
+            Some(gimli::AttributeValue::Encoding(encoding)) => Some(encoding),
+            _ => match self.type_ref(header_idx, entry.attr_value(gimli::DW_AT_type)?) {
+                Some((header_idx, offset)) => match self.get_type_meta(header_idx, offset)? {
+                    Type::Base(base) => Some(base.encoding),
+                    _ => None
+                },
+                None => None
+            }
+        };
+        Ok(match encoding {
+            Some(encoding) => encoding == gimli::DW_ATE_signed || encoding == gimli::DW_ATE_signed_char,
+            None => true
+        })
+    }
+
+    fn exports_symbols(&self, meta: &DwTypeMeta) -> Result<bool, Error> {
+        let unit = self.unit(meta.header_idx)?;
+        let entry = unit.entry(meta.offset)?;
+        Ok(entry.attr_value(gimli::DW_AT_export_symbols)? == Some(gimli::AttributeValue::Flag(true)))
+    }
+
+    // whether the chain of typedefs, qualifiers, pointers and arrays from
+    // `type_inst` can be followed to the end without an error or an
+    // unsupported tag
+    fn type_resolves(&self, type_inst: &Type, depth: usize) -> bool {
+        if depth > self.max_depth {
+            return false;
+        }
+        let meta = match type_inst {
+            Type::Array(t) => return self.type_resolves(&t.element, depth + 1),
+            Type::Typedef(Typedef { meta, .. }) | Type::Const(Const { meta, .. }) |
+            Type::Pointer(Pointer { meta, .. }) | Type::Reference(Reference { meta, .. }) |
+            Type::RvalueReference(RvalueReference { meta, .. }) => meta,
+            Type::Unknown(t) if is_qualifier(t.tag) => &t.meta,
+            Type::Unknown(_) => return false,
+            _ => return true
+        };
+        match self.resolve_meta_depth(meta, depth + 1) {
+            Ok(Some(inner)) => self.type_resolves(&inner, depth + 1),
+            Ok(None) => true,
+            Err(_) => false
+        }
+    }
+
+    // the struct or union behind typedefs and qualifiers
+    fn aggregate_meta(&self, type_inst: Type, depth: usize) -> Result<Option<DwTypeMeta>, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        match type_inst {
+            Type::Struct(t) => Ok(Some(t.meta)),
+            Type::Union(t) => Ok(Some(t.meta)),
+            Type::Typedef(_) | Type::Const(_) => match self.get_type(type_inst)? {
+                Some(inner) => self.aggregate_meta(inner, depth + 1),
+                None => Ok(None)
+            },
+            _ => Ok(None)
+        }
+    }
+
+    // evaluate a DW_AT_data_member_location: a plain constant, a simple
+    // expression or a location list with a single entry. Anything else is
+    // reported instead of guessing offset 0
+    fn member_location(&self, header_idx: usize, unit: &gimli::Unit<R>, attr: &gimli::Attribute<R>, die_offset: u64) -> Result<u64, Error> {
+        let value = attr.value();
+        let unsupported = Error::UnsupportedLocation(die_offset);
+        if let Some(location) = value.udata_value() {
+            return Ok(location);
+        }
+        if let Some(expr) = value.exprloc_value() {
+            return expression_offset(unit, expr)?.ok_or(unsupported);
+        }
+        // a location list only makes sense for a member if the location is
+        // the same everywhere, i.e. there is just one entry
+        let dwarf = self.dwarf(header_idx);
+        if let Some(list_offset) = dwarf.attr_locations_offset(unit, value)? {
+            let mut locations = dwarf.locations(unit, list_offset)?;
+            if let (Some(entry), None) = (locations.next()?, locations.next()?) {
+                if let Some(location) = expression_offset(unit, entry.data)? {
+                    return Ok(location);
+                }
+            }
+        }
+        Err(unsupported)
+    }
+
+    // the address of a location that is a lone DW_OP_addr or DW_OP_addrx
+    fn location_address(&self, header_idx: usize, unit: &gimli::Unit<R>, attr: &gimli::Attribute<R>) -> Result<Option<u64>, Error> {
+        let expr = match attr.value().exprloc_value() {
+            Some(expr) => expr,
+            None => return Ok(None)
+        };
+        let mut ops = expr.operations(unit.encoding());
+        let address = match (ops.next()?, ops.next()?) {
+            (Some(gimli::Operation::Address { address }), None) => address,
+            (Some(gimli::Operation::AddressIndex { index }), None) => self.dwarf(header_idx).address(unit, index)?,
+            _ => return Ok(None)
+        };
+        Ok(Some(address))
+    }
+
+    // addresses of the static members defined in unit `header_idx`, by the
+    // offset of the member declaration
+    fn static_definitions(&self, header_idx: usize) -> Result<Rc<Map<UnitOffset, u64>>, Error> {
+        if let Some(definitions) = self.static_addresses.borrow().get(&header_idx) {
+            return Ok(definitions.clone());
+        }
+        let unit = self.unit(header_idx)?;
+        let mut definitions = Map::new();
+        let mut entries = unit.entries();
+        while let Some((_delta_depth, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_variable {
+                continue;
+            }
+            let declaration = match entry.attr_value(gimli::DW_AT_specification)? {
+                Some(gimli::AttributeValue::UnitRef(offset)) => offset,
+                _ => continue
+            };
+            if let Some(attr) = entry.attr(gimli::DW_AT_location)? {
+                if let Some(address) = self.location_address(header_idx, &unit, &attr)? {
+                    definitions.insert(declaration, address);
+                }
+            }
+        }
+        let definitions = Rc::new(definitions);
+        self.static_addresses.borrow_mut().insert(header_idx, definitions.clone());
+        Ok(definitions)
+    }
+
+    fn parse_member(&self, header_idx: usize, unit: &gimli::Unit<R>, entry: &gimli::DebuggingInformationEntry<R>) -> Result<StructMember, Error> {
+        let mut member = StructMember::new();
+        member.meta = self.meta(header_idx, entry.offset());
+        member.is_inheritance = entry.tag() == gimli::DW_TAG_inheritance;
+        let mut attrs = MemberAttrs::default();
+        let mut origin = self.member_attrs(header_idx, unit, entry, &mut member, &mut attrs)?;
+
+        // members of inlined or optimized code may leave their name and type
+        // to the DIE they are an instance of, attributes of the member itself
+        // take precedence
+        let mut depth = 0;
+        while let Some((origin_header_idx, origin_offset)) = origin {
+            depth += 1;
+            if depth > self.max_depth {
+                return Err(gimli::Error::TypeMismatch.into()) // FIXME
+            }
+            let origin_unit = self.unit(origin_header_idx)?;
+            let origin_entry = origin_unit.entry(origin_offset)?;
+            origin = self.member_attrs(origin_header_idx, &origin_unit, &origin_entry, &mut member, &mut attrs)?;
+        }
+        let MemberAttrs { location, data_bit_offset, bit_offset, declaration, address, .. } = attrs;
+
+        // dwarf 5 emits static data members as DW_TAG_variable, older
+        // versions as location-less external member declarations
+        member.is_static = entry.tag() == gimli::DW_TAG_variable || address.is_some()
+            || (location.is_none() && data_bit_offset.is_none() && (member.is_external || declaration));
+
+        // C++ defines them outside of the class, in a variable whose
+        // DW_AT_specification points back at the member
+        member.static_address = match address {
+            Some(address) => Some(address),
+            None if member.is_static => self.static_definitions(header_idx)?.get(&entry.offset()).copied(),
+            None => None
+        };
+
+        // union members usually have no location at all, bitfields in
+        // dwarf 4+ may only carry a bit offset
+        member.offset = match (location, data_bit_offset) {
+            (Some(offset), _) => offset,
+            (None, Some(bit_offset)) => bit_offset / 8,
+            (None, None) => 0
+        };
+
+        // members rarely carry their own size, take it from the type
+        if member.size == 0 {
+            if let Some(mb_type) = &member.mb_type {
+                member.size = self.type_size(mb_type, 0).unwrap_or(0);
+            }
+        }
+
+        member.bit_position = match (data_bit_offset, bit_offset) {
+            (Some(data_bit_offset), _) => data_bit_offset,
+            (None, Some(bit_offset)) => {
+                // dwarf 2/3 count from the most significant bit of the
+                // storage unit, which is the last byte on little endian
+                let storage_bits = member.size.saturating_mul(8);
+                let bit_size = member.bit_size.unwrap_or(0);
+                let bits = match self.endian(header_idx) {
+                    gimli::RunTimeEndian::Little => storage_bits.saturating_sub(bit_offset).saturating_sub(bit_size),
+                    gimli::RunTimeEndian::Big => bit_offset
+                };
+                member.offset.saturating_mul(8).saturating_add(bits)
+            }
+            (None, None) => member.offset.saturating_mul(8)
+        };
+
+        // the type of a zero-width bitfield only gives the alignment
+        if member.is_zero_width_bitfield() {
+            member.size = 0;
+        }
+        Ok(member)
+    }
+
+    // fill in the attributes of `entry` that aren't set yet, returns where
+    // its DW_AT_abstract_origin points
+    fn member_attrs(&self, header_idx: usize, unit: &gimli::Unit<R>, entry: &gimli::DebuggingInformationEntry<R>,
+                    member: &mut StructMember, found: &mut MemberAttrs) -> Result<Option<(usize, UnitOffset)>, Error> {
+        let mut attrs = entry.attrs();
+        let mut origin = None;
+        while let Some(attr) = attrs.next()? {
+            if attr.name() == gimli::DW_AT_abstract_origin {
+                origin = self.type_ref(header_idx, Some(attr.value()));
+                continue;
+            }
+            if found.seen.contains(&attr.name()) {
+                continue;
+            }
+            found.seen.push(attr.name());
+            match attr.name() {
+                gimli::DW_AT_type => {
+                    if let Some((type_header_idx, offset)) = self.type_ref(header_idx, Some(attr.value())) {
+                        member.mb_type = Some(self.get_type_meta(type_header_idx, offset)?);
+                    }
+                }
+                gimli::DW_AT_name => {
+                    member.name = self.name_attr_to_string(header_idx, &attr)?;
+                }
+                gimli::DW_AT_byte_size => {
+                    let member_size = attr.value().udata_value();
+                    member.size = member_size.unwrap_or(0);
+                }
+                gimli::DW_AT_data_member_location => {
+                    // an absolute address makes it a static member
+                    match self.location_address(header_idx, unit, &attr)? {
+                        Some(address) => found.address = Some(address),
+                        None => found.location = Some(self.member_location(header_idx, unit, &attr, member.meta.die_offset())?)
+                    }
+                }
+                gimli::DW_AT_location => {
+                    found.address = self.location_address(header_idx, unit, &attr)?;
+                }
+                gimli::DW_AT_data_bit_offset => {
+                    found.data_bit_offset = attr.value().udata_value();
+                }
+                gimli::DW_AT_bit_offset => {
+                    found.bit_offset = attr.value().udata_value();
+                }
+                gimli::DW_AT_bit_size => {
+                    member.bit_size = attr.value().udata_value();
+                }
+                gimli::DW_AT_external => {
+                    member.is_external = attr.value() == gimli::AttributeValue::Flag(true);
+                }
+                gimli::DW_AT_declaration => {
+                    found.declaration = attr.value() == gimli::AttributeValue::Flag(true);
+                }
+                gimli::DW_AT_artificial => {
+                    member.is_artificial = attr.value() == gimli::AttributeValue::Flag(true);
+                }
+                _ => {}
+            }
+        }
+        Ok(origin)
+    }
+
+    // the elements of multi-dimensional arrays are arrays themselves
+    fn array_size(&self, array: &Array, depth: usize) -> Result<u64, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let stride = match array.stride {
+            Some(stride) => stride,
+            None => self.type_size(&array.element, depth + 1)?
+        };
+        return Ok(array.element_count.saturating_mul(stride));
+    }
+
+    fn type_size(&self, type_inst: &Type, depth: usize) -> Result<u64, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let size = match type_inst {
+            Type::Typedef(t) if t.size != 0 => { t.size }
+            Type::Typedef(t) => {
+                match self.resolve_meta_depth(&t.meta, depth + 1)? {
+                    Some(inner) => self.type_size(&inner, depth + 1)?,
+                    None => 0
+                }
+            }
+            Type::Const(t) => {
+                match self.resolve_meta_depth(&t.meta, depth + 1)? {
+                    Some(inner) => self.type_size(&inner, depth + 1)?,
+                    None => 0
+                }
+            }
+            Type::Array(t) =>      { self.array_size(t, depth)? }
+            Type::Struct(t) =>     { t.size }
+            Type::Union(t) =>      { t.size }
+            Type::Base(t) =>       { t.size }
+            Type::Enum(t) =>       { t.size }
+            Type::Pointer(t) =>    { t.size }
+            Type::Reference(t) =>  { t.size }
+            Type::RvalueReference(t) => { t.size }
+            Type::Subroutine(t) => { t.size }
+            Type::Unspecified(t) => { t.size }
+            Type::FortranString(t) => { t.length.unwrap_or(0) }
+            Type::Void =>          { 0 }
+            // volatile, restrict and _Atomic have the size of what they qualify
+            Type::Unknown(t) if is_qualifier(t.tag) => {
+                match self.resolve_meta_depth(&t.meta, depth + 1)? {
+                    Some(inner) => self.type_size(&inner, depth + 1)?,
+                    None => 0
+                }
+            }
+            Type::Unknown(_) =>    { 0 }
+        };
+        Ok(size)
+    }
+
+    /// Number of structs in `struct_dict`, i.e. found by `load_structs`.
+    pub fn struct_count(&self) -> usize {
+        self.struct_dict.len()
+    }
+
+    /// Index the structs of every unit in `struct_dict`. Calling it again
+    /// after `add_object` only walks the units of the new objects, the
+    /// structs already indexed are kept as they are.
+    pub fn load_structs(&mut self) -> Result<(), Error> {
+        self.load_structs_with_progress(|_, _| {})
+    }
+
+    /// Same as `load_structs`, calling `progress(units_done, units_total)`
+    /// after each compilation unit.
+    pub fn load_structs_with_progress<F: FnMut(usize, usize)>(&mut self, mut progress: F) -> Result<(), Error> {
+        let total = self.headers.len();
+        for header_idx in self.indexed_units..total {
+            let unit = self.unit(header_idx)?;
+            let mut entries = unit.entries();
+            while let Some((_delta_depth, entry)) = entries.next_dfs()? {
+                if entry.tag() != gimli::DW_TAG_structure_type {
+                    continue;
+                }
+                self.load_struct(header_idx, entry)?;
+            }
+            progress(header_idx + 1, total);
+            self.indexed_units = header_idx + 1;
+        }
+        Ok(())
+    }
+
+    pub fn load_struct(&mut self, header_idx: usize, entry: &gimli::DebuggingInformationEntry<R>) -> Result<(), Error> {
+        if let Some(dw_struct) = self.struct_entry(header_idx, entry)? {
+            match self.struct_dict.entry(dw_struct.name.clone()) {
+                Entry::Occupied(mut dentry) if dentry.get().declaration && !dw_struct.declaration => {
+                    dentry.insert(dw_struct);
+                }
+                Entry::Occupied(_) if dw_struct.declaration => {}
+                Entry::Occupied(mut dentry) => {
+                    dentry.get_mut().refcnt += 1;
+                }
+                Entry::Vacant(dentry) => {
+                    self.demangled_names.entry(struct_query(&dw_struct.name)).or_insert_with(|| dw_struct.name.clone());
+                    dentry.insert(dw_struct);
+                }
+            };
+        }
+        Ok(())
+    }
+
+    // the struct a DW_TAG_structure_type DIE defines, None for anonymous
+    // structs and, unless include_declarations is set, declarations
+    fn struct_entry(&self, header_idx: usize, entry: &gimli::DebuggingInformationEntry<R>) -> Result<Option<Struct>, Error> {
+        let mut attrs = entry.attrs();
+        let mut struct_name: Option<String> = None;
+        let mut struct_size: Option<u64> = None;
+        let mut declaration = false;
+        while let Some(attr) = attrs.next()? {
+            match attr.name() {
+                gimli::DW_AT_name => {
+                    struct_name = self.name_attr_to_string(header_idx, &attr)?;
+                }
+                gimli::DW_AT_byte_size => {
+                    struct_size = attr.value().udata_value();
+                }
+                gimli::DW_AT_declaration if !self.include_declarations => {
+                    // just say empty set to declarations
+                    return Ok(None);
+                }
+                gimli::DW_AT_declaration => {
+                    declaration = attr.value() == gimli::AttributeValue::Flag(true);
+                }
+                _ => {}
+            }
+            // declarations have no size
+            if struct_name.is_some() && struct_size.is_some() {
+                break;
+            }
+        }
+        let dw_struct = struct_name.map(|name| {
+            let size = struct_size.unwrap_or(0);
+            let declared_size = struct_size;
+            let meta = self.meta(header_idx, entry.offset());
+            Struct{name, size, declared_size, declaration, meta, refcnt: 0}
+        });
+        Ok(dw_struct)
+    }
+
+    /// Resolve the members of every indexed struct into a `StructIndex` and
+    /// drop the dwarf sections. Call `load_structs` first.
+    pub fn into_index(self) -> Result<StructIndex, Error> {
+        let mut structs = Map::new();
+        for (name, dw_struct) in self.struct_dict.iter() {
+            let members = self.members(&dw_struct.meta)?.into_iter().filter(|member| !member.is_static).map(|member| {
+                IndexedMember {
+                    name: member.name,
+                    offset: member.offset,
+                    size: member.size,
+                    bit_size: member.bit_size
+                }
+            });
+            structs.insert(name.clone(), IndexedStruct {
+                name: dw_struct.name.clone(),
+                size: dw_struct.size,
+                members: members.collect()
+            });
+        }
+        Ok(StructIndex { structs })
+    }
+
+    /// Look up an indexed struct by name.
+    ///
+    /// Falls back to comparing the demangled forms of both the query and the
+    /// indexed names, ignoring whitespace, so `c++filt` output such as
+    /// `vector<int, std::allocator<int>>` matches the dwarf name.
+    ///
+    /// A parser from `new_lazy` looks up structs that are not in
+    /// `struct_dict` in the dwarf and remembers the result, so
+    /// `load_structs` doesn't have to be called first.
+    pub fn get_struct(&self, name: &str) -> Option<Struct> {
+        if let Some(dw_struct) = self.struct_dict.get(name) {
+            return Some(dw_struct.clone());
+        }
+        let query = struct_query(name);
+        if self.lazy {
+            if let Some(found) = self.lazy_structs.borrow().get(name) {
+                return found.clone();
+            }
+            // errors are a miss like for the other lookups
+            let found = self.find_struct(name, &query).unwrap_or(None);
+            self.lazy_structs.borrow_mut().insert(name.to_string(), found.clone());
+            if found.is_some() {
+                return found;
+            }
+        }
+        let found = self.demangled_names.get(&query).and_then(|name| self.struct_dict.get(name));
+        found.cloned()
+    }
+
+    // walk the units for the first definition of `name`, or failing that of
+    // a struct matching `query`. Stops at the first exact match, which is
+    // the definition load_structs would keep
+    fn find_struct(&self, name: &str, query: &str) -> Result<Option<Struct>, Error> {
+        let mut declaration = None;
+        let mut fallback = None;
+        for header_idx in 0..self.headers.len() {
+            let unit = self.unit(header_idx)?;
+            let mut entries = unit.entries();
+            while let Some((_delta_depth, entry)) = entries.next_dfs()? {
+                if entry.tag() != gimli::DW_TAG_structure_type {
+                    continue;
+                }
+                let dw_struct = match self.struct_entry(header_idx, entry)? {
+                    Some(dw_struct) => dw_struct,
+                    None => continue
+                };
+                if dw_struct.name == name && !dw_struct.declaration {
+                    return Ok(Some(dw_struct));
+                }
+                if dw_struct.name == name {
+                    declaration = declaration.or(Some(dw_struct));
+                } else if fallback.is_none() && struct_matches(&dw_struct, query) {
+                    fallback = Some(dw_struct);
+                }
+            }
+        }
+        Ok(declaration.or(fallback))
     }
 
-    fn get_array_bounds(&self, header_idx: usize, arr_offset: UnitOffset) -> Result<u64, gimli::Error> {
-        let mut iter = self.sections.units().skip(header_idx);
+    /// The type whose DIE is at `offset` from the start of unit
+    /// `header_idx`, see `UnitInfo::header_idx`.
+    pub fn type_at(&self, header_idx: usize, offset: u64) -> Result<Type, Error> {
+        let offset = usize::try_from(offset).map_err(|_| gimli::Error::NoEntryAtGivenOffset)?;
+        self.get_type_meta(header_idx, UnitOffset(offset))
+    }
+
+    /// The type whose DIE is at `offset` in `.debug_info` of the object the
+    /// parser was created from, the offsets printed by `llvm-dwarfdump` and
+    /// `readelf --debug-dump=info`.
+    pub fn type_at_global(&self, offset: u64) -> Result<Type, Error> {
+        self.resolve_global_in(0, offset)
+    }
 
-        if let Some(header) = iter.next()? {
-            let unit = self.sections.unit(header)?;
-            let mut nested_entries = unit.entries_at_offset(arr_offset)?;
-            let _ = nested_entries.next_dfs(); // skip one
-            if let Some(dfs) = nested_entries.next_dfs()? {
-                let type_dfs = dfs.1;
-                let tag = type_dfs.tag();
+    /// The type whose DIE is at `debug_info_offset` in `.debug_info`, looked
+    /// up in the objects in the order they were added until one has a unit
+    /// holding the offset. Offsets are only unique per object, use
+    /// `resolve_global_in` to pick one.
+    pub fn resolve_global(&self, debug_info_offset: u64) -> Result<Type, Error> {
+        for object_idx in 0..self.objects.len() {
+            match self.resolve_global_in(object_idx, debug_info_offset) {
+                Err(Error::Gimli(gimli::Error::NoEntryAtGivenOffset)) => continue,
+                result => return result
+            }
+        }
+        Err(gimli::Error::NoEntryAtGivenOffset.into())
+    }
 
-                // println!("    type tag: {}", type_dfs.tag());
+    /// Like `resolve_global` for the object `object_idx`, 0 for the one the
+    /// parser was created from and counting up for each `add_object`.
+    pub fn resolve_global_in(&self, object_idx: usize, debug_info_offset: u64) -> Result<Type, Error> {
+        let offset = usize::try_from(debug_info_offset).map_err(|_| gimli::Error::NoEntryAtGivenOffset)?;
+        // the units of an object are contiguous, any of them will do
+        let header_idx = self.headers.partition_point(|(idx, _)| *idx < object_idx);
+        if self.headers.get(header_idx).map(|(idx, _)| *idx) != Some(object_idx) {
+            return Err(gimli::Error::NoEntryAtGivenOffset.into());
+        }
+        match self.debug_info_ref(header_idx, gimli::DebugInfoOffset(offset)) {
+            Some((header_idx, unit_offset)) => self.get_type_meta(header_idx, unit_offset),
+            None => Err(gimli::Error::NoEntryAtGivenOffset.into())
+        }
+    }
 
-                let mut attrs = type_dfs.attrs();
-                match tag {
-                    gimli::DW_TAG_subrange_type => {
-                        while let Some(attr) = attrs.next()? {
-                            match attr.name() {
-                                gimli::DW_AT_upper_bound => {
-                                    return Ok(attr.value().udata_value().unwrap_or(0) + 1)
-                                }
-                                _ => {}
-                            }
-                           // println!("    type attr: {}", attr.name());
+    /// The type `type_inst` refers to. Pointers, typedefs and qualifiers
+    /// without a target resolve to `Type::Void`.
+    pub fn get_type(&self, type_inst: Type ) -> Result<Option<Type>, Error> {
+        //println!("get_type({:?})", type_inst);
+        let refers_to_void = matches!(type_inst, Type::Pointer(_) | Type::Typedef(_) | Type::Const(_));
+        let meta = match type_inst.get_meta() {
+            Some(meta) => meta,
+            None => return Ok(None)
+        };
+        let target = self.resolve_meta(&meta)?;
+        if target.is_none() && refers_to_void {
+            return Ok(Some(Type::Void));
+        }
+        Ok(target)
+    }
+
+    /// Structs with a member of type `type_name`, a const qualified one or an
+    /// array of it. Types are compared by `canonical_type_name`, e.g.
+    /// `spinlock_t` or `struct list_head`.
+    pub fn structs_containing_type(&self, type_name: &str) -> Result<Vec<&Struct>, Error> {
+        let mut found = Vec::new();
+        for (name, dw_struct) in self.struct_dict.iter() {
+            if self.struct_member_types(name, dw_struct)?.contains(type_name) {
+                found.push(dw_struct);
+            }
+        }
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(found)
+    }
+
+    fn struct_member_types(&self, name: &str, dw_struct: &Struct) -> Result<Rc<Set<String>>, Error> {
+        if let Some(types) = self.member_types.borrow().get(name) {
+            return Ok(types.clone());
+        }
+        let mut types = Set::new();
+        for member in self.members(&dw_struct.meta)? {
+            let mut mb_type = member.mb_type;
+            while let Some(type_inst) = mb_type {
+                types.insert(canonical_type_name(&type_inst, self));
+                mb_type = match type_inst {
+                    Type::Array(t) => Some(*t.element),
+                    Type::Const(_) => self.get_type(type_inst)?,
+                    _ => None
+                };
+            }
+        }
+        let types = Rc::new(types);
+        self.member_types.borrow_mut().insert(name.to_string(), types.clone());
+        Ok(types)
+    }
+
+    /// How many members of the indexed structs have each type, most used
+    /// first. Types are named by `canonical_type_name` and counted as
+    /// declared, so `int[4]` and `const int` are separate from `int`.
+    /// Static members are not counted.
+    pub fn type_usage_histogram(&self) -> Result<Vec<(String, usize)>, Error> {
+        // many members share a type DIE, name each one once
+        let mut names: Map<DwTypeMeta, String> = Map::new();
+        let mut counts: Map<String, usize> = Map::new();
+        for dw_struct in self.struct_dict.values() {
+            for member in self.members(&dw_struct.meta)? {
+                if member.is_static {
+                    continue;
+                }
+                let name = match &member.mb_type {
+                    Some(mb_type) => match mb_type.meta() {
+                        Some(meta) => names.entry(meta.clone()).or_insert_with(|| canonical_type_name(mb_type, self)).clone(),
+                        None => canonical_type_name(mb_type, self)
+                    },
+                    None => String::from("void")
+                };
+                *counts.entry(name).or_default() += 1;
+            }
+        }
+        let mut histogram: Vec<(String, usize)> = counts.into_iter().collect();
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(histogram)
+    }
+
+    /// Total hole and padding bytes of every indexed struct, worst first.
+    pub fn padding_report(&self) -> Result<Vec<(String, u64)>, Error> {
+        let mut report = Vec::new();
+        for (name, dw_struct) in self.struct_dict.iter() {
+            let holes = dw_struct.holes(self)?;
+            report.push((name.clone(), holes.iter().map(|hole| hole.size).sum::<u64>()));
+        }
+        report.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(report)
+    }
+
+    // natural alignment, assuming scalars are aligned to their size
+    fn type_alignment(&self, type_inst: &Type, depth: usize) -> Result<u64, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let align = match type_inst {
+            Type::Typedef(Typedef { meta, .. }) | Type::Const(Const { meta, .. }) => {
+                match self.resolve_meta_depth(meta, depth + 1)? {
+                    Some(inner) => self.type_alignment(&inner, depth + 1)?,
+                    None => 1
+                }
+            }
+            Type::Array(t) => self.type_alignment(&t.element, depth + 1)?,
+            Type::Base(Base { alignment: Some(alignment), .. }) => *alignment,
+            // arrays of characters
+            Type::FortranString(_) => 1,
+            // a declaration has no members, its definition does
+            Type::Struct(t) => self.members_alignment(&self.struct_definition(t).meta, depth)?,
+            Type::Union(t) => self.members_alignment(&t.meta, depth)?,
+            _ => self.type_size(type_inst, depth + 1)?
+        };
+        Ok(align.max(1))
+    }
+
+    // the largest alignment of the members of a struct or union, static
+    // members are stored outside of it
+    fn members_alignment(&self, meta: &DwTypeMeta, depth: usize) -> Result<u64, Error> {
+        let mut align = 1;
+        for member in self.members(meta)?.into_iter().filter(|member| !member.is_static) {
+            if let Some(mb_type) = &member.mb_type {
+                align = align.max(self.type_alignment(mb_type, depth + 1)?);
+            }
+        }
+        Ok(align)
+    }
+
+    fn resolve_meta(&self, meta: &DwTypeMeta) -> Result<Option<Type>, Error> {
+        self.resolve_meta_depth(meta, 0)
+    }
+
+    fn resolve_meta_depth(&self, meta: &DwTypeMeta, depth: usize) -> Result<Option<Type>, Error> {
+        let unit = self.unit(meta.header_idx)?;
+        let mut nested_entries = unit.entries_at_offset(meta.offset)?;
+
+        if let Some((_delta_depth, entry)) = nested_entries.next_dfs()? {
+            let mut attrs = entry.attrs();
+            while let Some(attr) = attrs.next()? {
+                match attr.name() {
+                    gimli::DW_AT_type => {
+                        if let Some((header_idx, offset)) = self.type_ref(meta.header_idx, Some(attr.value())) {
+                            let _type = self.get_type_meta_depth(header_idx, offset, depth)?;
+                            return Ok(Some(_type));
                         }
                     }
-                    _ => {
-                        return Err(gimli::Error::TypeMismatch) // FIXME
+                    _ => {}
+                }
+            }
+            return Ok(None);
+        }
+        // FIXME
+        Err(gimli::Error::TypeMismatch.into())
+    }
+
+    /// Every base type in the file, the first one seen for each name.
+    pub fn base_types(&self) -> Result<Vec<Base>, Error> {
+        let mut names = Set::new();
+        let mut base_types = Vec::new();
+        for header_idx in 0..self.headers.len() {
+            let unit = self.unit(header_idx)?;
+            let mut entries = unit.entries();
+            while let Some((_delta_depth, entry)) = entries.next_dfs()? {
+                if entry.tag() != gimli::DW_TAG_base_type {
+                    continue;
+                }
+                if let Type::Base(base) = self.get_type_meta(header_idx, entry.offset())? {
+                    if names.insert(base.name.clone()) {
+                        base_types.push(base);
                     }
                 }
             }
         }
-        Ok(0)
-    }
-
-    fn get_type_meta(&self, header_idx: usize, offset: UnitOffset) -> Result<Type, gimli::Error> {
-        let mut iter = self.sections.units().skip(header_idx);
-        let meta = DwTypeMeta { offset, header_idx };
-
-        if let Some(header) = iter.next()? {
-            let unit = self.sections.unit(header)?;
-            let mut nested_entries = unit.entries_at_offset(offset)?;
-            if let Some(dfs) = nested_entries.next_dfs()? {
-                let type_dfs = dfs.1;
-                let tag = type_dfs.tag();
-
-                // println!("    type tag: {}", type_dfs.tag());
-
-                let mut attrs = type_dfs.attrs();
-                match tag {
-                    gimli::DW_TAG_structure_type => {
-                        let mut size: u64 = 0;
-                        while let Some(attr) = attrs.next()? {
-                            // println!("    type attr: {}", attr.name());
-                            match attr.name() {
-                                gimli::DW_AT_name => {
-                                    if let Some(name) = name_attr_to_string(&self.sections.debug_str, &attr)? {
-                                        let entry = self.struct_dict.get(&name);
-                                        match entry {
-                                            Some(entry) => {
-                                                return Ok(Type::Struct( Struct {
-                                                    name: entry.name.to_string(),
-                                                    meta, size,
-                                                    refcnt: 0,
-                                                }));
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                gimli::DW_AT_byte_size => {
-                                    size = attr.value().udata_value().unwrap_or(0);
-                                }
-                                _ => { }
-                            }
+        Ok(base_types)
+    }
+
+    /// Every enum definition in the file, the first one seen for each name
+    /// and every anonymous enum, which are only told apart by their DIE
+    /// offset. Forward declarations are left out.
+    pub fn enums(&self) -> Result<Vec<Enum>, Error> {
+        let mut names = Set::new();
+        let mut enums = Vec::new();
+        for header_idx in 0..self.headers.len() {
+            let unit = self.unit(header_idx)?;
+            let mut entries = unit.entries();
+            while let Some((_delta_depth, entry)) = entries.next_dfs()? {
+                if entry.tag() != gimli::DW_TAG_enumeration_type {
+                    continue;
+                }
+                if let Some(gimli::AttributeValue::Flag(true)) = entry.attr_value(gimli::DW_AT_declaration)? {
+                    continue;
+                }
+                if let Type::Enum(dw_enum) = self.get_type_meta(header_idx, entry.offset())? {
+                    match &dw_enum.name {
+                        Some(name) if !names.insert(name.clone()) => {}
+                        _ => enums.push(dw_enum)
+                    }
+                }
+            }
+        }
+        Ok(enums)
+    }
+
+    /// The `DW_AT_producer` string of every unit that has one, e.g.
+    /// `"GNU C17 12.2.0 -g"` or `"clang version 16.0.0"`.
+    pub fn producers(&self) -> Result<Vec<String>, Error> {
+        let mut producers = Vec::new();
+        for header_idx in 0..self.headers.len() {
+            let unit = self.unit(header_idx)?;
+            if let Some(producer) = self.unit_producer(header_idx, &unit)? {
+                producers.push(producer);
+            }
+        }
+        Ok(producers)
+    }
+
+    /// Name, producer and code range of every compilation unit.
+    pub fn units(&self) -> Result<Vec<UnitInfo>, Error> {
+        let mut units = Vec::new();
+        for header_idx in 0..self.headers.len() {
+            let unit = self.unit(header_idx)?;
+            let dwarf = self.dwarf(header_idx);
+            let mut info = UnitInfo { header_idx, name: None, producer: None, low_pc: None, high_pc: None };
+            let mut high_pc = None;
+            let mut entries = unit.entries();
+            if let Some((_, root)) = entries.next_dfs()? {
+                let mut attrs = root.attrs();
+                while let Some(attr) = attrs.next()? {
+                    match attr.name() {
+                        gimli::DW_AT_name => {
+                            info.name = Some(dwarf.attr_string(&unit, attr.value())?.to_string_lossy()?.to_string());
+                        }
+                        gimli::DW_AT_producer => {
+                            info.producer = Some(dwarf.attr_string(&unit, attr.value())?.to_string_lossy()?.to_string());
+                        }
+                        gimli::DW_AT_low_pc => {
+                            info.low_pc = dwarf.attr_address(&unit, attr.value())?;
+                        }
+                        gimli::DW_AT_high_pc => {
+                            high_pc = Some(attr.value());
                         }
-                        // handle anon struct
-                        return Ok(Type::Struct(
-                                    Struct {
-                                        name: String::from("void"),
-                                        meta, size, refcnt: 1
-                                    }
-                                ));
+                        _ => {}
                     }
-                    gimli::DW_TAG_typedef => {
-                        let mut name: String = String::new();
-                        let mut size: u64 = 0;
-                        while let Some(attr) = attrs.next()? {
-                            // println!("    type attr: {}", attr.name());
-                            match attr.name() {
-                                gimli::DW_AT_name => {
-                                    name = name_attr_to_string(&self.sections.debug_str, &attr)?.unwrap_or(String::from("wtf"));
-                                }
-                                gimli::DW_AT_byte_size => {
-                                    size = attr.value().udata_value().unwrap_or(0);
-                                }
-                                _ => { }
+                }
+            }
+            // since dwarf 4 high_pc is usually the size of the range rather
+            // than an address
+            info.high_pc = match high_pc {
+                Some(gimli::AttributeValue::Addr(address)) => Some(address),
+                Some(value) => match (info.low_pc, value.udata_value()) {
+                    (Some(low_pc), Some(size)) => Some(low_pc.wrapping_add(size)),
+                    _ => None
+                },
+                None => None
+            };
+            units.push(info);
+        }
+        Ok(units)
+    }
+
+    fn unit_producer(&self, header_idx: usize, unit: &gimli::Unit<R>) -> Result<Option<String>, Error> {
+        let mut entries = unit.entries();
+        if let Some((_, root)) = entries.next_dfs()? {
+            if let Some(value) = root.attr_value(gimli::DW_AT_producer)? {
+                let producer = self.dwarf(header_idx).attr_string(unit, value)?;
+                return Ok(Some(producer.to_string_lossy()?.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    // the toolchain of unit `header_idx`, read from DW_AT_producer once
+    fn producer(&self, header_idx: usize, unit: &gimli::Unit<R>) -> Result<Producer, Error> {
+        if let Some(producer) = self.producers.borrow().get(&header_idx) {
+            return Ok(*producer);
+        }
+        let producer = match self.unit_producer(header_idx, unit)? {
+            Some(producer) => Producer::from_name(&producer),
+            None => Producer::Other
+        };
+        self.producers.borrow_mut().insert(header_idx, producer);
+        Ok(producer)
+    }
+
+    // element count and stride of each dimension, outermost first. An array
+    // without subranges has a single dimension of unknown bounds
+    fn get_array_bounds(&self, header_idx: usize, arr_offset: UnitOffset) -> Result<Vec<(u64, Option<u64>)>, Error> {
+        let unit = self.unit(header_idx)?;
+        let producer = self.producer(header_idx, &unit)?;
+        let mut tree = unit.entries_tree(Some(arr_offset))?;
+        let root = tree.root()?;
+
+        let mut dims = Vec::new();
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            match entry.tag() {
+                gimli::DW_TAG_subrange_type => {
+                    // gcc describes the bounds with DW_AT_upper_bound, clang
+                    // and rustc with DW_AT_count
+                    let mut upper_bound = None;
+                    let mut count = None;
+                    let mut stride = None;
+                    let mut index_type = None;
+                    let mut attrs = entry.attrs();
+                    while let Some(attr) = attrs.next()? {
+                        match attr.name() {
+                            gimli::DW_AT_upper_bound => {
+                                upper_bound = Some(attr.value());
+                            }
+                            gimli::DW_AT_type => {
+                                index_type = self.type_ref(header_idx, Some(attr.value()));
                             }
+                            gimli::DW_AT_count => {
+                                count = attr.value().udata_value();
+                            }
+                            gimli::DW_AT_byte_stride | gimli::DW_AT_bit_stride => {
+                                stride = stride_bytes(&attr);
+                            }
+                            _ => {}
                         }
-                        return Ok(Type::Typedef( Typedef { name, meta, size }));
                     }
-                    gimli::DW_TAG_pointer_type => {
-                        //while let Some(attr) = attrs.next()? {
-                        //    println!("    type attr: {}", attr.name());
-                        //}
-                        return Ok(Type::Pointer( Pointer{ meta, size: 8 } ));
+                    // -1 is all ones in the width of the index type, which
+                    // is usually sizetype
+                    let index_size = match index_type {
+                        Some((index_header_idx, index_offset)) => self.get_type_meta(index_header_idx, index_offset)?.size(self)?,
+                        None => unit.encoding().address_size.into()
+                    };
+                    let upper_bound = upper_bound.map(|value| upper_bound_count(value, index_size));
+                    let bounds = match producer {
+                        Producer::Llvm => count.or(upper_bound),
+                        _ => upper_bound.or(count)
+                    };
+                    dims.push((bounds.unwrap_or(0), stride));
+                }
+                _ => {
+                    return Err(gimli::Error::TypeMismatch.into()) // FIXME
+                }
+            }
+        }
+        if dims.is_empty() {
+            dims.push((0, None));
+        }
+        Ok(dims)
+    }
+
+    fn get_type_meta(&self, header_idx: usize, offset: UnitOffset) -> Result<Type, Error> {
+        self.get_type_meta_depth(header_idx, offset, 0)
+    }
+
+    // `depth` counts the typedef/array links followed to get here so that
+    // cyclic type chains end in an error instead of a stack overflow
+    fn get_type_meta_depth(&self, header_idx: usize, offset: UnitOffset, depth: usize) -> Result<Type, Error> {
+        if depth > self.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let meta = self.meta(header_idx, offset);
+
+        let unit = self.unit(header_idx)?;
+        let mut nested_entries = unit.entries_at_offset(offset)?;
+        if let Some(dfs) = nested_entries.next_dfs()? {
+            let type_dfs = dfs.1;
+            let tag = type_dfs.tag();
+
+            // with -fdebug-types-section a type is only a stub holding the
+            // signature of the type unit that defines it
+            if let Some((sig_header_idx, sig_offset)) = self.type_ref(header_idx, type_dfs.attr_value(gimli::DW_AT_signature)?) {
+                return self.get_type_meta_depth(sig_header_idx, sig_offset, depth + 1);
+            }
+
+            // println!("    type tag: {}", type_dfs.tag());
+
+            let mut attrs = type_dfs.attrs();
+            match tag {
+                gimli::DW_TAG_structure_type => {
+                    let mut declared_size = None;
+                    let mut declaration = false;
+                    let mut name: Option<String> = None;
+                    while let Some(attr) = attrs.next()? {
+                        // println!("    type attr: {}", attr.name());
+                        match attr.name() {
+                            gimli::DW_AT_name => {
+                                name = self.name_attr_to_string(header_idx, &attr)?;
+                            }
+                            gimli::DW_AT_byte_size => {
+                                declared_size = attr.value().udata_value();
+                            }
+                            gimli::DW_AT_declaration => {
+                                declaration = attr.value() == gimli::AttributeValue::Flag(true);
+                            }
+                            _ => { }
+                        }
                     }
-                    gimli::DW_TAG_const_type => {
-                        // while let Some(attr) = attrs.next()? {
-                        //     println!("    type attr: {}", attr.name());
-                        // }
-                        return Ok(Type::Const( Const{ meta, size: 8 } ));
+                    // anonymous structs get an empty name, declarations that
+                    // are never defined keep theirs
+                    return Ok(Type::Struct( Struct {
+                        name: name.unwrap_or_default(),
+                        size: declared_size.unwrap_or(0),
+                        declared_size, declaration, meta,
+                        refcnt: 0,
+                    }));
+                }
+                gimli::DW_TAG_typedef => {
+                    let mut name: String = String::new();
+                    let mut size: Option<u64> = None;
+                    let mut target = None;
+                    while let Some(attr) = attrs.next()? {
+                        // println!("    type attr: {}", attr.name());
+                        match attr.name() {
+                            gimli::DW_AT_name => {
+                                name = self.name_attr_to_string(header_idx, &attr)?.unwrap_or(String::from("wtf"));
+                            }
+                            gimli::DW_AT_byte_size => {
+                                size = attr.value().udata_value();
+                            }
+                            gimli::DW_AT_type => {
+                                target = self.type_ref(header_idx, Some(attr.value()));
+                            }
+                            _ => { }
+                        }
                     }
-                    gimli::DW_TAG_base_type => {
-                        let mut name: String = String::new();
-                        let size: u64 = 0;
-                        while let Some(attr) = attrs.next()? {
-                            // println!("    type attr: {}", attr.name());
-                            match attr.name() {
-                                gimli::DW_AT_name => {
-                                    name = name_attr_to_string(&self.sections.debug_str, &attr)?.unwrap_or(String::from("void"));
+                    // typedefs almost never carry a size, use the one of the
+                    // type they name
+                    let size = match (size, target) {
+                        (Some(size), _) => size,
+                        (None, Some((target_header_idx, target))) => {
+                            let target = self.get_type_meta_depth(target_header_idx, target, depth + 1)?;
+                            self.type_size(&target, depth + 1)?
+                        }
+                        (None, None) => 0
+                    };
+                    return Ok(Type::Typedef( Typedef { name, meta, size }));
+                }
+                gimli::DW_TAG_pointer_type => {
+                    let size = pointer_size(&unit, type_dfs)?;
+                    let address_class = match type_dfs.attr_value(gimli::DW_AT_address_class)? {
+                        Some(gimli::AttributeValue::AddressClass(class)) => Some(class.0),
+                        Some(value) => value.udata_value(),
+                        None => None
+                    };
+                    return Ok(Type::Pointer( Pointer{ meta, size, address_class } ));
+                }
+                gimli::DW_TAG_coarray_type => {
+                    // a Fortran coarray only adds codimensions across images,
+                    // each image holds a plain instance of the type
+                    return match self.type_ref(header_idx, type_dfs.attr_value(gimli::DW_AT_type)?) {
+                        Some((inner_header_idx, inner_offset)) => self.get_type_meta_depth(inner_header_idx, inner_offset, depth + 1),
+                        None => Ok(Type::Unknown( Unknown{ tag, meta } ))
+                    };
+                }
+                gimli::DW_TAG_reference_type => {
+                    let size = pointer_size(&unit, type_dfs)?;
+                    return Ok(Type::Reference( Reference{ meta, size } ));
+                }
+                gimli::DW_TAG_rvalue_reference_type => {
+                    let size = pointer_size(&unit, type_dfs)?;
+                    return Ok(Type::RvalueReference( RvalueReference{ meta, size } ));
+                }
+                gimli::DW_TAG_const_type => {
+                    // while let Some(attr) = attrs.next()? {
+                    //     println!("    type attr: {}", attr.name());
+                    // }
+                    return Ok(Type::Const( Const{ meta, size: 8 } ));
+                }
+                gimli::DW_TAG_base_type => {
+                    let mut name: String = String::new();
+                    let mut size: u64 = 0;
+                    let mut encoding = gimli::DwAte(0);
+                    let mut bit_size = None;
+                    let mut alignment = None;
+                    while let Some(attr) = attrs.next()? {
+                        // println!("    type attr: {}", attr.name());
+                        match attr.name() {
+                            gimli::DW_AT_name => {
+                                name = self.name_attr_to_string(header_idx, &attr)?.unwrap_or_default();
+                            }
+                            gimli::DW_AT_byte_size => {
+                                size = attr.value().udata_value().unwrap_or(0);
+                            }
+                            gimli::DW_AT_bit_size => {
+                                bit_size = attr.value().udata_value();
+                            }
+                            gimli::DW_AT_alignment => {
+                                alignment = attr.value().udata_value();
+                            }
+                            gimli::DW_AT_encoding => {
+                                if let gimli::AttributeValue::Encoding(value) = attr.value() {
+                                    encoding = value;
                                 }
-                                _ => { }
                             }
+                            _ => { }
                         }
-                        return Ok(Type::Base( Base{ name, size, meta } ))
                     }
-                    gimli::DW_TAG_union_type => {
-                        // mb_type.type_tag = MemberType::Union;
-                        let size = 0;
-                        // while let Some(attr) = attrs.next()? {
-                        //    println!("    type attr: {}", attr.name());
-                        // }
-                        return Ok(Type::Union( Union{ size, meta } ))
+                    // a type may give only its bit size
+                    if size == 0 {
+                        size = bit_size.unwrap_or(0).div_ceil(8);
                     }
-                    gimli::DW_TAG_array_type => {
-                        // Array types are immediately followed by a DW_TAG_subrange_type
-                        // which describes the array size in the upper_bound
-                        //while let Some(attr) = attrs.next()? {
-                        //   println!("    type attr: {}", attr.name());
-                        //}
-                        let bounds = self.get_array_bounds(header_idx, offset)?;
-                        // println!("bounds: {}", bounds);
-                        return Ok(Type::Array( Array{ size: bounds, meta } ))
+                    return Ok(Type::Base( Base{ name, size, encoding, bit_size, alignment, meta } ))
+                }
+                gimli::DW_TAG_union_type => {
+                    // mb_type.type_tag = MemberType::Union;
+                    let mut size = 0;
+                    let mut name = None;
+                    while let Some(attr) = attrs.next()? {
+                        match attr.name() {
+                            gimli::DW_AT_name => {
+                                name = self.name_attr_to_string(header_idx, &attr)?;
+                            }
+                            gimli::DW_AT_byte_size => {
+                                size = attr.value().udata_value().unwrap_or(0);
+                            }
+                            _ => { }
+                        }
                     }
-                    gimli::DW_TAG_enumeration_type => {
-                        // mb_type.type_tag = MemberType::Enum;
-                        let size = 0;
-                        let mut name = None;
-                        while let Some(attr) = attrs.next()? {
-                            // println!("    type attr: {}", attr.name());
-                            match attr.name() {
-                                gimli::DW_AT_name => {
-                                    name = name_attr_to_string(&self.sections.debug_str, &attr)?;
-                                }
-                                _ => { }
+                    return Ok(Type::Union( Union{ name, size, meta } ))
+                }
+                gimli::DW_TAG_array_type => {
+                    // Array types are immediately followed by a DW_TAG_subrange_type
+                    // which describes the array size in the upper_bound
+                    //while let Some(attr) = attrs.next()? {
+                    //   println!("    type attr: {}", attr.name());
+                    //}
+                    let mut dims = self.get_array_bounds(header_idx, offset)?;
+                    // println!("bounds: {:?}", dims);
+                    let mut stride = None;
+                    while let Some(attr) = attrs.next()? {
+                        match attr.name() {
+                            gimli::DW_AT_byte_stride | gimli::DW_AT_bit_stride => {
+                                stride = stride_bytes(&attr);
                             }
+                            _ => {}
+                        }
+                    }
+                    let mut element = match self.type_ref(header_idx, type_dfs.attr_value(gimli::DW_AT_type)?) {
+                        Some((elem_header_idx, elem_offset)) => {
+                            self.get_type_meta_depth(elem_header_idx, elem_offset, depth + 1)?
                         }
-                        return Ok(Type::Enum( Enum{ name, size, meta } ));
+                        None => Type::Unknown( Unknown{ tag: gimli::DwTag(0), meta: meta.clone() } )
+                    };
+                    // `int a[2][3]` is one DIE with a subrange per dimension,
+                    // made into an array of 2 arrays of 3 ints. The array's
+                    // own stride is the distance between the innermost elements
+                    let (mut bounds, subrange_stride) = dims.pop().unwrap_or((0, None));
+                    let mut stride = subrange_stride.or(stride);
+                    while let Some((outer_bounds, outer_stride)) = dims.pop() {
+                        element = Type::Array( Array{ element_count: bounds, element: Box::new(element), stride, meta: meta.clone() } );
+                        bounds = outer_bounds;
+                        stride = outer_stride;
                     }
-                    gimli::DW_TAG_subroutine_type => {
-                        // mb_type.type_tag = MemberType::Subroutine;
-                        let size = 0;
-                        //while let Some(attr) = attrs.next()? {
-                        //    println!("    type attr: {}", attr.name());
-                        //}
-                        return Ok(Type::Subroutine( Subroutine{ size, meta } ));
+                    return Ok(Type::Array( Array{ element_count: bounds, element: Box::new(element), stride, meta } ))
+                }
+                gimli::DW_TAG_enumeration_type => {
+                    // mb_type.type_tag = MemberType::Enum;
+                    let mut size = 0;
+                    let mut name = None;
+                    while let Some(attr) = attrs.next()? {
+                        // println!("    type attr: {}", attr.name());
+                        match attr.name() {
+                            gimli::DW_AT_name => {
+                                name = self.name_attr_to_string(header_idx, &attr)?;
+                            }
+                            gimli::DW_AT_byte_size => {
+                                size = attr.value().udata_value().unwrap_or(0);
+                            }
+                            _ => { }
+                        }
                     }
-                    gimli::DW_TAG_formal_parameter => {
-                        let size = 0;
-                        //while let Some(attr) = attrs.next()? {
-                        //    println!("    type attr: {}", attr.name());
-                        //}
-                        return Ok(Type::Subroutine( Subroutine{ size, meta } ));
+                    return Ok(Type::Enum( Enum{ name, size, meta } ));
+                }
+                gimli::DW_TAG_subroutine_type => {
+                    // mb_type.type_tag = MemberType::Subroutine;
+                    let size = 0;
+                    //while let Some(attr) = attrs.next()? {
+                    //    println!("    type attr: {}", attr.name());
+                    //}
+                    return Ok(Type::Subroutine( Subroutine{ size, meta } ));
+                }
+                gimli::DW_TAG_formal_parameter => {
+                    let size = 0;
+                    //while let Some(attr) = attrs.next()? {
+                    //    println!("    type attr: {}", attr.name());
+                    //}
+                    return Ok(Type::Subroutine( Subroutine{ size, meta } ));
+                }
+                gimli::DW_TAG_unspecified_type => {
+                    let mut name = None;
+                    let mut size = None;
+                    while let Some(attr) = attrs.next()? {
+                        match attr.name() {
+                            gimli::DW_AT_name => {
+                                name = self.name_attr_to_string(header_idx, &attr)?;
+                            }
+                            gimli::DW_AT_byte_size => {
+                                size = attr.value().udata_value();
+                            }
+                            _ => { }
+                        }
                     }
-                    _ => {
-                        while let Some(attr) = attrs.next()? {
-                            println!("    type attr: {}", attr.name());
+                    // compilers leave out the size of nullptr_t, it is
+                    // pointer sized
+                    let size = match (size, name.as_deref()) {
+                        (Some(size), _) => size,
+                        (None, Some("decltype(nullptr)")) => unit.header.address_size() as u64,
+                        (None, _) => 0
+                    };
+                    return Ok(Type::Unspecified( Unspecified{ name, size, meta } ));
+                }
+                gimli::DW_TAG_string_type => {
+                    // DW_AT_string_length is a location, the length isn't
+                    // known without a running process
+                    let mut length = None;
+                    while let Some(attr) = attrs.next()? {
+                        if attr.name() == gimli::DW_AT_byte_size {
+                            length = attr.value().udata_value();
                         }
                     }
+                    return Ok(Type::FortranString( FortranString{ length, meta } ));
+                }
+                // including vendor extensions such as DW_TAG_GNU_call_site,
+                // see Unknown::is_vendor_extension and is_gnu_extension
+                _ => {
+                    return Ok(Type::Unknown( Unknown{ tag, meta } ));
                 }
             }
         }
         // FIXME
-        Err(gimli::Error::TypeMismatch)
+        Err(gimli::Error::TypeMismatch.into())
+    }
+
+}
+
+#[cfg(feature = "demangle")]
+fn demangle(name: &str) -> Option<String> {
+    if let Ok(symbol) = rustc_demangle::try_demangle(name) {
+        return Some(format!("{:#}", symbol));
+    }
+    if name.starts_with("_Z") {
+        let symbol = cpp_demangle::Symbol::new(name).ok()?;
+        return symbol.demangle(&cpp_demangle::DemangleOptions::default()).ok();
+    }
+    None
+}
+
+#[cfg(not(feature = "demangle"))]
+fn demangle(_name: &str) -> Option<String> {
+    None
+}
+
+// DW_AT_byte_stride or DW_AT_bit_stride in bytes, None for strides that
+// are computed at runtime
+fn stride_bytes(attr: &gimli::Attribute<R>) -> Option<u64> {
+    let stride = attr.value().udata_value()?;
+    if attr.name() == gimli::DW_AT_bit_stride {
+        return Some(stride.div_ceil(8));
+    }
+    Some(stride)
+}
+
+// explicit DW_AT_byte_size of a pointer like DIE, used for unusual address
+// spaces, otherwise the address size of the unit
+fn pointer_size(unit: &gimli::Unit<R>, entry: &gimli::DebuggingInformationEntry<R>) -> Result<u64, Error> {
+    match entry.attr_value(gimli::DW_AT_byte_size)?.and_then(|value| value.udata_value()) {
+        Some(size) => Ok(size),
+        None => Ok(unit.header.address_size() as u64)
+    }
+}
+
+// volatile, restrict and _Atomic, which are read as Type::Unknown
+pub(crate) fn is_qualifier(tag: gimli::DwTag) -> bool {
+    tag == gimli::DW_TAG_volatile_type || tag == gimli::DW_TAG_restrict_type || tag == gimli::DW_TAG_atomic_type
+}
+
+// data members and the base classes at a fixed offset, virtual base classes
+// are placed at run time
+fn is_member(entry: &gimli::DebuggingInformationEntry<R>) -> Result<bool, Error> {
+    let member = match entry.tag() {
+        gimli::DW_TAG_member | gimli::DW_TAG_variable => true,
+        gimli::DW_TAG_inheritance => !matches!(entry.attr_value(gimli::DW_AT_virtuality)?,
+                                               Some(gimli::AttributeValue::Virtuality(virtuality)) if virtuality != gimli::DW_VIRTUALITY_none),
+        _ => false
+    };
+    Ok(member)
+}
+
+// what get_struct compares demangled struct names against
+fn struct_query(name: &str) -> String {
+    strip_whitespace(&demangle(name).unwrap_or_else(|| name.to_string()))
+}
+
+fn struct_matches(dw_struct: &Struct, query: &str) -> bool {
+    let name = dw_struct.demangled_name().unwrap_or_else(|| dw_struct.name.clone());
+    strip_whitespace(&name) == query
+}
+
+fn strip_whitespace(name: &str) -> String {
+    name.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// element count from an inclusive DW_AT_upper_bound, flexible array members
+// written as `[0]` by older compilers have an upper bound of -1. The form
+// alone doesn't tell -1 apart, gcc writes the bound of `[256]` as data1 0xff,
+// so unsigned bounds are only -1 if all `index_size` bytes are ones
+fn upper_bound_count(value: gimli::AttributeValue<R>, index_size: u64) -> u64 {
+    let minus_one = match index_size {
+        1..=8 => u64::MAX >> (64 - index_size * 8),
+        _ => u64::MAX
+    };
+    match value {
+        gimli::AttributeValue::Sdata(bound) if bound < 0 => 0,
+        _ => match value.udata_value() {
+            Some(bound) if bound == minus_one => 0,
+            Some(bound) => bound.saturating_add(1),
+            None => 0
+        }
     }
+}
 
+fn enum_value(value: gimli::AttributeValue<R>, signed: bool, endian: gimli::RunTimeEndian) -> Result<EnumValue, Error> {
+    match value {
+        gimli::AttributeValue::Sdata(value) => Ok(EnumValue::Int(value)),
+        gimli::AttributeValue::Udata(value) => Ok(EnumValue::from_le_bytes(value.to_le_bytes().to_vec(), false)),
+        // fixed size forms hold the bits of the value, sign extended from
+        // the width of the form in a signed enum
+        gimli::AttributeValue::Data1(value) => Ok(EnumValue::from_le_bytes(value.to_le_bytes().to_vec(), signed)),
+        gimli::AttributeValue::Data2(value) => Ok(EnumValue::from_le_bytes(value.to_le_bytes().to_vec(), signed)),
+        gimli::AttributeValue::Data4(value) => Ok(EnumValue::from_le_bytes(value.to_le_bytes().to_vec(), signed)),
+        gimli::AttributeValue::Data8(value) => Ok(EnumValue::from_le_bytes(value.to_le_bytes().to_vec(), signed)),
+        // DW_FORM_data16 and blocks hold 128 bit values in target order
+        gimli::AttributeValue::Block(block) => {
+            let mut bytes = block.to_slice()?.into_owned();
+            if endian == gimli::RunTimeEndian::Big {
+                bytes.reverse();
+            }
+            Ok(EnumValue::from_le_bytes(bytes, signed))
+        }
+        _ => Err(gimli::Error::UnsupportedAttributeForm.into())
+    }
 }
 
-fn name_attr_to_string(debug_str: &gimli::DebugStr<R>, attr: &gimli::Attribute<gimli::EndianReader<gimli::LittleEndian, Rc<[u8]>>>) -> Result<Option<String>, gimli::Error> {
-    let name = match attr.value() {
-        gimli::AttributeValue::String(val) => {
-            Some(val.to_string_lossy()?.to_string())
+// coalesce holes that are only separated by zero sized members, which are
+// moved after the merged hole
+fn merge_adjacent_holes(items: Vec<LayoutItem>) -> Vec<LayoutItem> {
+    let mut merged = Vec::with_capacity(items.len());
+    let mut pending: Option<Hole> = None;
+    let mut empty_members = Vec::new();
+    for item in items {
+        match item {
+            LayoutItem::Hole(hole) => match pending.as_mut() {
+                Some(last) if last.offset.saturating_add(last.size) == hole.offset => {
+                    last.size += hole.size;
+                    last.trailing = hole.trailing;
+                    last.separator = hole.separator;
+                }
+                _ => {
+                    merged.extend(pending.take().map(LayoutItem::Hole));
+                    merged.append(&mut empty_members);
+                    pending = Some(hole);
+                }
+            },
+            LayoutItem::Member(member) if pending.is_some() && member.size == 0 && member.bit_size.is_none() => {
+                empty_members.push(LayoutItem::Member(member));
+            }
+            member => {
+                merged.extend(pending.take().map(LayoutItem::Hole));
+                merged.append(&mut empty_members);
+                merged.push(member);
+            }
         }
-        gimli::AttributeValue::DebugStrRef(val) => {
-            Some(debug_str.get_str(val)?.to_string_lossy()?.to_string())
+    }
+    merged.extend(pending.map(LayoutItem::Hole));
+    merged.append(&mut empty_members);
+    merged
+}
+
+// sum the constants of a simple location expression like
+// `DW_OP_plus_uconst 8`, None for anything that needs a real evaluator
+fn expression_offset(unit: &gimli::Unit<R>, expr: gimli::Expression<R>) -> Result<Option<u64>, Error> {
+    let mut offset: u64 = 0;
+    let mut ops = expr.operations(unit.encoding());
+    while let Some(op) = ops.next()? {
+        match op {
+            gimli::Operation::PlusConstant { value } |
+            gimli::Operation::UnsignedConstant { value } => {
+                offset = offset.wrapping_add(value);
+            }
+            gimli::Operation::Plus => {}
+            _ => return Ok(None)
         }
-        _ => {None}
-    };
-    Ok(name)
+    }
+    Ok(Some(offset))
 }
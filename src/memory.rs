@@ -0,0 +1,92 @@
+// debug sections read from memory instead of a file, see Parser::from_memory
+
+use std::vec;
+use std::vec::Vec;
+
+use crate::{Error, Map, Parser, ParserBuilder};
+
+/// A source of bytes addressed by absolute position, such as
+/// `/proc/<pid>/mem` of a live process or a core dump.
+///
+/// `Parser::from_memory` reads each debug section once with a single call,
+/// so implementations don't need to cache. A byte slice or vector is read
+/// with addresses as indices, and a `File` with addresses as file offsets,
+/// which is how `/proc/<pid>/mem` maps the target's address space.
+pub trait MemoryReader {
+    /// Fill `buf` with the bytes starting at `address`, failing if any of
+    /// them can't be read.
+    fn read_at(&mut self, address: u64, buf: &mut [u8]) -> std::io::Result<()>;
+}
+
+impl MemoryReader for &[u8] {
+    fn read_at(&mut self, address: u64, buf: &mut [u8]) -> std::io::Result<()> {
+        let data = usize::try_from(address).ok()
+            .and_then(|start| Some(start..start.checked_add(buf.len())?))
+            .and_then(|range| self.get(range));
+        match data {
+            Some(data) => {
+                buf.copy_from_slice(data);
+                Ok(())
+            }
+            None => Err(std::io::ErrorKind::UnexpectedEof.into())
+        }
+    }
+}
+
+impl MemoryReader for Vec<u8> {
+    fn read_at(&mut self, address: u64, buf: &mut [u8]) -> std::io::Result<()> {
+        self.as_slice().read_at(address, buf)
+    }
+}
+
+#[cfg(unix)]
+impl MemoryReader for std::fs::File {
+    fn read_at(&mut self, address: u64, buf: &mut [u8]) -> std::io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(self, buf, address)
+    }
+}
+
+/// Where the debug sections are in a `MemoryReader`, as the address and
+/// size of each. Sections that are not listed are treated as empty, the
+/// same as with `ParserBuilder`.
+#[derive(Clone, Debug, Default)]
+pub struct SectionLayout {
+    sections: Map<gimli::SectionId, (u64, u64)>,
+    endian: Option<gimli::RunTimeEndian>,
+}
+
+impl SectionLayout {
+    pub fn new() -> SectionLayout {
+        SectionLayout::default()
+    }
+
+    pub fn section(mut self, id: gimli::SectionId, address: u64, size: u64) -> SectionLayout {
+        self.sections.insert(id, (address, size));
+        self
+    }
+
+    /// Byte order of the sections, little endian by default.
+    pub fn endian(mut self, endian: gimli::RunTimeEndian) -> SectionLayout {
+        self.endian = Some(endian);
+        self
+    }
+}
+
+impl Parser {
+    /// Build a parser from debug sections that are mapped somewhere other
+    /// than a file, e.g. in a ptrace target. The sections are copied out of
+    /// `reader` up front, the reader is not used after this returns.
+    pub fn from_memory<M: MemoryReader>(mut reader: M, layout: SectionLayout) -> Result<Parser, Error> {
+        let mut builder = ParserBuilder::new();
+        if let Some(endian) = layout.endian {
+            builder = builder.endian(endian);
+        }
+        for (id, (address, size)) in layout.sections {
+            let size = usize::try_from(size).map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+            let mut data = vec![0; size];
+            reader.read_at(address, &mut data)?;
+            builder = builder.section(id, data);
+        }
+        builder.build()
+    }
+}
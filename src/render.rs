@@ -0,0 +1,493 @@
+// C declarations for structs, in the style of pahole's output
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{is_qualifier, Const, DwTypeMeta, Error, LayoutItem, Parser, Struct, StructMember, Subroutine, Type, Unknown, DEFAULT_MAX_DEPTH};
+
+/// How `Struct::to_c_decl_with` shows arrays without elements, such as
+/// flexible array members.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlexibleArrayStyle {
+    /// `char data[];`
+    Empty,
+    /// `char data[0];`, the GNU zero-length array spelling.
+    Zero,
+    /// Leave the member out.
+    Omit,
+}
+
+/// Options for `Struct::to_c_decl_with`.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    die_offsets: bool,
+    max_depth: usize,
+    flexible_array_style: FlexibleArrayStyle,
+    typedef_sizes: bool,
+    // emit valid C: anonymous aggregates are inlined, bitfields get their
+    // width, used by emit_header
+    pub(crate) compilable: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            die_offsets: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            flexible_array_style: FlexibleArrayStyle::Empty,
+            typedef_sizes: false,
+            compilable: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn new() -> RenderOptions {
+        RenderOptions::default()
+    }
+
+    pub(crate) fn compilable() -> RenderOptions {
+        RenderOptions { compilable: true, ..RenderOptions::default() }
+    }
+
+    /// Annotate the struct and its members with their DIE offsets.
+    pub fn die_offsets(mut self, die_offsets: bool) -> RenderOptions {
+        self.die_offsets = die_offsets;
+        self
+    }
+
+    /// How many pointer, array, qualifier and inlined type levels a member
+    /// type may have before the rest is rendered as `...`. Defaults to
+    /// `DEFAULT_MAX_DEPTH`.
+    pub fn max_depth(mut self, max_depth: usize) -> RenderOptions {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// How arrays without elements are shown, `FlexibleArrayStyle::Empty`
+    /// by default. DWARF doesn't tell `[]` from `[0]` apart reliably, so
+    /// pick whichever the source uses.
+    pub fn flexible_array_style(mut self, style: FlexibleArrayStyle) -> RenderOptions {
+        self.flexible_array_style = style;
+        self
+    }
+
+    /// Keep typedef names but note the size they resolve to after the
+    /// member, e.g. `u64 addr; /* 8 bytes */`. Arrays of typedefs get the
+    /// size of one element.
+    pub fn typedef_sizes(mut self, typedef_sizes: bool) -> RenderOptions {
+        self.typedef_sizes = typedef_sizes;
+        self
+    }
+}
+
+// one line of the struct body, the comment is aligned across all lines
+struct Line {
+    decl: String,
+    comment: Option<String>,
+}
+
+impl Struct {
+    /// Render the struct as a C declaration with `/* offset size */`
+    /// comments after each member and the holes between them.
+    pub fn to_c_decl(&self, parser: &Parser) -> Result<String, Error> {
+        self.to_c_decl_with(parser, &RenderOptions::default())
+    }
+
+    pub fn to_c_decl_with(&self, parser: &Parser, options: &RenderOptions) -> Result<String, Error> {
+        let mut lines = Vec::new();
+
+        // static members take no space, list them before the layout
+        for member in parser.members(&self.meta)? {
+            if member.is_static {
+                let comment = die_offset_comment(&member, options);
+                let decl = format!("static {}", member_decl(parser, member, options)?);
+                lines.push(Line { decl, comment });
+            }
+        }
+
+        for item in self.layout(parser)? {
+            match item {
+                LayoutItem::Member(member) if omitted(&member, options) => {}
+                LayoutItem::Member(member) => {
+                    let mut comment = format!("{:5} {:5}", member.offset, member.size);
+                    if let Some(die_offset) = die_offset_comment(&member, options) {
+                        comment = format!("{} {}", comment, die_offset);
+                    }
+                    let decl = member_decl(parser, member, options)?;
+                    lines.push(Line { decl, comment: Some(comment) });
+                }
+                LayoutItem::Hole(hole) => {
+                    if hole.trailing {
+                        lines.push(Line { decl: format!("/* padding: {} */", hole.size), comment: None });
+                    } else if hole.packed {
+                        lines.push(Line { decl: format!("/* XXX {} bytes hole, packed, no padding expected */", hole.size), comment: None });
+                    } else if hole.separator {
+                        lines.push(Line { decl: format!("/* XXX {} bytes hole, aligned by zero-width bitfield */", hole.size), comment: None });
+                    } else {
+                        lines.push(Line { decl: format!("/* XXX {} bytes hole, try to pack */", hole.size), comment: None });
+                    }
+                }
+            }
+        }
+
+        let width = lines.iter().filter(|line| line.comment.is_some()).map(|line| line.decl.len()).max().unwrap_or(0);
+        let mut out = if options.die_offsets {
+            format!("struct {} {{ /* {:#x} */\n", self.name, self.die_offset())
+        } else {
+            format!("struct {} {{\n", self.name)
+        };
+        for line in lines {
+            match line.comment {
+                Some(comment) => out.push_str(&format!("\t{:width$} /* {} */\n", line.decl, comment, width = width)),
+                None => out.push_str(&format!("\t{}\n", line.decl))
+            }
+        }
+        out.push_str(&format!("\n\t/* size: {} */\n}};\n", self.size));
+        Ok(out)
+    }
+}
+
+fn omitted(member: &StructMember, options: &RenderOptions) -> bool {
+    if options.flexible_array_style != FlexibleArrayStyle::Omit {
+        return false;
+    }
+    matches!(member.mb_type, Some(Type::Array(ref arr_type)) if arr_type.element_count == 0)
+}
+
+fn die_offset_comment(member: &StructMember, options: &RenderOptions) -> Option<String> {
+    if !options.die_offsets {
+        return None;
+    }
+    Some(format!("{:#x}", member.die_offset()))
+}
+
+// `type name;` for a member, members without a name or type are rendered
+// as best we can
+pub(crate) fn member_decl(parser: &Parser, member: StructMember, options: &RenderOptions) -> Result<String, Error> {
+    member_decl_depth(parser, member, options, 0)
+}
+
+fn member_decl_depth(parser: &Parser, member: StructMember, options: &RenderOptions, depth: usize) -> Result<String, Error> {
+    let name = member.name.unwrap_or_default();
+    let typedef_size = match &member.mb_type {
+        Some(mb_type) if options.typedef_sizes => typedef_size(parser, mb_type, depth)?,
+        _ => None
+    };
+    let mut decl = match member.mb_type {
+        Some(mb_type) => get_member_string_depth(parser, mb_type, &name, 0, options, depth)?,
+        None => name
+    };
+    // the member's type is the storage unit of a bitfield, e.g. the
+    // `unsigned int` of `unsigned int x : 3`
+    if let Some(bit_size) = member.bit_size {
+        decl = format!("{} : {}", decl.trim_end(), bit_size);
+    }
+    // base classes are listed the way pahole does
+    if member.is_inheritance {
+        return Ok(format!("/* {} <ancestor>; */", decl.trim_end()));
+    }
+    match typedef_size {
+        Some(size) => Ok(format!("{}; /* {} bytes */", decl.trim_end(), size)),
+        None => Ok(format!("{};", decl.trim_end()))
+    }
+}
+
+// size of the typedef a member is declared with, looking through
+// qualifiers and arrays but not pointers
+fn typedef_size(parser: &Parser, mb_type: &Type, depth: usize) -> Result<Option<u64>, Error> {
+    if depth > parser.max_depth {
+        return Err(gimli::Error::TypeMismatch.into()) // FIXME
+    }
+    match mb_type {
+        Type::Typedef(_) => Ok(Some(mb_type.size(parser)?)),
+        Type::Array(t) => typedef_size(parser, &t.element, depth + 1),
+        Type::Const(Const { meta, .. }) => match parser.resolve_meta(meta)? {
+            Some(inner) => typedef_size(parser, &inner, depth + 1),
+            None => Ok(None)
+        },
+        Type::Unknown(Unknown { tag, meta }) if is_qualifier(*tag) => {
+            match parser.resolve_meta(meta)? {
+                Some(inner) => typedef_size(parser, &inner, depth + 1),
+                None => Ok(None)
+            }
+        }
+        _ => Ok(None)
+    }
+}
+
+// `{ int a; long b; }` for an anonymous struct or union
+fn inline_body(parser: &Parser, meta: &DwTypeMeta, options: &RenderOptions, depth: usize) -> Result<String, Error> {
+    let mut decls = Vec::new();
+    for member in parser.members(meta)? {
+        if !member.is_static && !omitted(&member, options) {
+            decls.push(member_decl_depth(parser, member, options, depth)?);
+        }
+    }
+    Ok(format!("{{ {} }}", decls.join(" ")))
+}
+
+// `{ A = 0, B = 1 }` for an enum
+pub(crate) fn enum_body(parser: &Parser, meta: &DwTypeMeta) -> Result<String, Error> {
+    let enumerators = parser.enumerators(meta)?.into_iter().map(|(name, value)| {
+        format!("{} = {}", name, value)
+    });
+    Ok(format!("{{ {} }}", enumerators.collect::<Vec<_>>().join(", ")))
+}
+
+// anonymous members marked with DW_AT_export_symbols are shown with their
+// members since those are accessed as if they belonged to the parent
+fn promoted(parser: &Parser, meta: &DwTypeMeta, mb_name: &str, level: u8) -> Result<bool, Error> {
+    if level != 0 || !mb_name.is_empty() {
+        return Ok(false);
+    }
+    parser.exports_symbols(meta)
+}
+
+// recursive string builder
+pub(crate) fn get_member_string(parser: &Parser, mb_type: Type, mb_name: &str, level: u8, options: &RenderOptions) -> Result<String, Error> {
+    get_member_string_depth(parser, mb_type, mb_name, level, options, 0)
+}
+
+// the declarator is built inside-out: `declarator` holds everything between
+// the base type and the end of the declaration so far, e.g. `*table[8]`,
+// and each level wraps it before handing it to the type it refers to.
+// `level` is the position in the declarator, `depth` counts every type
+// followed so far including inlined bodies and subroutine parameters
+fn get_member_string_depth(parser: &Parser, mb_type: Type, declarator: &str, level: u8, options: &RenderOptions, depth: usize) -> Result<String, Error> {
+    if depth > options.max_depth {
+        return Ok(format!("... {}", declarator));
+    }
+    match mb_type {
+        Type::Struct(struct_type) => {
+            let struct_name = if !struct_type.name.is_empty() {
+                struct_type.name
+            } else if options.compilable || promoted(parser, &struct_type.meta, declarator, level)? {
+                inline_body(parser, &struct_type.meta, options, depth + 1)?
+            } else {
+                String::from("{...}")
+            };
+            return Ok(format!("struct {} {}", struct_name, declarator));
+        }
+        Type::Base(base_type) => {
+            return Ok(format!("{} {}", base_type.name, declarator));
+        }
+        Type::Typedef(typedef_type) => {
+            return Ok(format!("{} {}", typedef_type.name, declarator));
+        }
+        Type::Const(Const { ref meta, .. }) => {
+            return qualified_string(parser, "const", meta, declarator, level, options, depth);
+        }
+        Type::Pointer(_) => {
+            if let Some(inner_type) = parser.get_type(mb_type)? {
+                let declarator = pointer_declarator("*", declarator, &inner_type);
+                // function pointers wrap the name: ret (*name)(params)
+                if let Type::Subroutine(ref subroutine) = inner_type {
+                    return get_subroutine_string(parser, subroutine, &declarator, options, depth + 1);
+                }
+                return get_member_string_depth(parser, inner_type, &declarator, level+1, options, depth + 1);
+            }
+        }
+        Type::Reference(_) => {
+            if let Some(inner_type) = parser.get_type(mb_type)? {
+                let declarator = pointer_declarator("&", declarator, &inner_type);
+                return get_member_string_depth(parser, inner_type, &declarator, level+1, options, depth + 1);
+            }
+        }
+        Type::RvalueReference(_) => {
+            if let Some(inner_type) = parser.get_type(mb_type)? {
+                let declarator = pointer_declarator("&&", declarator, &inner_type);
+                return get_member_string_depth(parser, inner_type, &declarator, level+1, options, depth + 1);
+            }
+        }
+        Type::Enum(ref enum_type) => {
+            if let Some(enum_name) = &enum_type.name {
+                return Ok(format!("enum {} {}", enum_name, declarator));
+            }
+            // anonymous enums are only known by their values
+            let body = enum_body(parser, &enum_type.meta)?;
+            return Ok(format!("enum {} {}", body, declarator));
+        }
+        Type::Array(arr_type) => {
+            let declarator = if arr_type.element_count == 0 && options.flexible_array_style == FlexibleArrayStyle::Zero {
+                format!("{}[0]", declarator)
+            } else if arr_type.element_count == 0 {
+                format!("{}[]", declarator)
+            } else {
+                format!("{}[{}]", declarator, arr_type.element_count)
+            };
+            return get_member_string_depth(parser, *arr_type.element, &declarator, level+1, options, depth + 1);
+        }
+        Type::Subroutine(_) => {
+            return Ok(format!("subroutine {}", declarator));
+        }
+        Type::Unspecified(unspecified_type) => {
+            let unspecified_name = unspecified_type.name.unwrap_or(String::from("void"));
+            return Ok(format!("{} {}", unspecified_name, declarator));
+        }
+        Type::Void => {
+            return Ok(format!("void {}", declarator));
+        }
+        Type::FortranString(string_type) => {
+            return match string_type.length {
+                Some(length) => Ok(format!("character(len={}) {}", length, declarator)),
+                None => Ok(format!("character(len=*) {}", declarator))
+            };
+        }
+        Type::Union(union_type) => {
+            let union_name = match union_type.name {
+                Some(name) => name,
+                None if options.compilable || promoted(parser, &union_type.meta, declarator, level)? => {
+                    inline_body(parser, &union_type.meta, options, depth + 1)?
+                }
+                None => String::from("{...}")
+            };
+            return Ok(format!("union {} {}", union_name, declarator));
+        }
+        Type::Unknown(Unknown { tag, ref meta }) if is_qualifier(tag) => {
+            return qualified_string(parser, qualifier_keyword(tag), meta, declarator, level, options, depth);
+        }
+        Type::Unknown(unknown_type) => {
+            return Ok(format!("/* {} */ {}", unknown_type.tag, declarator));
+        }
+    }
+    Ok(String::new())
+}
+
+// `const int x`, or `char *const p` when the qualifier applies to a pointer
+// and so belongs in the declarator
+fn qualified_string(parser: &Parser, keyword: &str, meta: &DwTypeMeta, declarator: &str, level: u8, options: &RenderOptions, depth: usize) -> Result<String, Error> {
+    let inner_type = parser.resolve_meta(meta)?.unwrap_or(Type::Void);
+    if let Type::Pointer(_) = inner_type {
+        let declarator = format!("{} {}", keyword, declarator);
+        return get_member_string_depth(parser, inner_type, &declarator, level+1, options, depth + 1);
+    }
+    let inner_string = get_member_string_depth(parser, inner_type, declarator, level+1, options, depth + 1)?;
+    Ok(format!("{} {}", keyword, inner_string))
+}
+
+// the keyword of a qualifier read as Type::Unknown, see is_qualifier
+fn qualifier_keyword(tag: gimli::DwTag) -> &'static str {
+    match tag {
+        gimli::DW_TAG_volatile_type => "volatile",
+        gimli::DW_TAG_restrict_type => "restrict",
+        _ => "_Atomic"
+    }
+}
+
+// `*name`, or `(*name)` when pointing to an array or function since `[]` and
+// `()` bind tighter than `*`
+fn pointer_declarator(op: &str, declarator: &str, target: &Type) -> String {
+    match target {
+        Type::Array(_) | Type::Subroutine(_) => format!("({}{})", op, declarator),
+        _ => format!("{}{}", op, declarator)
+    }
+}
+
+/// A declarator-free name for `type_inst` such as `struct foo`, `int`,
+/// `foo_t` or `char *`, meant as a map key when building type tables.
+/// Anonymous types are named after their DIE offset, e.g.
+/// `struct <anon 0x2e>`, so that every reference to the same type gives the
+/// same name.
+pub fn canonical_type_name(type_inst: &Type, parser: &Parser) -> String {
+    match canonical_name(type_inst, parser, 0) {
+        Ok(name) => name,
+        Err(_) => format!("<invalid {}>", anon_suffix(type_inst))
+    }
+}
+
+fn anon_suffix(type_inst: &Type) -> String {
+    match type_inst.meta() {
+        Some(meta) => format!("{:#x}", meta.die_offset()),
+        None => String::from("void")
+    }
+}
+
+// `int *`, but `char **` and `const char *&`
+fn declarator(inner: String, sigil: &str) -> String {
+    if inner.ends_with('*') { format!("{}{}", inner, sigil) } else { format!("{} {}", inner, sigil) }
+}
+
+fn canonical_name(type_inst: &Type, parser: &Parser, depth: usize) -> Result<String, Error> {
+    if depth > parser.max_depth {
+        return Err(gimli::Error::TypeMismatch.into()) // FIXME
+    }
+    let target = |meta| -> Result<String, Error> {
+        match parser.resolve_meta(meta)? {
+            Some(inner) => canonical_name(&inner, parser, depth + 1),
+            None => Ok(String::from("void"))
+        }
+    };
+    // qualified pointers are `char *const`
+    let qualified = |keyword: &str, meta| -> Result<String, Error> {
+        match parser.resolve_meta(meta)? {
+            Some(inner @ Type::Pointer(_)) => Ok(format!("{}{}", canonical_name(&inner, parser, depth + 1)?, keyword)),
+            _ => Ok(format!("{} {}", keyword, target(meta)?))
+        }
+    };
+    let name = match type_inst {
+        Type::Struct(t) if t.name.is_empty() => format!("struct <anon {}>", anon_suffix(type_inst)),
+        Type::Struct(t) => format!("struct {}", t.name),
+        Type::Union(t) => match &t.name {
+            Some(name) => format!("union {}", name),
+            None => format!("union <anon {}>", anon_suffix(type_inst))
+        },
+        Type::Enum(t) => match &t.name {
+            Some(name) => format!("enum {}", name),
+            None => format!("enum <anon {}>", anon_suffix(type_inst))
+        },
+        Type::Typedef(t) => t.name.clone(),
+        Type::Base(t) => t.name.clone(),
+        Type::Pointer(t) => declarator(target(&t.meta)?, "*"),
+        Type::Reference(t) => declarator(target(&t.meta)?, "&"),
+        Type::RvalueReference(t) => declarator(target(&t.meta)?, "&&"),
+        Type::Const(t) => qualified("const", &t.meta)?,
+        Type::Array(t) => format!("{}[{}]", canonical_name(&t.element, parser, depth + 1)?, t.element_count),
+        Type::Subroutine(t) => {
+            let ret = match t.return_type(parser)? {
+                Some(ret) => canonical_name(&ret, parser, depth + 1)?,
+                None => String::from("void")
+            };
+            let mut params = Vec::new();
+            for param in t.params(parser)? {
+                params.push(canonical_name(&param, parser, depth + 1)?);
+            }
+            if t.is_variadic(parser)? {
+                params.push(String::from("..."));
+            }
+            format!("{} ({})", ret, params.join(", "))
+        }
+        Type::Unspecified(t) => t.name.clone().unwrap_or(String::from("void")),
+        Type::Void => String::from("void"),
+        Type::FortranString(t) => match t.length {
+            Some(length) => format!("character(len={})", length),
+            None => String::from("character(len=*)")
+        },
+        Type::Unknown(t) if is_qualifier(t.tag) => qualified(qualifier_keyword(t.tag), &t.meta)?,
+        Type::Unknown(t) => format!("<{} {}>", t.tag, anon_suffix(type_inst)),
+    };
+    Ok(name)
+}
+
+// ret declarator(params), the return type gets the whole declarator so that
+// functions returning function pointers nest correctly
+fn get_subroutine_string(parser: &Parser, subroutine: &Subroutine, declarator: &str, options: &RenderOptions, depth: usize) -> Result<String, Error> {
+    let no_name = String::new();
+    let mut params = Vec::new();
+    for param in subroutine.params(parser)? {
+        params.push(get_member_string_depth(parser, param, &no_name, 1, options, depth)?.trim_end().to_string());
+    }
+    if subroutine.is_variadic(parser)? {
+        params.push(String::from("..."));
+    }
+    if params.is_empty() {
+        params.push(String::from("void"));
+    }
+
+    let declarator = format!("{}({})", declarator, params.join(", "));
+    match subroutine.return_type(parser)? {
+        Some(ret_type) => get_member_string_depth(parser, ret_type, &declarator, 1, options, depth),
+        None => Ok(format!("void {}", declarator))
+    }
+}
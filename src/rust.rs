@@ -0,0 +1,269 @@
+// #[repr(C)] rust declarations for structs, see Struct::to_rust_decl
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{is_qualifier, Const, Error, Parser, Struct, StructMember, Type, Typedef};
+
+// a field of the rust struct, `align` is what rust will align it to which is
+// less than C's for byte array placeholders
+struct Field {
+    name: String,
+    ty: String,
+    offset: u64,
+    size: u64,
+    align: u64,
+    comment: Option<String>,
+}
+
+impl Struct {
+    /// Render the struct as a `#[repr(C)]` rust struct for FFI, e.g.
+    ///
+    /// ```text
+    /// #[repr(C)]
+    /// pub struct foo {
+    ///     pub a: core::ffi::c_int,
+    ///     pub next: *mut foo,
+    /// }
+    /// ```
+    ///
+    /// Typedefs are resolved to the type they name and named structs are
+    /// referred to by name, so those need a declaration of their own. Types
+    /// with no rust equivalent, such as unions, anonymous structs and
+    /// bitfields, become `[u8; N]` fields of the same size, and explicit
+    /// padding fields keep every member at its C offset.
+    pub fn to_rust_decl(&self, parser: &Parser) -> Result<String, Error> {
+        let packed = self.is_packed(parser)?;
+        let mut fields = Vec::new();
+        let mut bitfields: Vec<StructMember> = Vec::new();
+        let mut anon_count = 0;
+        let mut bitfield_count = 0;
+        for member in self.members_by_offset(parser)? {
+            // padding fields take care of the alignment it forces
+            if member.is_zero_width_bitfield() {
+                continue;
+            }
+            if member.bit_size.is_some() {
+                bitfields.push(member);
+                continue;
+            }
+            if !bitfields.is_empty() {
+                fields.push(bitfield_run(&bitfields, bitfield_count));
+                bitfield_count += 1;
+                bitfields.clear();
+            }
+            let name = match &member.name {
+                Some(name) => rust_ident(name),
+                None => {
+                    anon_count += 1;
+                    format!("_anon{}", anon_count - 1)
+                }
+            };
+            let (ty, align) = match &member.mb_type {
+                Some(mb_type) => rust_type(parser, mb_type, 0)?,
+                None => (placeholder(member.size), 1)
+            };
+            fields.push(Field { name, ty, offset: member.offset, size: member.size, align, comment: None });
+        }
+        if !bitfields.is_empty() {
+            fields.push(bitfield_run(&bitfields, bitfield_count));
+        }
+
+        let mut lines = Vec::new();
+        let mut end: u64 = 0;
+        let mut max_align = 1;
+        let mut pad_count = 0;
+        for field in fields {
+            let align = if packed { 1 } else { field.align };
+            max_align = max_align.max(align);
+            if field.offset > end.next_multiple_of(align) {
+                lines.push(format!("    pub _padding{}: [u8; {}],", pad_count, field.offset - end));
+                pad_count += 1;
+            }
+            if let Some(comment) = field.comment {
+                lines.push(format!("    // {}", comment));
+            }
+            lines.push(format!("    pub {}: {},", field.name, field.ty));
+            end = end.max(field.offset.saturating_add(field.size));
+        }
+        if self.size > end.next_multiple_of(max_align) {
+            lines.push(format!("    pub _padding{}: [u8; {}],", pad_count, self.size - end));
+        }
+
+        // placeholders only ask for byte alignment, keep the C alignment
+        let c_align = parser.type_alignment(&Type::Struct(self.clone()), 0)?;
+        let repr = if packed {
+            String::from("#[repr(C, packed)]")
+        } else if c_align > max_align {
+            format!("#[repr(C, align({}))]", c_align)
+        } else {
+            String::from("#[repr(C)]")
+        };
+        let mut out = format!("{}\npub struct {} {{\n", repr, rust_ident(&self.name));
+        for line in lines {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push_str("}\n");
+        Ok(out)
+    }
+}
+
+// consecutive bitfields become one byte array covering all of their bits
+fn bitfield_run(members: &[StructMember], idx: usize) -> Field {
+    let start = members.iter().map(|member| member.bit_position()).min().unwrap_or(0) / 8;
+    let end = members.iter().map(|member| {
+        member.bit_position().saturating_add(member.bit_size.unwrap_or(0)).div_ceil(8)
+    }).max().unwrap_or(start);
+    let names = members.iter().map(|member| {
+        format!("{}: {}", member.name.as_deref().unwrap_or("<anonymous>"), member.bit_size.unwrap_or(0))
+    });
+    let size = end.saturating_sub(start);
+    Field {
+        name: format!("_bitfield{}", idx),
+        ty: placeholder(size),
+        offset: start,
+        size,
+        align: 1,
+        comment: Some(names.collect::<Vec<_>>().join(", ")),
+    }
+}
+
+fn placeholder(size: u64) -> String {
+    format!("[u8; {}]", size)
+}
+
+// the rust spelling of a type and the alignment rust gives it
+fn rust_type(parser: &Parser, type_inst: &Type, depth: usize) -> Result<(String, u64), Error> {
+    if depth > parser.max_depth {
+        return Err(gimli::Error::TypeMismatch.into()) // FIXME
+    }
+    let mapped = match type_inst {
+        Type::Base(t) => match base_type(&t.name, t.encoding, t.size) {
+            Some(name) => (name, t.size.max(1)),
+            None => (placeholder(t.size), 1)
+        },
+        Type::Enum(t) => {
+            let encoding = parser.enum_encoding(&t.meta)?;
+            match base_type("", encoding, t.size) {
+                Some(name) => (name, t.size.max(1)),
+                None => (placeholder(t.size), 1)
+            }
+        }
+        Type::Struct(t) if !t.name.is_empty() => {
+            // forward declarations have no members to align by
+            let align = parser.type_alignment(&Type::Struct(parser.struct_definition(t).clone()), depth + 1)?;
+            (rust_ident(&t.name), align)
+        }
+        Type::Pointer(t) => {
+            let target = parser.resolve_meta(&t.meta)?;
+            (pointer_type(parser, target, "mut", depth + 1)?, t.size.max(1))
+        }
+        Type::Array(t) => {
+            let (element, align) = rust_type(parser, &t.element, depth + 1)?;
+            (format!("[{}; {}]", element, t.element_count), align)
+        }
+        Type::Typedef(Typedef { meta, .. }) | Type::Const(Const { meta, .. }) => match parser.resolve_meta(meta)? {
+            Some(inner) => return rust_type(parser, &inner, depth + 1),
+            None => (String::from("core::ffi::c_void"), 1)
+        },
+        Type::Unknown(t) if is_qualifier(t.tag) => match parser.resolve_meta(&t.meta)? {
+            Some(inner) => return rust_type(parser, &inner, depth + 1),
+            None => (String::from("core::ffi::c_void"), 1)
+        },
+        _ => (placeholder(type_inst.size(parser)?), 1)
+    };
+    Ok(mapped)
+}
+
+// `*mut T`, `*const T` for const targets, and nullable function pointers.
+// `None` is a pointer to void
+fn pointer_type(parser: &Parser, target: Option<Type>, mutability: &str, depth: usize) -> Result<String, Error> {
+    if depth > parser.max_depth {
+        return Err(gimli::Error::TypeMismatch.into()) // FIXME
+    }
+    let target = match target {
+        Some(Type::Const(t)) => return pointer_type(parser, parser.resolve_meta(&t.meta)?, "const", depth + 1),
+        Some(Type::Unknown(t)) if is_qualifier(t.tag) => {
+            return pointer_type(parser, parser.resolve_meta(&t.meta)?, mutability, depth + 1);
+        }
+        Some(target) => target,
+        None => return Ok(format!("*{} core::ffi::c_void", mutability))
+    };
+    let ty = match target {
+        Type::Void | Type::Unspecified(_) => String::from("core::ffi::c_void"),
+        Type::Subroutine(t) => {
+            let mut params = Vec::new();
+            for param in t.params(parser)? {
+                params.push(rust_type(parser, &param, depth + 1)?.0);
+            }
+            if t.is_variadic(parser)? {
+                params.push(String::from("..."));
+            }
+            let ret = match t.return_type(parser)? {
+                Some(ret) => format!(" -> {}", rust_type(parser, &ret, depth + 1)?.0),
+                None => String::new()
+            };
+            return Ok(format!("Option<unsafe extern \"C\" fn({}){}>", params.join(", "), ret));
+        }
+        _ => rust_type(parser, &target, depth + 1)?.0
+    };
+    Ok(format!("*{} {}", mutability, ty))
+}
+
+fn base_type(name: &str, encoding: gimli::DwAte, size: u64) -> Option<String> {
+    let ffi = match name {
+        "char" => Some("c_char"),
+        "signed char" => Some("c_schar"),
+        "unsigned char" => Some("c_uchar"),
+        "short int" | "short" => Some("c_short"),
+        "short unsigned int" | "unsigned short" => Some("c_ushort"),
+        "int" => Some("c_int"),
+        "unsigned int" => Some("c_uint"),
+        "long int" | "long" => Some("c_long"),
+        "long unsigned int" | "unsigned long" => Some("c_ulong"),
+        "long long int" | "long long" => Some("c_longlong"),
+        "long long unsigned int" | "unsigned long long" => Some("c_ulonglong"),
+        "float" => Some("c_float"),
+        "double" => Some("c_double"),
+        _ => None
+    };
+    if let Some(ffi) = ffi {
+        return Some(format!("core::ffi::{}", ffi));
+    }
+    let ty = match (encoding, size) {
+        (gimli::DW_ATE_boolean, 1) => "bool",
+        (gimli::DW_ATE_signed | gimli::DW_ATE_signed_char, 1) => "i8",
+        (gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char, 1) => "u8",
+        (gimli::DW_ATE_signed, 2) => "i16",
+        (gimli::DW_ATE_unsigned | gimli::DW_ATE_UTF, 2) => "u16",
+        (gimli::DW_ATE_signed, 4) => "i32",
+        (gimli::DW_ATE_unsigned | gimli::DW_ATE_UTF, 4) => "u32",
+        (gimli::DW_ATE_signed, 8) => "i64",
+        (gimli::DW_ATE_unsigned, 8) => "u64",
+        (gimli::DW_ATE_signed, 16) => "i128",
+        (gimli::DW_ATE_unsigned, 16) => "u128",
+        (gimli::DW_ATE_float, 4) => "f32",
+        (gimli::DW_ATE_float, 8) => "f64",
+        _ => return None
+    };
+    Some(String::from(ty))
+}
+
+// C names that aren't rust identifiers, e.g. keywords or C++ templates
+fn rust_ident(name: &str) -> String {
+    let mut ident: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    match ident.as_str() {
+        "self" | "Self" | "super" | "crate" => format!("{}_", ident),
+        "as" | "break" | "const" | "continue" | "else" | "enum" | "extern" | "false" | "fn" | "for" | "if" |
+        "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" |
+        "static" | "struct" | "trait" | "true" | "type" | "unsafe" | "use" | "where" | "while" | "async" |
+        "await" | "dyn" | "abstract" | "become" | "box" | "do" | "final" | "macro" | "override" | "priv" |
+        "typeof" | "unsized" | "virtual" | "yield" | "try" | "gen" => format!("r#{}", ident),
+        _ => ident
+    }
+}
@@ -0,0 +1,245 @@
+// decoding struct instances from bytes, see Struct::read_value
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{is_qualifier, DwTypeMeta, Error, Parser, Struct, StructMember, Type};
+
+/// A decoded value, see `Struct::read_value`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// Signed integers, signed enums and characters.
+    Signed(i64),
+    /// Unsigned integers, unsigned enums and characters.
+    Unsigned(u64),
+    Float(f64),
+    Bool(bool),
+    /// The address a pointer or reference holds, it is not followed.
+    Pointer(u64),
+    Struct(StructValue),
+    /// Every member of the union decoded from the same bytes, since which
+    /// one is in use isn't known.
+    Union(StructValue),
+    Array(Vec<Value>),
+    /// The bytes of a value that can't be decoded, e.g. a `long double`, a
+    /// 128 bit integer or a function.
+    Bytes(Vec<u8>),
+}
+
+/// The members of a struct or union instance in declaration order.
+/// Members of anonymous structs and unions appear directly, as in C.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StructValue {
+    pub fields: Vec<(String, Value)>,
+}
+
+impl StructValue {
+    /// The value of the member called `name`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.fields.iter().find(|(field, _)| field == name).map(|(_, value)| value)
+    }
+}
+
+impl Struct {
+    /// Decode an instance of the struct from `bytes`, which has to hold at
+    /// least `size` bytes, using the encoding of each member's base type
+    /// and the byte order of the file. Nested structs, unions and arrays are
+    /// decoded recursively, pointers give the address they hold.
+    pub fn read_value(&self, parser: &Parser, bytes: &[u8]) -> Result<StructValue, Error> {
+        let reader = ValueReader { parser, bytes, endian: parser.endian(self.meta.header_idx) };
+        reader.bytes(0, self.size)?;
+        reader.aggregate(&self.meta, 0, 0)
+    }
+}
+
+struct ValueReader<'a> {
+    parser: &'a Parser,
+    bytes: &'a [u8],
+    endian: gimli::RunTimeEndian,
+}
+
+impl ValueReader<'_> {
+    fn bytes(&self, offset: u64, size: u64) -> Result<&[u8], Error> {
+        let end = offset.saturating_add(size);
+        let range = usize::try_from(offset).ok().zip(usize::try_from(end).ok());
+        match range.and_then(|(start, end)| self.bytes.get(start..end)) {
+            Some(bytes) => Ok(bytes),
+            None => Err(Error::BufferTooSmall(end))
+        }
+    }
+
+    fn aggregate(&self, meta: &DwTypeMeta, offset: u64, depth: usize) -> Result<StructValue, Error> {
+        let mut value = StructValue::default();
+        for member in self.parser.members(meta)? {
+            if member.is_static {
+                continue;
+            }
+            self.member(member, offset, &mut value, depth)?;
+        }
+        Ok(value)
+    }
+
+    fn member(&self, member: StructMember, offset: u64, value: &mut StructValue, depth: usize) -> Result<(), Error> {
+        let bit_position = offset.saturating_mul(8).saturating_add(member.bit_position());
+        let mb_type = match member.mb_type {
+            Some(mb_type) => mb_type,
+            None => return Ok(())
+        };
+        if let Some(bit_size) = member.bit_size {
+            // unnamed bitfields are padding
+            let name = match member.name {
+                Some(name) => name,
+                None => return Ok(())
+            };
+            let field = self.bitfield(&mb_type, bit_position, bit_size, depth)?;
+            value.fields.push((name, field));
+            return Ok(());
+        }
+        let field = self.value(mb_type, offset.saturating_add(member.offset), depth + 1)?;
+        match (member.name, field) {
+            (Some(name), field) => value.fields.push((name, field)),
+            (None, Value::Struct(inner) | Value::Union(inner)) => value.fields.extend(inner.fields),
+            (None, _) => {}
+        }
+        Ok(())
+    }
+
+    fn value(&self, type_inst: Type, offset: u64, depth: usize) -> Result<Value, Error> {
+        if depth > self.parser.max_depth {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        let value = match type_inst {
+            Type::Struct(ref t) => {
+                let meta = self.parser.struct_definition(t).meta.clone();
+                Value::Struct(self.aggregate(&meta, offset, depth + 1)?)
+            }
+            Type::Union(ref t) => Value::Union(self.aggregate(&t.meta, offset, depth + 1)?),
+            Type::Typedef(_) | Type::Const(_) => match self.parser.get_type(type_inst)? {
+                Some(inner) => self.value(inner, offset, depth + 1)?,
+                None => Value::Bytes(Vec::new())
+            },
+            Type::Unknown(ref t) if is_qualifier(t.tag) => match self.parser.resolve_meta(&t.meta)? {
+                Some(inner) => self.value(inner, offset, depth + 1)?,
+                None => Value::Bytes(Vec::new())
+            },
+            Type::Array(t) => {
+                let element_stride = match t.stride {
+                    Some(stride) => stride,
+                    None => t.element.size(self.parser)?
+                };
+                let mut elements = Vec::new();
+                for idx in 0..t.element_count {
+                    let element_offset = offset.saturating_add(idx.saturating_mul(element_stride));
+                    elements.push(self.value((*t.element).clone(), element_offset, depth + 1)?);
+                }
+                Value::Array(elements)
+            }
+            Type::Pointer(t) => Value::Pointer(self.unsigned(offset, t.size)?),
+            Type::Reference(t) => Value::Pointer(self.unsigned(offset, t.size)?),
+            Type::RvalueReference(t) => Value::Pointer(self.unsigned(offset, t.size)?),
+            Type::Base(t) => self.scalar(t.encoding, offset, t.size)?,
+            Type::Enum(t) => self.scalar(self.parser.enum_encoding(&t.meta)?, offset, t.size)?,
+            _ => {
+                let size = type_inst.size(self.parser)?;
+                Value::Bytes(self.bytes(offset, size)?.to_vec())
+            }
+        };
+        Ok(value)
+    }
+
+    fn scalar(&self, encoding: gimli::DwAte, offset: u64, size: u64) -> Result<Value, Error> {
+        let bytes = self.bytes(offset, size)?;
+        if !matches!(size, 1 | 2 | 4 | 8) {
+            return Ok(Value::Bytes(bytes.to_vec()));
+        }
+        let raw = self.unsigned(offset, size)?;
+        let value = match encoding {
+            gimli::DW_ATE_boolean => Value::Bool(raw != 0),
+            gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => Value::Signed(sign_extend(raw, size * 8)),
+            gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char | gimli::DW_ATE_UTF | gimli::DW_ATE_address => Value::Unsigned(raw),
+            gimli::DW_ATE_float if size == 4 => Value::Float(f32::from_bits(raw as u32).into()),
+            gimli::DW_ATE_float if size == 8 => Value::Float(f64::from_bits(raw)),
+            _ => Value::Bytes(bytes.to_vec())
+        };
+        Ok(value)
+    }
+
+    // an integer of up to 8 bytes in the file's byte order
+    fn unsigned(&self, offset: u64, size: u64) -> Result<u64, Error> {
+        let bytes = self.bytes(offset, size)?;
+        if bytes.len() > 8 {
+            return Err(gimli::Error::TypeMismatch.into()) // FIXME
+        }
+        Ok(fold_bytes(bytes, self.endian))
+    }
+
+    // bit positions count from the least significant bit of the first byte
+    // on little endian targets and from the most significant one on big
+    // endian targets
+    fn bitfield(&self, mb_type: &Type, bit_position: u64, bit_size: u64, depth: usize) -> Result<Value, Error> {
+        if bit_size == 0 || bit_size > 64 {
+            return Ok(Value::Bytes(Vec::new()));
+        }
+        let start = bit_position / 8;
+        let end = bit_position.saturating_add(bit_size).div_ceil(8);
+        let bytes = self.bytes(start, end - start)?;
+        let bits = (bytes.len() * 8) as u32;
+        let word = match self.endian {
+            gimli::RunTimeEndian::Little => bytes.iter().rev().fold(0u128, |acc, byte| (acc << 8) | *byte as u128),
+            gimli::RunTimeEndian::Big => bytes.iter().fold(0u128, |acc, byte| (acc << 8) | *byte as u128),
+        };
+        let shift = match self.endian {
+            gimli::RunTimeEndian::Little => (bit_position % 8) as u32,
+            gimli::RunTimeEndian::Big => bits - (bit_position % 8) as u32 - bit_size as u32,
+        };
+        let raw = ((word >> shift) & ((1u128 << bit_size) - 1)) as u64;
+        let value = match self.parser.scalar_encoding(mb_type, depth)? {
+            Some(gimli::DW_ATE_boolean) => Value::Bool(raw != 0),
+            Some(gimli::DW_ATE_signed | gimli::DW_ATE_signed_char) => Value::Signed(sign_extend(raw, bit_size)),
+            _ => Value::Unsigned(raw)
+        };
+        Ok(value)
+    }
+}
+
+fn fold_bytes(bytes: &[u8], endian: gimli::RunTimeEndian) -> u64 {
+    match endian {
+        gimli::RunTimeEndian::Little => bytes.iter().rev().fold(0, |acc, byte| (acc << 8) | *byte as u64),
+        gimli::RunTimeEndian::Big => bytes.iter().fold(0, |acc, byte| (acc << 8) | *byte as u64),
+    }
+}
+
+fn sign_extend(raw: u64, bits: u64) -> i64 {
+    if bits == 0 || bits >= 64 {
+        return raw as i64;
+    }
+    let shift = 64 - bits;
+    ((raw << shift) as i64) >> shift
+}
+
+// C initializer style, e.g. `{ .a = 1, .p = 0x1000 }`
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Signed(value) => write!(f, "{}", value),
+            Value::Unsigned(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Pointer(address) => write!(f, "{:#x}", address),
+            Value::Struct(value) | Value::Union(value) => {
+                let fields: Vec<String> = value.fields.iter().map(|(name, field)| format!(".{} = {}", name, field)).collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Value::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| format!("{}", element)).collect();
+                write!(f, "{{ {} }}", elements.join(", "))
+            }
+            Value::Bytes(bytes) => {
+                let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                write!(f, "<{}>", bytes.join(" "))
+            }
+        }
+    }
+}
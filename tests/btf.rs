@@ -0,0 +1,136 @@
+// decodes the BTF Parser::to_btf produces for the benchmark binary and
+// compares a struct in it with the dwarf it came from
+
+use std::fs;
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs");
+
+const KIND_INT: u32 = 1;
+const KIND_PTR: u32 = 2;
+const KIND_STRUCT: u32 = 4;
+const KIND_TYPEDEF: u32 = 8;
+
+// struct btf_type followed by its kind specific data as words
+#[derive(Debug)]
+struct BtfType {
+    name: String,
+    kind: u32,
+    kind_flag: bool,
+    vlen: u32,
+    size_or_type: u32,
+    data: Vec<u32>,
+}
+
+struct Btf {
+    // by id, 0 is void
+    types: Vec<Option<BtfType>>,
+    strings: Vec<u8>,
+}
+
+impl Btf {
+    fn named(&self, kind: u32, name: &str) -> &BtfType {
+        self.types.iter().flatten().find(|btf_type| btf_type.kind == kind && btf_type.name == name)
+            .unwrap_or_else(|| panic!("no type {} of kind {}", name, kind))
+    }
+
+    fn get(&self, id: u32) -> &BtfType {
+        self.types[id as usize].as_ref().unwrap()
+    }
+
+    fn string(&self, offset: u32) -> String {
+        let rest = &self.strings[offset as usize..];
+        let end = rest.iter().position(|byte| *byte == 0).unwrap();
+        String::from_utf8(rest[..end].to_vec()).unwrap()
+    }
+}
+
+fn word(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn decode(bytes: &[u8]) -> Btf {
+    assert_eq!(u16::from_le_bytes([bytes[0], bytes[1]]), 0xeb9f, "magic");
+    assert_eq!(bytes[2], 1, "version");
+    assert_eq!(bytes[3], 0, "flags");
+    let hdr_len = word(bytes, 4) as usize;
+    assert_eq!(hdr_len, 24);
+    let (type_off, type_len) = (word(bytes, 8) as usize, word(bytes, 12) as usize);
+    let (str_off, str_len) = (word(bytes, 16) as usize, word(bytes, 20) as usize);
+    assert_eq!(hdr_len + str_off + str_len, bytes.len());
+    let types = &bytes[hdr_len + type_off..hdr_len + type_off + type_len];
+    let strings = &bytes[hdr_len + str_off..hdr_len + str_off + str_len];
+    assert_eq!(strings.first(), Some(&0));
+    assert_eq!(strings.last(), Some(&0));
+
+    let mut btf = Btf { types: vec![None], strings: strings.to_vec() };
+    let mut offset = 0;
+    while offset < types.len() {
+        let info = word(types, offset + 4);
+        let kind = (info >> 24) & 0x1f;
+        let vlen = info & 0xffff;
+        let words = match kind {
+            1 => 1,
+            3 => 3,
+            4 | 5 => 3 * vlen,
+            6 | 13 => 2 * vlen,
+            19 => 3 * vlen,
+            2 | 7..=11 | 16 => 0,
+            _ => panic!("unexpected kind {}", kind)
+        };
+        let data = (0..words as usize).map(|idx| word(types, offset + 12 + idx * 4)).collect();
+        let name = btf.string(word(types, offset));
+        btf.types.push(Some(BtfType {
+            name,
+            kind,
+            kind_flag: info >> 31 != 0,
+            vlen,
+            size_or_type: word(types, offset + 8),
+            data,
+        }));
+        offset += 12 + words as usize * 4;
+    }
+    assert_eq!(offset, types.len());
+    btf
+}
+
+#[test]
+fn struct_round_trips() {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = rshole::Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    let btf = decode(&parser.to_btf().unwrap());
+
+    // members by name, bit offset and the name and size of their int type
+    let dw_struct = parser.get_struct("holey").unwrap();
+    let holey = btf.named(KIND_STRUCT, "holey");
+    assert_eq!(holey.size_or_type as u64, dw_struct.size);
+    assert!(!holey.kind_flag);
+    let expected: Vec<_> = dw_struct.members_by_offset(&parser).unwrap().into_iter().map(|member| {
+        let name = match member.mb_type.unwrap() {
+            rshole::Type::Base(base) => base.name,
+            mb_type => panic!("unexpected member type {:?}", mb_type)
+        };
+        (member.name.unwrap(), member.offset * 8, name, member.size as u32)
+    }).collect();
+    assert_eq!(holey.vlen as usize, expected.len());
+    let actual: Vec<_> = holey.data.chunks(3).map(|fields| {
+        let mb_type = btf.get(fields[1]);
+        assert_eq!(mb_type.kind, KIND_INT);
+        (btf.string(fields[0]), fields[2] as u64, mb_type.name.clone(), mb_type.size_or_type)
+    }).collect();
+    assert_eq!(actual, expected);
+
+    // bitfields set kind_flag and keep their size in the offset
+    let bits = btf.named(KIND_STRUCT, "bits");
+    assert!(bits.kind_flag);
+    let order = bits.data.chunks(3).find(|fields| btf.string(fields[0]) == "order").unwrap();
+    assert_eq!(order[2], (4 << 24) | 8);
+    assert_eq!(btf.get(order[1]).kind, KIND_TYPEDEF);
+
+    // a pointer back to the struct holding it
+    let node = btf.named(KIND_STRUCT, "node");
+    let parent = node.data.chunks(3).find(|fields| btf.string(fields[0]) == "parent").unwrap();
+    let pointer = btf.get(parent[1]);
+    assert_eq!(pointer.kind, KIND_PTR);
+    assert!(std::ptr::eq(btf.get(pointer.size_or_type), node));
+}
@@ -0,0 +1,66 @@
+// parsers built from synthetic dwarf with ParserBuilder, no object file. These
+// also run without default features: cargo test --no-default-features --test builder
+
+mod common;
+
+use common::{string, udata, Dwarf};
+use rshole::{Error, ParserBuilder};
+
+// struct point { int x; char tag; };
+fn point() -> Dwarf {
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let root = dwarf.root();
+    let point = dwarf.add(root, gimli::DW_TAG_structure_type, vec![
+        (gimli::DW_AT_name, string("point")),
+        (gimli::DW_AT_byte_size, udata(8)),
+    ]);
+    dwarf.member(point, "x", int, 0);
+    dwarf.member(point, "tag", char_type, 4);
+    dwarf
+}
+
+#[test]
+fn builds_from_sections() {
+    let parser = point().parser();
+    let point = parser.get_struct("point").unwrap();
+    assert_eq!(point.size, 8);
+    let members: Vec<_> = point.members_by_offset(&parser).unwrap().into_iter()
+        .map(|member| (member.name.unwrap(), member.offset, member.size))
+        .collect();
+    assert_eq!(members, [(String::from("x"), 0, 4), (String::from("tag"), 4, 1)]);
+    let holes: Vec<_> = point.holes(&parser).unwrap().into_iter().map(|hole| (hole.offset, hole.size, hole.trailing)).collect();
+    assert_eq!(holes, [(5, 3, true)]);
+}
+
+#[test]
+fn section_setters() {
+    let mut builder = ParserBuilder::new();
+    for (id, data) in point().sections() {
+        builder = match id {
+            gimli::SectionId::DebugInfo => builder.debug_info(data),
+            gimli::SectionId::DebugAbbrev => builder.debug_abbrev(data),
+            gimli::SectionId::DebugStr => builder.debug_str(data),
+            id => builder.section(id, data),
+        };
+    }
+    let mut parser = builder.build().unwrap();
+    parser.load_structs().unwrap();
+    assert!(parser.get_struct("point").is_some());
+}
+
+#[test]
+fn no_debug_info() {
+    assert!(matches!(ParserBuilder::new().build(), Err(Error::NoDebugInfo)));
+}
+
+#[test]
+fn without_std() {
+    // everything but loading files works on section data alone
+    let parser = point().parser();
+    let decl = parser.get_struct("point").unwrap().to_c_decl(&parser).unwrap();
+    assert!(decl.contains("int x;"), "{}", decl);
+    assert_eq!(parser.padding_report().unwrap(), [(String::from("point"), 3)]);
+    assert_eq!(Error::NoDebugInfo.to_string(), "no debug info found");
+}
@@ -0,0 +1,203 @@
+// layout of the C++ classes in tests/data/classes and its 32-bit build
+// classes32, see tests/data/classes.cc
+
+use std::fs;
+
+use object::{Object, ObjectSection, ObjectSymbol};
+use rshole::{canonical_type_name, LayoutItem, Parser, StructMemberIter, Type};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/classes");
+const INPUT_32: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/classes32");
+
+fn parser() -> Parser {
+    load(INPUT)
+}
+
+fn load(path: &str) -> Parser {
+    let data = fs::read(path).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+// .debug_info offset of the first DIE with `tag`
+fn find_die(path: &str, tag: gimli::DwTag) -> u64 {
+    let data = fs::read(path).unwrap();
+    let file = object::File::parse(&*data).unwrap();
+    let load = |name: &str| gimli::EndianSlice::new(file.section_by_name(name).unwrap().data().unwrap(), gimli::LittleEndian);
+    let debug_info = gimli::DebugInfo::from(load(".debug_info"));
+    let debug_abbrev = gimli::DebugAbbrev::from(load(".debug_abbrev"));
+    let mut units = debug_info.units();
+    while let Some(header) = units.next().unwrap() {
+        let abbreviations = header.abbreviations(&debug_abbrev).unwrap();
+        let mut entries = header.entries(&abbreviations);
+        while let Some((_, entry)) = entries.next_dfs().unwrap() {
+            if entry.tag() == tag {
+                return entry.offset().to_debug_info_offset(&header).unwrap().0 as u64;
+            }
+        }
+    }
+    panic!("no {} in {}", tag, path)
+}
+
+#[test]
+fn base_classes() {
+    let parser = parser();
+    let derived = parser.get_struct("Derived").unwrap();
+    let members = derived.members_by_offset(&parser).unwrap();
+    let bases: Vec<_> = members.iter().filter(|member| member.is_inheritance).map(|member| {
+        let name = match &member.mb_type {
+            Some(Type::Struct(base)) => base.name.clone(),
+            mb_type => panic!("base of unexpected type {:?}", mb_type)
+        };
+        (name, member.offset, member.size)
+    }).collect();
+    assert_eq!(bases, [(String::from("Base"), 0, 16), (String::from("Mixin"), 16, 4)]);
+
+    // the polymorphic base with its vtable pointer fills the start
+    let layout = derived.layout(&parser).unwrap();
+    let holes: Vec<_> = layout.iter().filter_map(|item| match item {
+        LayoutItem::Hole(hole) if !hole.trailing => Some(hole.offset),
+        _ => None
+    }).collect();
+    assert!(holes.is_empty(), "{:?}", holes);
+
+    let decl = derived.to_c_decl(&parser).unwrap();
+    assert!(decl.contains("/* struct Base <ancestor>; */"), "{}", decl);
+    assert!(decl.contains("/* struct Mixin <ancestor>; */"), "{}", decl);
+
+    // a real hole after a base class
+    let child = parser.get_struct("Child").unwrap();
+    let holes: Vec<_> = child.holes(&parser).unwrap().into_iter().map(|hole| (hole.offset, hole.size)).collect();
+    assert_eq!(holes, [(2, 2)]);
+}
+
+#[test]
+fn virtual_base_classes() {
+    // a virtual base has no fixed offset and is left out
+    let parser = parser();
+    let diamond = parser.get_struct("Diamond").unwrap();
+    let members = diamond.members_by_offset(&parser).unwrap();
+    assert!(members.iter().all(|member| !member.is_inheritance));
+    assert!(members[0].is_vtable_ptr());
+    assert_eq!(members[1].name.as_deref(), Some("d"));
+}
+
+#[test]
+fn nullptr_is_pointer_sized() {
+    for (path, size) in [(INPUT, 8), (INPUT_32, 4)] {
+        let parser = load(path);
+        let members = parser.get_struct("Null").unwrap().members_by_offset(&parser).unwrap();
+        assert!(matches!(members[0].mb_type, Some(Type::Unspecified(_))), "{:?}", members[0].mb_type);
+        assert_eq!(members[0].size, size, "{}", path);
+        assert_eq!(members[1].offset, size, "{}", path);
+    }
+}
+
+#[test]
+fn references() {
+    for (path, size) in [(INPUT, 8), (INPUT_32, 4)] {
+        let parser = load(path);
+        let refs = parser.get_struct("Refs").unwrap();
+        let members = refs.members_by_offset(&parser).unwrap();
+        assert!(matches!(members[0].mb_type, Some(Type::Reference(_))), "{:?}", members[0].mb_type);
+        assert!(matches!(members[1].mb_type, Some(Type::RvalueReference(_))), "{:?}", members[1].mb_type);
+        let layout: Vec<_> = members.iter().map(|member| (member.offset, member.size)).collect();
+        assert_eq!(layout, [(0, size), (size, size), (2 * size, size)], "{}", path);
+
+        let names: Vec<_> = members.into_iter().map(|member| canonical_type_name(&member.mb_type.unwrap(), &parser)).collect();
+        assert_eq!(names, ["int &", "int &&", "const char *&"]);
+        let decl = refs.to_c_decl(&parser).unwrap();
+        for expected in ["int &ref;", "int &&rref;", "const char *&name;"] {
+            assert!(decl.contains(expected), "{} not in {}", expected, decl);
+        }
+    }
+}
+
+#[test]
+fn nested_types() {
+    let parser = parser();
+    let outer = parser.get_struct("Outer").unwrap();
+    let nested: Vec<_> = outer.nested_types(&parser).unwrap().into_iter().map(|type_inst| match type_inst {
+        Type::Struct(dw_struct) => dw_struct.name,
+        Type::Enum(dw_enum) => dw_enum.name.unwrap(),
+        type_inst => panic!("unexpected nested type {:?}", type_inst)
+    }).collect();
+    assert_eq!(nested, ["Inner", "Kind"]);
+
+    // the definitions don't end the members early
+    let members: Vec<_> = outer.members_by_offset(&parser).unwrap().into_iter()
+        .map(|member| (member.name.unwrap(), member.offset))
+        .collect();
+    assert_eq!(members, [(String::from("inner"), 0), (String::from("kind"), 4), (String::from("after"), 8)]);
+}
+
+#[test]
+fn static_members() {
+    let parser = parser();
+    let counter = parser.get_struct("Counter").unwrap();
+    let members: Vec<_> = StructMemberIter::new(&counter, &parser).map(|member| {
+        let member = member.unwrap();
+        (member.name.unwrap(), member.is_static, member.is_external)
+    }).collect();
+    assert_eq!(members, [
+        (String::from("total"), true, true),
+        (String::from("value"), false, false),
+        (String::from("limit"), true, true),
+    ]);
+
+    // statics take no space in the struct
+    let layout: Vec<_> = counter.members_by_offset(&parser).unwrap().into_iter().map(|member| (member.name.unwrap(), member.offset)).collect();
+    assert_eq!(layout, [(String::from("value"), 0)]);
+    assert!(counter.holes(&parser).unwrap().is_empty());
+}
+
+#[test]
+fn static_addresses() {
+    let data = fs::read(INPUT).unwrap();
+    let file = object::File::parse(&*data).unwrap();
+    let total = file.symbols().find(|symbol| symbol.name() == Ok("_ZN7Counter5totalE")).unwrap();
+
+    // from the definition outside the class, limit is a constant with no
+    // storage
+    let parser = parser();
+    let counter = parser.get_struct("Counter").unwrap();
+    let addresses: Vec<_> = StructMemberIter::new(&counter, &parser).map(|member| {
+        let member = member.unwrap();
+        (member.name.unwrap(), member.static_address)
+    }).collect();
+    assert_eq!(addresses, [
+        (String::from("total"), Some(total.address())),
+        (String::from("value"), None),
+        (String::from("limit"), None),
+    ]);
+}
+
+#[test]
+fn vendor_extensions() {
+    let parser = parser();
+    for (tag, vendor) in [(gimli::DW_TAG_GNU_template_parameter_pack, true), (gimli::DW_TAG_template_type_parameter, false)] {
+        match parser.type_at_global(find_die(INPUT, tag)).unwrap() {
+            Type::Unknown(unknown) => {
+                assert_eq!(unknown.tag, tag);
+                assert_eq!(unknown.is_vendor_extension(), vendor, "{}", tag);
+                assert_eq!(unknown.is_gnu_extension(), vendor, "{}", tag);
+            }
+            type_inst => panic!("{} read as {:?}", tag, type_inst)
+        }
+    }
+    // the pack doesn't get in the way of the members
+    let pack = parser.get_struct("Pack<int, char>").unwrap();
+    let names: Vec<_> = pack.members_by_offset(&parser).unwrap().into_iter().map(|member| member.name).collect();
+    assert_eq!(names, [Some(String::from("count"))]);
+}
+
+#[test]
+fn lookup_ignores_whitespace() {
+    let parser = parser();
+    for query in ["Pack<int,char>", "Pack< int, char >"] {
+        let pack = parser.get_struct(query).unwrap_or_else(|| panic!("{} not found", query));
+        assert_eq!(pack.name, "Pack<int, char>");
+    }
+    assert!(parser.get_struct("Pack<int,long>").is_none());
+}
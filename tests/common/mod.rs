@@ -0,0 +1,110 @@
+// synthetic dwarf for the tests, for what gcc can't be made to emit
+#![allow(dead_code)]
+
+use gimli::write::{AttributeValue, DwarfUnit, EndianVec, Sections, UnitEntryId};
+use gimli::{Encoding, Format, RunTimeEndian};
+use rshole::{Parser, ParserBuilder};
+
+pub struct Dwarf {
+    pub unit: DwarfUnit,
+    endian: RunTimeEndian,
+}
+
+impl Dwarf {
+    pub fn new() -> Dwarf {
+        Dwarf::with_encoding(5, 8)
+    }
+
+    pub fn with_encoding(version: u16, address_size: u8) -> Dwarf {
+        let encoding = Encoding { format: Format::Dwarf32, version, address_size };
+        Dwarf { unit: DwarfUnit::new(encoding), endian: RunTimeEndian::Little }
+    }
+
+    pub fn with_endian(mut self, endian: RunTimeEndian) -> Dwarf {
+        self.endian = endian;
+        self
+    }
+
+    pub fn root(&self) -> UnitEntryId {
+        self.unit.unit.root()
+    }
+
+    pub fn add(&mut self, parent: UnitEntryId, tag: gimli::DwTag, attrs: Vec<(gimli::DwAt, AttributeValue)>) -> UnitEntryId {
+        let id = self.unit.unit.add(parent, tag);
+        let entry = self.unit.unit.get_mut(id);
+        for (name, value) in attrs {
+            entry.set(name, value);
+        }
+        id
+    }
+
+    pub fn base(&mut self, name: &str, size: u64, encoding: gimli::DwAte) -> UnitEntryId {
+        let root = self.root();
+        self.add(root, gimli::DW_TAG_base_type, vec![
+            (gimli::DW_AT_name, string(name)),
+            (gimli::DW_AT_byte_size, udata(size)),
+            (gimli::DW_AT_encoding, AttributeValue::Encoding(encoding)),
+        ])
+    }
+
+    // a member of `parent` at byte `offset`
+    pub fn member(&mut self, parent: UnitEntryId, name: &str, mb_type: UnitEntryId, offset: u64) -> UnitEntryId {
+        self.add(parent, gimli::DW_TAG_member, vec![
+            (gimli::DW_AT_name, string(name)),
+            (gimli::DW_AT_type, AttributeValue::UnitRef(mb_type)),
+            (gimli::DW_AT_data_member_location, udata(offset)),
+        ])
+    }
+
+    pub fn sections(mut self) -> Vec<(gimli::SectionId, Vec<u8>)> {
+        let endian = self.endian;
+        write(endian, |sections| self.unit.write(sections))
+    }
+
+    pub fn builder(self) -> ParserBuilder {
+        let endian = self.endian;
+        builder(self.sections(), endian)
+    }
+
+    pub fn parser(self) -> Parser {
+        let mut parser = self.builder().build().unwrap();
+        parser.load_structs().unwrap();
+        parser
+    }
+}
+
+// the sections of several units, see gimli::write::Dwarf
+pub fn write_dwarf(dwarf: &mut gimli::write::Dwarf) -> ParserBuilder {
+    let endian = RunTimeEndian::Little;
+    builder(write(endian, |sections| dwarf.write(sections)), endian)
+}
+
+fn write<F>(endian: RunTimeEndian, f: F) -> Vec<(gimli::SectionId, Vec<u8>)>
+where
+    F: FnOnce(&mut Sections<EndianVec<RunTimeEndian>>) -> gimli::write::Result<()>
+{
+    let mut sections = Sections::new(EndianVec::new(endian));
+    f(&mut sections).unwrap();
+    let mut result = Vec::new();
+    sections.for_each(|id, data| -> Result<(), ()> {
+        result.push((id, data.slice().to_vec()));
+        Ok(())
+    }).unwrap();
+    result
+}
+
+fn builder(sections: Vec<(gimli::SectionId, Vec<u8>)>, endian: RunTimeEndian) -> ParserBuilder {
+    sections.into_iter().fold(ParserBuilder::new(), |builder, (id, data)| builder.section(id, data)).endian(endian)
+}
+
+pub fn string(value: &str) -> AttributeValue {
+    AttributeValue::String(value.as_bytes().to_vec())
+}
+
+pub fn udata(value: u64) -> AttributeValue {
+    AttributeValue::Udata(value)
+}
+
+pub fn unit_ref(id: UnitEntryId) -> AttributeValue {
+    AttributeValue::UnitRef(id)
+}
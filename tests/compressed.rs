@@ -0,0 +1,56 @@
+// Parser::from_compressed on tests/data/types compressed with the command
+// line tools, read when the matching feature is enabled and refused otherwise
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use rshole::{Error, Parser};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/types");
+
+// `tool` compresses INPUT into the returned file, None if it isn't installed
+fn compress(tool: &str) -> Option<PathBuf> {
+    let path = std::env::temp_dir().join(format!("rshole-{}-{}", tool, process::id()));
+    let output = match Command::new(tool).args(["-c", INPUT]).output() {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("skipping {}: {}", tool, err);
+            return None;
+        }
+    };
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    fs::write(&path, output.stdout).unwrap();
+    Some(path)
+}
+
+fn check(path: &Path, enabled: bool, feature: &str) {
+    match Parser::from_compressed(path) {
+        Ok(mut parser) => {
+            assert!(enabled, "{} read without the feature", feature);
+            parser.load_structs().unwrap();
+            assert!(parser.get_struct("qualifiers").is_some());
+        }
+        Err(Error::Io(err)) if !enabled => {
+            assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+            assert!(err.to_string().contains(feature), "{}", err);
+        }
+        Err(err) => panic!("{}: {}", feature, err)
+    }
+}
+
+#[test]
+fn uncompressed() {
+    check(Path::new(INPUT), true, "none");
+}
+
+#[test]
+fn compressed() {
+    for (tool, enabled) in [("gzip", cfg!(feature = "gzip")), ("xz", cfg!(feature = "xz")), ("zstd", cfg!(feature = "zstd"))] {
+        if let Some(path) = compress(tool) {
+            check(&path, enabled, tool);
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
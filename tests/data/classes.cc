@@ -0,0 +1,43 @@
+/* Input for the C++ tests in tests/, built with
+ * `g++ -g -O0 -nostdlib -static -fno-rtti -fno-exceptions -o classes classes.cc`
+ * and the same with `-m32 -o classes32`, and checked in so the results don't
+ * depend on the local compiler. */
+
+struct Base { virtual void poll() {} long id; };
+
+struct Mixin { int flags; };
+
+struct Derived : Base, Mixin { char tag; };
+
+struct Plain { short a; };
+
+struct Child : Plain { int b; };
+
+struct Shared { int refs; };
+
+struct Diamond : virtual Shared { int d; };
+
+struct Null { decltype(nullptr) np; int after; };
+
+struct Refs { int &ref; int &&rref; const char *&name; };
+
+struct Outer { struct Inner { int a; } inner; enum Kind { A, B } kind; int after; };
+
+struct Counter { static int total; int value; static const int limit = 4; };
+
+int Counter::total;
+
+template <typename... Ts> struct Pack { int count; };
+
+Derived derived;
+Child child;
+Diamond diamond;
+Null null;
+Pack<int, char> pack;
+Outer outer;
+Counter counter;
+int target;
+const char *target_name;
+Refs refs { target, static_cast<int &&>(target), target_name };
+
+extern "C" void _start(void) { for (;;) ; }
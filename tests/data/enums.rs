@@ -0,0 +1,31 @@
+// Input for tests/enums.rs, built with
+// `rustc -g -C opt-level=0 -C panic=abort -C link-arg=-nostartfiles -C link-arg=-static -C relocation-model=static -o enums enums.rs`
+// and checked in so the results don't depend on the local compiler.
+
+#![no_std]
+#![no_main]
+
+pub enum Shape {
+    Circle { radius: u32 },
+    Rect { w: u16, h: u16 },
+    Empty,
+}
+
+// a niche: Some is the dataful variant with no discriminant value
+pub struct Holder {
+    pub shape: Shape,
+    pub name: Option<&'static u8>,
+}
+
+#[no_mangle]
+pub static mut HOLDER: Holder = Holder { shape: Shape::Rect { w: 1, h: 2 }, name: None };
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    loop {}
+}
@@ -0,0 +1,16 @@
+/* Input for the tests in tests/, built with
+ * `gcc -g -gpubnames -O0 -nostdlib -static -o pubtypes pubtypes.c` so it has a
+ * .debug_pubtypes index, which leaves out `local`. */
+
+struct listed {
+    int a;
+    long b;
+};
+
+struct listed listed;
+
+void _start(void) {
+    struct local { int c; } local = { 0 };
+    (void)local;
+    for (;;) ;
+}
@@ -0,0 +1,64 @@
+/* Input for the tests in tests/, built with `gcc -g -O0 -nostdlib -static -o types types.c`
+ * and, with the types in type units, with `-gdwarf-4 -fdebug-types-section -o typeunits4` and
+ * `-gdwarf-5 -fdebug-types-section -o typeunits5`. Checked in so the results don't depend on the local compiler. */
+
+struct arrays {
+    char small[4];
+    char buf[256];
+    short wide[65536];
+    int empty[0];
+    char tail[];
+};
+
+struct arrays arrays;
+
+struct qualifiers {
+    volatile int lock;
+    char *restrict r;
+    _Atomic int counter;
+    char *const p;
+    const int *const *cpp;
+    volatile unsigned char *volatile io;
+    void (*const fp)(void);
+};
+
+struct qualifiers qualifiers;
+
+typedef void (*handler_t)(int);
+
+struct callbacks {
+    handler_t handler;
+    handler_t table[2];
+    int (*compare)(const void *, const void *);
+    int (*log)(const char *, ...);
+};
+
+struct callbacks callbacks;
+
+enum color { BLUE, BLACK = -1 };
+
+struct enums {
+    enum { RED = 1, GREEN = 2 } anon;
+    enum color named;
+};
+
+struct enums enums;
+
+struct args {
+    int argc;
+    const char *argv[];
+};
+
+struct args args;
+
+typedef struct { int x; short y; } point_t;
+typedef union { int i; float f; } number_t;
+
+struct shape {
+    point_t origin;
+    number_t scale;
+};
+
+struct shape shape;
+
+void _start(void) { for (;;) ; }
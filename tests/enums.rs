@@ -0,0 +1,64 @@
+// variant parts of the rust enums in tests/data/enums, see tests/data/enums.rs
+
+use std::fs;
+
+use rshole::{Parser, Type, VariantPart};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/enums");
+
+fn parser() -> Parser {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+fn variant_part(parser: &Parser, name: &str) -> VariantPart {
+    let dw_struct = parser.get_struct(name).unwrap_or_else(|| panic!("no struct {}", name));
+    dw_struct.variant_part(parser).unwrap().unwrap_or_else(|| panic!("{} has no variant part", name))
+}
+
+#[test]
+fn variants() {
+    let parser = parser();
+    let shape = variant_part(&parser, "Shape");
+    let discriminant = shape.discriminant.unwrap();
+    assert_eq!((discriminant.offset, discriminant.size), (0, 2));
+
+    let variants: Vec<_> = shape.variants.iter().map(|variant| {
+        let names: Vec<_> = variant.members.iter().map(|member| member.name.clone().unwrap()).collect();
+        (variant.discr_value, names)
+    }).collect();
+    assert_eq!(variants, [
+        (Some(0), vec![String::from("Circle")]),
+        (Some(1), vec![String::from("Rect")]),
+        (Some(2), vec![String::from("Empty")]),
+    ]);
+
+    // each variant's fields are a struct of their own
+    let rect = match &shape.variants[1].members[0].mb_type {
+        Some(Type::Struct(rect)) => rect.clone(),
+        mb_type => panic!("Rect is a {:?}", mb_type)
+    };
+    let fields: Vec<_> = rect.members_by_offset(&parser).unwrap().into_iter().map(|member| (member.name.unwrap(), member.offset)).collect();
+    assert_eq!(fields, [(String::from("w"), 2), (String::from("h"), 4)]);
+}
+
+#[test]
+fn niche_variants() {
+    // None is the null pointer and Some has no discriminant value of its own
+    let parser = parser();
+    let option = variant_part(&parser, "Option<&u8>");
+    let discr_values: Vec<_> = option.variants.iter().map(|variant| variant.discr_value).collect();
+    assert_eq!(discr_values, [Some(0), None]);
+    assert_eq!(option.discriminant.unwrap().size, 8);
+}
+
+#[test]
+fn enums_are_not_members() {
+    // the variant part isn't a member and leaves no holes
+    let parser = parser();
+    let shape = parser.get_struct("Shape").unwrap();
+    assert!(shape.members_by_offset(&parser).unwrap().is_empty());
+    assert!(shape.holes(&parser).unwrap().is_empty());
+}
@@ -0,0 +1,98 @@
+// scalar leaf fields of the structs in benches/data/structs, see
+// benches/data/structs.c
+
+use std::fs;
+
+use rshole::{ArrayFields, FlatField, FlattenOptions, Parser};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs");
+
+fn parser() -> Parser {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+fn flatten(parser: &Parser, name: &str, options: &FlattenOptions) -> Vec<FlatField> {
+    parser.get_struct(name).unwrap().flatten_fields_with(parser, options).unwrap()
+}
+
+// (path, offset, size)
+fn paths(fields: &[FlatField]) -> Vec<(&str, u64, u64)> {
+    fields.iter().map(|field| (field.path.as_str(), field.offset, field.size)).collect()
+}
+
+#[test]
+fn flatten_fields() {
+    let parser = parser();
+    let fields = parser.get_struct("table").unwrap().flatten_fields(&parser).unwrap();
+    assert_eq!(fields.len(), 3 + 4 * 5 + 2);
+    let fields = paths(&fields);
+    // the pointer is a leaf
+    assert_eq!(fields[0], ("buckets", 0, 8));
+    assert_eq!(fields[3..8], [("stats[0].a", 24, 1), ("stats[0].b", 32, 8), ("stats[0].c", 40, 1), ("stats[0].d", 44, 4), ("stats[0].e", 48, 2)]);
+    assert_eq!(fields[22], ("stats[3].e", 144, 2));
+    assert_eq!(fields[23..], [("lock", 152, 4), ("destroy", 160, 8)]);
+}
+
+#[test]
+fn encodings() {
+    let parser = parser();
+    let fields = flatten(&parser, "table", &FlattenOptions::new());
+    let encoding = |path: &str| fields.iter().find(|field| field.path == path).unwrap().base_encoding;
+    assert_eq!(encoding("buckets"), Some(gimli::DW_ATE_address));
+    assert_eq!(encoding("nr_buckets"), Some(gimli::DW_ATE_unsigned));
+    assert_eq!(encoding("stats[1].a"), Some(gimli::DW_ATE_signed_char));
+    // through volatile and the u32 typedef
+    assert_eq!(encoding("lock"), Some(gimli::DW_ATE_unsigned));
+}
+
+#[test]
+fn collapsed_arrays() {
+    let parser = parser();
+    let fields = flatten(&parser, "table", &FlattenOptions::new().arrays(ArrayFields::Collapse));
+    let stats: Vec<_> = fields.iter().filter(|field| field.path.starts_with("stats")).map(|field| (field.path.as_str(), field.offset, field.count, field.stride)).collect();
+    assert_eq!(stats, [
+        ("stats[].a", 24, 4, 32),
+        ("stats[].b", 32, 4, 32),
+        ("stats[].c", 40, 4, 32),
+        ("stats[].d", 44, 4, 32),
+        ("stats[].e", 48, 4, 32),
+    ]);
+}
+
+#[test]
+fn bitfields_and_anonymous_members() {
+    let parser = parser();
+    let node = flatten(&parser, "node", &FlattenOptions::new());
+    let field = |path: &str| node.iter().find(|field| field.path == path).unwrap_or_else(|| panic!("no {}", path));
+    // lo and hi are reached without a name for the anonymous struct
+    assert_eq!((field("id.raw").offset, field("id.lo").offset, field("id.hi").offset), (144, 144, 148));
+    let order = field("bits.order");
+    assert_eq!((order.bit_size, order.bit_position), (Some(4), Some(184 * 8 + 8)));
+}
+
+#[test]
+fn follow_pointers() {
+    let parser = parser();
+    let fields = flatten(&parser, "list_head", &FlattenOptions::new().follow_pointers_depth(1));
+    let followed: Vec<_> = fields.iter().map(|field| (field.path.as_str(), field.offset, field.pointer_depth)).collect();
+    assert_eq!(followed, [
+        ("next", 0, 0),
+        ("next->next", 0, 1),
+        ("next->prev", 8, 1),
+        ("prev", 8, 0),
+        ("prev->next", 0, 1),
+        ("prev->prev", 8, 1),
+    ]);
+    // the self-referential pointers stop after one level
+    let deeper = flatten(&parser, "list_head", &FlattenOptions::new().follow_pointers_depth(3));
+    assert_eq!(paths(&deeper), paths(&fields));
+
+    // a pointer to another struct is expanded in place
+    let fields = flatten(&parser, "task", &FlattenOptions::new().follow_pointers_depth(1));
+    let files: Vec<_> = fields.iter().filter(|field| field.path.starts_with("files")).map(|field| field.path.as_str()).collect();
+    assert_eq!(files[..3], ["files", "files->buckets", "files->nr_buckets"]);
+    assert!(flatten(&parser, "task", &FlattenOptions::new()).iter().all(|field| field.pointer_depth == 0 && !field.path.contains("->")));
+}
@@ -0,0 +1,88 @@
+// compiles the header Parser::emit_header writes and compares the layout of
+// the structs in the result with the dwarf the header came from
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+
+use common::{string, udata, Dwarf};
+use rshole::Parser;
+
+const INPUTS: [(&str, &[&str]); 2] = [
+    (concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs"), &["holey", "packed_hdr", "bits", "node", "table", "task"]),
+    (concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/types"), &["arrays", "qualifiers"]),
+];
+
+fn load(path: &Path) -> Parser {
+    let data = fs::read(path).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+// (name, offset, bit position, bit size)
+type MemberLayout = (Option<String>, u64, u64, Option<u64>);
+
+// size and layout of every member
+fn layout(parser: &Parser, name: &str) -> (u64, Vec<MemberLayout>) {
+    let dw_struct = parser.get_struct(name).unwrap_or_else(|| panic!("no struct {}", name));
+    let members = dw_struct.members_by_offset(parser).unwrap().into_iter()
+        .map(|member| (member.name.clone(), member.offset, member.bit_position(), member.bit_size))
+        .collect();
+    (dw_struct.size, members)
+}
+
+#[test]
+fn header_round_trips() {
+    let dir = std::env::temp_dir().join(format!("rshole-header-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (idx, (input, names)) in INPUTS.iter().enumerate() {
+        let parser = load(Path::new(input));
+        let mut source = parser.emit_header(names).unwrap();
+        for name in names.iter() {
+            source.push_str(&format!("struct {} {}_var;\n", name, name));
+        }
+        source.push_str("void _start(void) { for (;;) ; }\n");
+        let source_path = dir.join(format!("header{}.c", idx));
+        let output_path = dir.join(format!("header{}", idx));
+        fs::write(&source_path, &source).unwrap();
+
+        let output = match Command::new("cc").args(["-g", "-O0", "-nostdlib", "-static", "-o"]).arg(&output_path).arg(&source_path).output() {
+            Ok(output) => output,
+            Err(err) => {
+                eprintln!("skipping, no C compiler: {}", err);
+                return;
+            }
+        };
+        assert!(output.status.success(), "{}\n{}", String::from_utf8_lossy(&output.stderr), source);
+
+        let compiled = load(&output_path);
+        for name in names.iter() {
+            assert_eq!(layout(&compiled, name), layout(&parser, name), "struct {} in\n{}", name, source);
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unnamed_bitfields() {
+    // the `:6` between dirty and order isn't in the dwarf
+    let parser = load(Path::new(INPUTS[0].0));
+    let header = parser.emit_header(&["bits"]).unwrap();
+    assert!(header.contains("\tunsigned char : 6;\n"), "{}", header);
+}
+
+#[test]
+fn oversized_members() {
+    // sizes whose bit counts don't fit in 64 bits
+    let mut dwarf = Dwarf::new();
+    let huge = dwarf.base("huge", 1 << 62, gimli::DW_ATE_unsigned);
+    let root = dwarf.root();
+    let dw_struct = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("oversized")), (gimli::DW_AT_byte_size, udata(u64::MAX / 2))]);
+    dwarf.member(dw_struct, "a", huge, 0);
+    dwarf.member(dw_struct, "b", huge, 1 << 62);
+    let parser = dwarf.parser();
+    assert!(parser.emit_header(&["oversized"]).is_ok());
+}
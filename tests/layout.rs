@@ -0,0 +1,1161 @@
+// member layout, holes and padding of the structs in benches/data/structs,
+// see benches/data/structs.c, and of synthetic dwarf
+
+mod common;
+
+use std::collections::HashSet;
+use std::fs;
+
+use common::{string, udata, unit_ref, Dwarf};
+use gimli::write::AttributeValue;
+use gimli::RunTimeEndian;
+use rshole::{canonical_type_name, Error, LayoutItem, LayoutOptions, LayoutWarning, Parser, StructMemberIter, StructValue, Type, Value};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs");
+
+fn parser() -> Parser {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+// members by name and holes as `(offset, size, trailing)`
+fn describe(layout: Vec<LayoutItem>) -> Vec<String> {
+    layout.into_iter().map(|item| match item {
+        LayoutItem::Member(member) => member.name.unwrap_or_default(),
+        LayoutItem::Hole(hole) => format!("{:?}", (hole.offset, hole.size, hole.trailing)),
+    }).collect()
+}
+
+#[test]
+fn layout() {
+    let parser = parser();
+    let holey = parser.get_struct("holey").unwrap().layout(&parser).unwrap();
+    assert_eq!(describe(holey), ["a", "(1, 7, false)", "b", "c", "(17, 3, false)", "d", "e", "(26, 6, true)"]);
+    let list_head = parser.get_struct("list_head").unwrap().layout(&parser).unwrap();
+    assert_eq!(describe(list_head), ["next", "prev"]);
+}
+
+#[test]
+fn struct_count() {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    assert_eq!(parser.struct_count(), 0);
+    parser.load_structs().unwrap();
+    // list_head, holey, packed_hdr, bits, inner, node, table and task
+    assert_eq!(parser.struct_count(), 8);
+}
+
+#[test]
+fn into_index() {
+    let parser = parser();
+    let expected: Vec<_> = parser.struct_dict.iter().map(|(name, dw_struct)| {
+        let members: Vec<_> = dw_struct.members_by_offset(&parser).unwrap().into_iter()
+            .map(|member| (member.name, member.offset, member.size, member.bit_size))
+            .collect();
+        (name.clone(), dw_struct.size, members)
+    }).collect();
+
+    let index = parser.into_index().unwrap();
+    assert_eq!(index.len(), expected.len());
+    for (name, size, members) in expected {
+        let indexed = index.get(&name).unwrap_or_else(|| panic!("{} not indexed", name));
+        assert_eq!(indexed.size, size, "{}", name);
+        let indexed_members: Vec<_> = indexed.members.iter()
+            .map(|member| (member.name.clone(), member.offset, member.size, member.bit_size))
+            .collect();
+        assert_eq!(indexed_members, members, "{}", name);
+    }
+    assert_eq!(index.get("holey").unwrap().members.len(), 5);
+    assert!(index.get("missing").is_none());
+}
+
+#[test]
+fn void() {
+    let parser = parser();
+    let value = parser.get_struct("inner").unwrap().members_by_offset(&parser).unwrap().remove(1);
+    let pointer = value.mb_type.unwrap();
+    assert_eq!(canonical_type_name(&pointer, &parser), "void *");
+    assert!(matches!(parser.get_type(pointer).unwrap(), Some(Type::Void)));
+
+    // an anonymous struct is not void
+    let node = parser.get_struct("node").unwrap();
+    let id = node.resolve_field_path(&parser, "id").unwrap().unwrap().0;
+    let members = match id.mb_type {
+        Some(Type::Union(id)) => id.members(&parser).unwrap(),
+        mb_type => panic!("id is a {:?}", mb_type)
+    };
+    match &members[1].mb_type {
+        Some(Type::Struct(anon)) => assert!(anon.name.is_empty(), "{:?}", anon.name),
+        mb_type => panic!("{:?}", mb_type)
+    }
+    let decl = parser.get_struct("inner").unwrap().to_c_decl(&parser).unwrap();
+    assert!(decl.contains("void *value;"), "{}", decl);
+    let decl = node.to_c_decl(&parser).unwrap();
+    assert!(!decl.contains("void"), "{}", decl);
+}
+
+#[test]
+fn field_paths() {
+    let parser = parser();
+    let task = parser.get_struct("task").unwrap();
+    let resolve = |path: &str| task.resolve_field_path(&parser, path).unwrap().map(|(member, offset)| (member.name.unwrap(), offset));
+    assert_eq!(resolve("files"), Some((String::from("files"), 16)));
+    assert_eq!(resolve("root.list.prev"), Some((String::from("prev"), 32)));
+    // lo and hi belong to an anonymous struct inside the union
+    assert_eq!(resolve("root.id.raw"), Some((String::from("raw"), 168)));
+    assert_eq!(resolve("root.id.hi"), Some((String::from("hi"), 172)));
+    assert_eq!(resolve("root.bits.flags"), Some((String::from("flags"), 212)));
+    // pointers aren't followed
+    assert_eq!(resolve("files.nr_buckets"), None);
+    assert_eq!(resolve("root.missing"), None);
+}
+
+#[test]
+fn offset_of_path() {
+    let parser = parser();
+    let task = parser.get_struct("task").unwrap();
+    let offset_of = |path: &str| task.offset_of_path(&parser, path).unwrap();
+    assert_eq!(offset_of("tasks"), Some(0));
+    // hdr is packed, len right after kind
+    assert_eq!(offset_of("root.hdr.len"), Some(217));
+    assert_eq!(offset_of("root.id.lo"), Some(168));
+    assert_eq!(offset_of("root.entries"), Some(40));
+    assert_eq!(offset_of("root.nothing"), None);
+}
+
+#[test]
+fn type_sizes() {
+    let parser = parser();
+    let types: Vec<_> = parser.get_struct("node").unwrap().members_by_offset(&parser).unwrap().into_iter()
+        .map(|member| member.mb_type.unwrap())
+        .collect();
+    let sizes: Vec<_> = types.iter().map(|type_inst| type_inst.size(&parser).unwrap()).collect();
+    // struct, array, union, enum, pointer, function pointer, pointer, struct,
+    // packed struct and flexible array
+    assert_eq!(sizes, [16, 128, 8, 4, 8, 8, 8, 8, 5, 0]);
+
+    // const char
+    let target = parser.get_type(types[4].clone()).unwrap().unwrap();
+    assert!(matches!(target, Type::Const(_)), "{:?}", target);
+    assert_eq!(target.size(&parser).unwrap(), 1);
+    let base = parser.get_type(target).unwrap().unwrap();
+    assert!(matches!(base, Type::Base(_)), "{:?}", base);
+    assert_eq!(base.size(&parser).unwrap(), 1);
+    assert_eq!(Type::Void.size(&parser).unwrap(), 0);
+
+    // volatile u32, a qualified typedef
+    let lock = parser.get_struct("table").unwrap().resolve_field_path(&parser, "lock").unwrap().unwrap().0;
+    assert_eq!(lock.mb_type.unwrap().size(&parser).unwrap(), 4);
+}
+
+#[test]
+fn union_members() {
+    let parser = parser();
+    let id = parser.get_struct("node").unwrap().resolve_field_path(&parser, "id").unwrap().unwrap().0;
+    let id = match id.mb_type {
+        Some(Type::Union(id)) => id,
+        mb_type => panic!("id is a {:?}", mb_type)
+    };
+    let members: Vec<_> = id.members(&parser).unwrap().into_iter().map(|member| (member.name, member.offset, member.size)).collect();
+    assert_eq!(members, [(Some(String::from("raw")), 0, 8), (None, 0, 8)]);
+}
+
+fn pointer(parser: &Parser, struct_name: &str, path: &str) -> rshole::Pointer {
+    let member = parser.get_struct(struct_name).unwrap().resolve_field_path(parser, path).unwrap().unwrap().0;
+    match member.mb_type {
+        Some(Type::Pointer(pointer)) => pointer,
+        mb_type => panic!("{} is a {:?}", path, mb_type)
+    }
+}
+
+#[test]
+fn pointer_targets() {
+    let parser = parser();
+    let parent = pointer(&parser, "node", "parent").target_struct(&parser).unwrap().unwrap();
+    assert_eq!((parent.name.as_str(), parent.size), ("node", 200));
+    assert_eq!(parent, parser.get_struct("node").unwrap());
+
+    // struct node **
+    let buckets = pointer(&parser, "table", "buckets");
+    assert!(buckets.target_struct(&parser).unwrap().is_none());
+    assert_eq!(buckets.target_struct_recursive(&parser).unwrap().unwrap().name, "node");
+
+    assert!(pointer(&parser, "inner", "value").target_struct(&parser).unwrap().is_none());
+    assert!(pointer(&parser, "table", "destroy").target_struct_recursive(&parser).unwrap().is_none());
+}
+
+#[test]
+fn address_classes_and_coarrays() {
+    // struct gpu { int *global; int *plain; int counts[4][*]; } with a GPU
+    // address space on `global` and a coarray of counts
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let global = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![
+        (gimli::DW_AT_byte_size, udata(8)),
+        (gimli::DW_AT_type, unit_ref(int)),
+        (gimli::DW_AT_address_class, gimli::write::AttributeValue::AddressClass(gimli::DwAddr(1))),
+    ]);
+    let plain = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_byte_size, udata(8)), (gimli::DW_AT_type, unit_ref(int))]);
+    let array = dwarf.add(root, gimli::DW_TAG_array_type, vec![(gimli::DW_AT_type, unit_ref(int))]);
+    dwarf.add(array, gimli::DW_TAG_subrange_type, vec![(gimli::DW_AT_count, udata(4))]);
+    let coarray = dwarf.add(root, gimli::DW_TAG_coarray_type, vec![(gimli::DW_AT_type, unit_ref(array))]);
+    dwarf.add(coarray, gimli::DW_TAG_subrange_type, vec![]);
+    let gpu = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("gpu")), (gimli::DW_AT_byte_size, udata(32))]);
+    dwarf.member(gpu, "global", global, 0);
+    dwarf.member(gpu, "plain", plain, 8);
+    dwarf.member(gpu, "counts", coarray, 16);
+    let parser = dwarf.parser();
+
+    let members = parser.get_struct("gpu").unwrap().members_by_offset(&parser).unwrap();
+    let classes: Vec<_> = members[..2].iter().map(|member| match &member.mb_type {
+        Some(Type::Pointer(pointer)) => pointer.address_class,
+        mb_type => panic!("{:?}", mb_type)
+    }).collect();
+    assert_eq!(classes, [Some(1), None]);
+    // each image holds a plain int[4]
+    assert!(matches!(&members[2].mb_type, Some(Type::Array(array)) if array.element_count == 4), "{:?}", members[2].mb_type);
+    assert_eq!(members[2].size, 16);
+}
+
+#[test]
+fn structs_containing_type() {
+    let parser = parser();
+    let containing = |type_name: &str| {
+        let mut names: Vec<_> = parser.structs_containing_type(type_name).unwrap().into_iter().map(|dw_struct| dw_struct.name.clone()).collect();
+        names.sort();
+        names
+    };
+    assert_eq!(containing("struct list_head"), ["node", "task"]);
+    // as an array element
+    assert_eq!(containing("struct holey"), ["table"]);
+    assert_eq!(containing("struct inner"), ["node"]);
+    // answered from the cache the second time
+    assert_eq!(containing("struct list_head"), ["node", "task"]);
+    // pointers don't embed the struct
+    assert_eq!(containing("struct table"), Vec::<String>::new());
+    assert_eq!(containing("struct missing"), Vec::<String>::new());
+}
+
+#[test]
+fn packed_structs() {
+    let parser = parser();
+    let packed: Vec<_> = ["packed_hdr", "holey", "bits", "node", "task"].into_iter()
+        .map(|name| (name, parser.get_struct(name).unwrap().is_packed(&parser).unwrap()))
+        .collect();
+    assert_eq!(packed, [("packed_hdr", true), ("holey", false), ("bits", false), ("node", false), ("task", false)]);
+}
+
+// struct inner { int x; static double d; } embedded at 4 in
+// struct outer { char c; struct inner i; }, and struct holder { char c;
+// struct wide w; } with w at 4 and only a declaration of wide in the unit
+fn static_member_dwarf() -> Parser {
+    let mut dwarf = Dwarf::new();
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let double = dwarf.base("double", 8, gimli::DW_ATE_float);
+    let long = dwarf.base("long", 8, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let inner = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("inner")), (gimli::DW_AT_byte_size, udata(4))]);
+    dwarf.member(inner, "x", int, 0);
+    dwarf.add(inner, gimli::DW_TAG_variable, vec![
+        (gimli::DW_AT_name, string("d")),
+        (gimli::DW_AT_type, unit_ref(double)),
+        (gimli::DW_AT_external, AttributeValue::FlagPresent),
+        (gimli::DW_AT_declaration, AttributeValue::FlagPresent),
+    ]);
+    let outer = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("outer")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(outer, "c", char_type, 0);
+    dwarf.member(outer, "i", inner, 4);
+
+    let wide = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("wide")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(wide, "l", long, 0);
+    let declaration = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("wide")), (gimli::DW_AT_declaration, AttributeValue::FlagPresent)]);
+    let holder = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("holder")), (gimli::DW_AT_byte_size, udata(12))]);
+    dwarf.member(holder, "c", char_type, 0);
+    dwarf.member(holder, "w", declaration, 4);
+    dwarf.parser()
+}
+
+#[test]
+fn packed_static_members() {
+    // the static double doesn't align inner, the declared wide is aligned
+    // by its definition
+    let parser = static_member_dwarf();
+    assert!(!parser.get_struct("outer").unwrap().is_packed(&parser).unwrap());
+    assert!(parser.get_struct("holder").unwrap().is_packed(&parser).unwrap());
+}
+
+#[test]
+fn packed_holes() {
+    // struct gapped { char a; int b; char c; } __attribute__((packed)) with
+    // c moved out to 6 by hand
+    let mut dwarf = Dwarf::new();
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let gapped = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("gapped")), (gimli::DW_AT_byte_size, udata(7))]);
+    dwarf.member(gapped, "a", char_type, 0);
+    dwarf.member(gapped, "b", int, 1);
+    dwarf.member(gapped, "c", char_type, 6);
+    let parser = dwarf.parser();
+    let gapped = parser.get_struct("gapped").unwrap();
+
+    let holes: Vec<_> = gapped.holes(&parser).unwrap().into_iter().map(|hole| (hole.offset, hole.size, hole.packed)).collect();
+    assert_eq!(holes, [(5, 1, true)]);
+    let skip = LayoutOptions::new().skip_packed_holes(true);
+    assert_eq!(describe(gapped.layout_with(&parser, &skip).unwrap()), ["a", "b", "c"]);
+    let decl = gapped.to_c_decl(&parser).unwrap();
+    assert!(decl.contains("/* XXX 1 bytes hole, packed, no padding expected */"), "{}", decl);
+
+    // holes of other structs aren't marked
+    let structs = self::parser();
+    assert!(structs.get_struct("holey").unwrap().holes(&structs).unwrap().iter().all(|hole| !hole.packed));
+}
+
+#[test]
+fn first_cacheline_members() {
+    let parser = parser();
+    let names = |name: &str, line_size| -> Vec<String> {
+        parser.get_struct(name).unwrap().first_cacheline_members(&parser, line_size).unwrap().into_iter()
+            .map(|member| member.name.unwrap())
+            .collect()
+    };
+    // root is the one that crosses the line
+    assert_eq!(names("task", 64), ["tasks", "files"]);
+    // bitfields by their bits, count reaches past the second byte
+    assert_eq!(names("bits", 2), ["valid", "dirty", "order"]);
+    assert!(names("holey", 0).is_empty());
+}
+
+#[test]
+fn member_at_bit() {
+    let parser = parser();
+    let at = |name: &str, bit_pos| parser.get_struct(name).unwrap().member_at_bit(&parser, bit_pos).unwrap().and_then(|member| member.name);
+    // valid:1, dirty:1, the unnamed :6, order:4, count:20, then flags
+    let bits: Vec<_> = [0, 1, 2, 7, 8, 11, 12, 31, 32, 39, 40].into_iter().map(|bit_pos| at("bits", bit_pos)).collect();
+    let expected = [Some("valid"), Some("dirty"), None, None, Some("order"), Some("order"), Some("count"), Some("count"), Some("flags"), Some("flags"), None];
+    assert_eq!(bits, expected.map(|name| name.map(String::from)));
+    // the hole after a
+    assert_eq!(at("holey", 8), None);
+    assert_eq!(at("holey", 64).as_deref(), Some("b"));
+}
+
+#[test]
+fn largest_hole() {
+    let parser = parser();
+    let largest = |name: &str| parser.get_struct(name).unwrap().largest_hole(&parser).unwrap().map(|hole| (hole.offset, hole.size));
+    // bigger than the 3 bytes after c and the 6 bytes of padding
+    assert_eq!(largest("holey"), Some((1, 7)));
+    assert_eq!(largest("table"), Some((156, 4)));
+    // only trailing padding
+    assert_eq!(largest("bits"), None);
+}
+
+#[test]
+fn members_by_cacheline() {
+    let parser = parser();
+    let lines = |name: &str, line_size| -> Vec<(u64, Vec<String>)> {
+        parser.get_struct(name).unwrap().members_by_cacheline(&parser, line_size).unwrap().into_iter()
+            .map(|(line, members)| (line, members.into_iter().map(|member| member.name.unwrap()).collect()))
+            .collect()
+    };
+    let line = |idx: u64, names: &[&str]| (idx, names.iter().map(|name| String::from(*name)).collect::<Vec<_>>());
+    // root spans four lines
+    assert_eq!(lines("task", 64), [
+        line(0, &["tasks", "files", "root"]),
+        line(1, &["root"]),
+        line(2, &["root"]),
+        line(3, &["root", "comm", "prio", "policy", "utime"]),
+        line(4, &["stime", "load"]),
+    ]);
+    // count's bits are in the first two, the last line is padding
+    assert_eq!(lines("bits", 2), [
+        line(0, &["valid", "dirty", "order", "count"]),
+        line(1, &["count"]),
+        line(2, &["flags"]),
+        line(3, &[]),
+    ]);
+    assert!(lines("bits", 0).is_empty());
+}
+
+#[test]
+fn decl_locations() {
+    let parser = parser();
+    let holey = parser.get_struct("holey").unwrap();
+    let location = holey.decl_location(&parser).unwrap().unwrap();
+    // the file joined with the directory it was compiled in
+    let file = location.file.unwrap();
+    assert!(file.starts_with('/') && file.ends_with("/benches/data/structs.c"), "{}", file);
+    assert_eq!((location.line, location.column), (Some(13), Some(8)));
+    let a = holey.members_by_offset(&parser).unwrap().remove(0).decl_location(&parser).unwrap().unwrap();
+    assert_eq!((a.line, a.column), (Some(13), Some(21)));
+
+    // nothing to say without the attributes
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("empty")), (gimli::DW_AT_byte_size, udata(0))]);
+    let parser = dwarf.parser();
+    assert_eq!(parser.get_struct("empty").unwrap().decl_location(&parser).unwrap(), None);
+}
+
+#[test]
+fn base_type_alignment() {
+    // an 8 byte vector type aligned to 16, at 16 in `aligned` and at 8 in
+    // `misaligned`
+    let mut dwarf = Dwarf::new();
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let vector = dwarf.base("vec8", 8, gimli::DW_ATE_float);
+    dwarf.unit.unit.get_mut(vector).set(gimli::DW_AT_alignment, udata(16));
+    let root = dwarf.root();
+    for (name, offset) in [("aligned", 16), ("misaligned", 8)] {
+        let dw_struct = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string(name)), (gimli::DW_AT_byte_size, udata(32))]);
+        dwarf.member(dw_struct, "c", char_type, 0);
+        dwarf.member(dw_struct, "x", vector, offset);
+    }
+    let parser = dwarf.parser();
+
+    let alignments: Vec<_> = parser.base_types().unwrap().into_iter().map(|base| (base.name, base.alignment)).collect();
+    assert_eq!(alignments, [(String::from("char"), None), (String::from("vec8"), Some(16))]);
+    let aligned = parser.get_struct("aligned").unwrap();
+    assert_eq!(aligned.members_by_offset(&parser).unwrap()[1].natural_alignment(&parser).unwrap(), 16);
+    assert!(aligned.misaligned_members(&parser).unwrap().is_empty());
+    let misaligned: Vec<_> = parser.get_struct("misaligned").unwrap().misaligned_members(&parser).unwrap().into_iter().map(|member| member.name.unwrap()).collect();
+    assert_eq!(misaligned, ["x"]);
+}
+
+#[test]
+fn zero_width_bitfields() {
+    // struct split { char a; int : 0; char b; }, as clang describes it
+    let mut dwarf = Dwarf::new();
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let split = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("split")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(split, "a", char_type, 0);
+    dwarf.add(split, gimli::DW_TAG_member, vec![
+        (gimli::DW_AT_type, unit_ref(int)),
+        (gimli::DW_AT_bit_size, udata(0)),
+        (gimli::DW_AT_data_bit_offset, udata(32)),
+    ]);
+    dwarf.member(split, "b", char_type, 4);
+    let parser = dwarf.parser();
+    let split = parser.get_struct("split").unwrap();
+
+    let members = split.members_by_offset(&parser).unwrap();
+    assert!(members[1].is_zero_width_bitfield());
+    assert_eq!((members[1].offset, members[1].size), (4, 0));
+    let holes: Vec<_> = split.holes(&parser).unwrap().into_iter().map(|hole| (hole.offset, hole.size, hole.separator)).collect();
+    assert_eq!(holes, [(1, 3, true), (5, 3, false)]);
+    let decl = split.to_c_decl(&parser).unwrap();
+    assert!(decl.contains("/* XXX 3 bytes hole, aligned by zero-width bitfield */"), "{}", decl);
+    assert!(decl.contains("int : 0;"), "{}", decl);
+}
+
+#[test]
+fn member_names() {
+    // the same names as full member iteration
+    let parser = parser();
+    for (name, dw_struct) in parser.struct_dict.iter() {
+        let names: Vec<_> = StructMemberIter::new(dw_struct, &parser).map(|member| member.unwrap().name).collect();
+        assert_eq!(dw_struct.member_names(&parser).unwrap(), names, "{}", name);
+    }
+    let node = parser.get_struct("node").unwrap().member_names(&parser).unwrap();
+    assert_eq!(node[..4], [Some(String::from("list")), Some(String::from("entries")), Some(String::from("id")), Some(String::from("state"))]);
+}
+
+#[test]
+fn misaligned_members() {
+    let parser = parser();
+    let misaligned = |name: &str| -> Vec<_> {
+        parser.get_struct(name).unwrap().misaligned_members(&parser).unwrap().into_iter().map(|member| member.name.unwrap()).collect()
+    };
+    // u32 len right after the u8 kind
+    assert_eq!(misaligned("packed_hdr"), ["len"]);
+    let hdr = parser.get_struct("packed_hdr").unwrap().members_by_offset(&parser).unwrap();
+    assert_eq!((hdr[0].natural_alignment(&parser).unwrap(), hdr[1].natural_alignment(&parser).unwrap()), (1, 4));
+    // order and count start inside their storage unit
+    assert!(misaligned("bits").is_empty());
+    assert!(misaligned("holey").is_empty());
+    assert!(misaligned("node").is_empty());
+}
+
+#[test]
+fn misaligned_static_members() {
+    // a static member of larger alignment doesn't misalign the struct it is
+    // in, nor make the header or rust declaration of its container packed
+    let parser = static_member_dwarf();
+    let outer = parser.get_struct("outer").unwrap();
+    assert!(outer.misaligned_members(&parser).unwrap().is_empty());
+    assert_eq!(outer.members_by_offset(&parser).unwrap()[1].natural_alignment(&parser).unwrap(), 4);
+    let header = parser.emit_header(&["outer"]).unwrap();
+    assert!(!header.contains("packed"), "{}", header);
+    let decl = outer.to_rust_decl(&parser).unwrap();
+    assert!(decl.contains("#[repr(C)]"), "{}", decl);
+
+    let holder = parser.get_struct("holder").unwrap();
+    let misaligned: Vec<_> = holder.misaligned_members(&parser).unwrap().into_iter().map(|member| member.name.unwrap()).collect();
+    assert_eq!(misaligned, ["w"]);
+}
+
+#[test]
+fn type_identity() {
+    let parser = parser();
+    let list = parser.get_struct("node").unwrap().resolve_field_path(&parser, "list").unwrap().unwrap().0.mb_type.unwrap();
+    let tasks = parser.get_struct("task").unwrap().resolve_field_path(&parser, "tasks").unwrap().unwrap().0.mb_type.unwrap();
+    assert_eq!(list, tasks);
+
+    // one entry per DIE however often it is referenced
+    let mut types = HashSet::new();
+    for name in ["node", "task", "list_head"] {
+        for member in parser.get_struct(name).unwrap().members_by_offset(&parser).unwrap() {
+            types.insert(member.mb_type.unwrap());
+        }
+    }
+    let list_heads = types.iter().filter(|type_inst| canonical_type_name(type_inst, &parser) == "struct list_head").count();
+    assert_eq!(list_heads, 1);
+    // next and prev share their pointer type
+    let pointers = types.iter().filter(|type_inst| canonical_type_name(type_inst, &parser) == "struct list_head *").count();
+    assert_eq!(pointers, 1);
+
+    // u32 and volatile u32
+    let u32_type = parser.get_struct("packed_hdr").unwrap().members_by_offset(&parser).unwrap().remove(1).mb_type.unwrap();
+    let lock = parser.get_struct("table").unwrap().resolve_field_path(&parser, "lock").unwrap().unwrap().0.mb_type.unwrap();
+    assert_ne!(u32_type, lock);
+}
+
+#[test]
+fn padding_report() {
+    let parser = parser();
+    let report = parser.padding_report().unwrap();
+    assert!(report.windows(2).all(|pair| pair[0].1 >= pair[1].1), "{:?}", report);
+    let padding = |name: &str| report.iter().find(|(struct_name, _)| struct_name == name).unwrap_or_else(|| panic!("no {} in {:?}", name, report)).1;
+    // 7 after a, 3 after c and 6 at the end
+    assert_eq!(padding("holey"), 16);
+    assert_eq!(padding("packed_hdr"), 0);
+    assert_eq!(padding("list_head"), 0);
+}
+
+#[test]
+fn padding_report_of_empty_structs() {
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("empty")), (gimli::DW_AT_byte_size, udata(0))]);
+    dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("opaque")), (gimli::DW_AT_byte_size, udata(16))]);
+    let parser = dwarf.parser();
+    // without members nothing is known to be padding
+    assert_eq!(parser.padding_report().unwrap(), [(String::from("empty"), 0), (String::from("opaque"), 0)]);
+}
+
+// struct flags { unsigned a:3, b:5; char c; unsigned d:4; } with dwarf 3
+// bit offsets for a and b and a dwarf 4 data bit offset for d
+fn flags(endian: RunTimeEndian) -> Parser {
+    let mut dwarf = Dwarf::with_encoding(4, 8).with_endian(endian);
+    let uint = dwarf.base("unsigned int", 4, gimli::DW_ATE_unsigned);
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let root = dwarf.root();
+    let flags = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("flags")), (gimli::DW_AT_byte_size, udata(8))]);
+    // counted from the most significant bit, the first bit on big endian
+    let bit_offset = |position: u64, size: u64| match endian {
+        RunTimeEndian::Little => 32 - position - size,
+        RunTimeEndian::Big => position,
+    };
+    for (name, position, size) in [("a", 0, 3), ("b", 3, 5)] {
+        dwarf.add(flags, gimli::DW_TAG_member, vec![
+            (gimli::DW_AT_name, string(name)),
+            (gimli::DW_AT_type, unit_ref(uint)),
+            (gimli::DW_AT_byte_size, udata(4)),
+            (gimli::DW_AT_bit_size, udata(size)),
+            (gimli::DW_AT_bit_offset, udata(bit_offset(position, size))),
+            (gimli::DW_AT_data_member_location, udata(0)),
+        ]);
+    }
+    dwarf.member(flags, "c", char_type, 1);
+    dwarf.add(flags, gimli::DW_TAG_member, vec![
+        (gimli::DW_AT_name, string("d")),
+        (gimli::DW_AT_type, unit_ref(uint)),
+        (gimli::DW_AT_bit_size, udata(4)),
+        (gimli::DW_AT_data_bit_offset, udata(20)),
+    ]);
+    dwarf.parser()
+}
+
+#[test]
+fn bit_positions() {
+    for endian in [RunTimeEndian::Little, RunTimeEndian::Big] {
+        let parser = flags(endian);
+        let members: Vec<_> = parser.get_struct("flags").unwrap().members_by_offset(&parser).unwrap().into_iter()
+            .map(|member| (member.bit_position(), member.offset, member.name.unwrap()))
+            .collect();
+        assert_eq!(members, [
+            (0, 0, String::from("a")),
+            (3, 0, String::from("b")),
+            (8, 1, String::from("c")),
+            (20, 2, String::from("d")),
+        ], "{:?}", endian);
+    }
+}
+
+#[test]
+fn bitfield_groups() {
+    let names = |parser: &Parser, name: &str| -> Vec<Vec<String>> {
+        parser.get_struct(name).unwrap().bitfield_groups(parser).unwrap().into_iter()
+            .map(|group| group.into_iter().map(|member| member.name.unwrap()).collect())
+            .collect()
+    };
+    // gcc leaves out the unnamed :6
+    assert_eq!(names(&parser(), "bits"), [["valid", "dirty", "order", "count"]]);
+    // c ends the run even though d is in the same unsigned int as a and b
+    assert_eq!(names(&flags(RunTimeEndian::Little), "flags"), [vec!["a", "b"], vec!["d"]]);
+
+    // struct split { unsigned a:4, b:4, c:30; }, c doesn't fit in the first
+    // unsigned int
+    let mut dwarf = Dwarf::new();
+    let uint = dwarf.base("unsigned int", 4, gimli::DW_ATE_unsigned);
+    let root = dwarf.root();
+    let split = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("split")), (gimli::DW_AT_byte_size, udata(8))]);
+    for (name, position, size) in [("a", 0, 4), ("b", 4, 4), ("c", 32, 30)] {
+        dwarf.add(split, gimli::DW_TAG_member, vec![
+            (gimli::DW_AT_name, string(name)),
+            (gimli::DW_AT_type, unit_ref(uint)),
+            (gimli::DW_AT_bit_size, udata(size)),
+            (gimli::DW_AT_data_bit_offset, udata(position)),
+        ]);
+    }
+    assert_eq!(names(&dwarf.parser(), "split"), [vec!["a", "b"], vec!["c"]]);
+}
+
+#[test]
+fn raw_attributes() {
+    let parser = parser();
+    let attributes = parser.get_struct("holey").unwrap().raw_attributes(&parser).unwrap();
+    let attributes: Vec<_> = attributes.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+    // the sibling as its .debug_info offset
+    assert_eq!(attributes, [
+        ("DW_AT_name", "holey"),
+        ("DW_AT_byte_size", "32"),
+        ("DW_AT_decl_file", "1"),
+        ("DW_AT_decl_line", "13"),
+        ("DW_AT_decl_column", "8"),
+        ("DW_AT_sibling", "0x106"),
+    ]);
+}
+
+#[test]
+fn density() {
+    let parser = parser();
+    let density = |name: &str| parser.get_struct(name).unwrap().density(&parser).unwrap();
+    // 16 of 32 bytes, the rest holes and padding
+    assert_eq!(density("holey"), 0.5);
+    assert_eq!(density("packed_hdr"), 1.0);
+    // 26 bits of bitfields and a byte
+    assert_eq!(density("bits"), 34.0 / 64.0);
+
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("empty")), (gimli::DW_AT_byte_size, udata(0))]);
+    let parser = dwarf.parser();
+    assert_eq!(parser.get_struct("empty").unwrap().density(&parser).unwrap(), 1.0);
+}
+
+#[test]
+fn structural_duplicates() {
+    // struct a { int x; struct a *next; } with b and e the same under other
+    // names, e through a typedef, and c pointing to a instead of itself
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let long = dwarf.base("long", 8, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let myint = dwarf.add(root, gimli::DW_TAG_typedef, vec![(gimli::DW_AT_name, string("myint")), (gimli::DW_AT_type, unit_ref(int))]);
+    let mut structs = Vec::new();
+    for (name, field) in [("a", int), ("b", int), ("c", int), ("e", myint)] {
+        let dw_struct = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string(name)), (gimli::DW_AT_byte_size, udata(16))]);
+        dwarf.member(dw_struct, "x", field, 0);
+        structs.push(dw_struct);
+    }
+    for (idx, target) in [(0, 0), (1, 1), (2, 0), (3, 3)] {
+        let pointer = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_byte_size, udata(8)), (gimli::DW_AT_type, unit_ref(structs[target]))]);
+        dwarf.member(structs[idx], "next", pointer, 8);
+    }
+    let single = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("d")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(single, "x", long, 0);
+    assert_eq!(dwarf.parser().structural_duplicates().unwrap(), [["a", "b", "e"]]);
+
+    assert!(parser().structural_duplicates().unwrap().is_empty());
+}
+
+#[test]
+fn merge_adjacent_holes() {
+    // struct gap { char a; int empty[0]; long b; } with empty placed at 2,
+    // inside the hole before b
+    let mut dwarf = Dwarf::new();
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let long = dwarf.base("long", 8, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let empty = dwarf.add(root, gimli::DW_TAG_array_type, vec![(gimli::DW_AT_type, unit_ref(int))]);
+    dwarf.add(empty, gimli::DW_TAG_subrange_type, vec![(gimli::DW_AT_count, udata(0))]);
+    let gap = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("gap")), (gimli::DW_AT_byte_size, udata(16))]);
+    dwarf.member(gap, "a", char_type, 0);
+    dwarf.member(gap, "empty", empty, 2);
+    dwarf.member(gap, "b", long, 8);
+    let parser = dwarf.parser();
+    let gap = parser.get_struct("gap").unwrap();
+
+    assert_eq!(describe(gap.layout(&parser).unwrap()), ["a", "(1, 1, false)", "empty", "(2, 6, false)", "b"]);
+    let merged = LayoutOptions::new().merge_adjacent_holes(true);
+    assert_eq!(describe(gap.layout_with(&parser, &merged).unwrap()), ["a", "(1, 7, false)", "empty", "b"]);
+    let holes: Vec<_> = gap.holes_with(&parser, &merged).unwrap().into_iter().map(|hole| (hole.offset, hole.size)).collect();
+    assert_eq!(holes, [(1, 7)]);
+}
+
+#[test]
+fn unresolved_members() {
+    // struct sets { int len; char *name; set text; set *next; } with a
+    // pascal set type nothing else knows how to read
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let root = dwarf.root();
+    let set_type = dwarf.add(root, gimli::DW_TAG_set_type, vec![(gimli::DW_AT_byte_size, udata(8)), (gimli::DW_AT_type, unit_ref(char_type))]);
+    let char_pointer = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_type, unit_ref(char_type))]);
+    let set_pointer = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_type, unit_ref(set_type))]);
+    let sets = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("sets")), (gimli::DW_AT_byte_size, udata(32))]);
+    dwarf.member(sets, "len", int, 0);
+    dwarf.member(sets, "name", char_pointer, 8);
+    dwarf.member(sets, "text", set_type, 16);
+    dwarf.member(sets, "next", set_pointer, 24);
+    let parser = dwarf.parser();
+    let sets = parser.get_struct("sets").unwrap();
+    assert_eq!(sets.unresolved_members(&parser).unwrap(), [Some(String::from("text")), Some(String::from("next"))]);
+    assert!(!sets.is_fully_resolved(&parser).unwrap());
+
+    let structs = self::parser();
+    for name in ["holey", "node", "task"] {
+        assert!(structs.get_struct(name).unwrap().is_fully_resolved(&structs).unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn type_usage_histogram() {
+    // struct a { int x; int y; char c; }; struct b { int z; const int w; };
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let root = dwarf.root();
+    let const_int = dwarf.add(root, gimli::DW_TAG_const_type, vec![(gimli::DW_AT_type, unit_ref(int))]);
+    let a = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("a")), (gimli::DW_AT_byte_size, udata(12))]);
+    dwarf.member(a, "x", int, 0);
+    dwarf.member(a, "y", int, 4);
+    dwarf.member(a, "c", char_type, 8);
+    let b = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("b")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(b, "z", int, 0);
+    dwarf.member(b, "w", const_int, 4);
+    // most used first, then by name
+    assert_eq!(dwarf.parser().type_usage_histogram().unwrap(), [
+        (String::from("int"), 3),
+        (String::from("char"), 1),
+        (String::from("const int"), 1),
+    ]);
+}
+
+#[test]
+fn declared_sizes() {
+    // an empty struct and a pointer to one that is only declared
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("empty")), (gimli::DW_AT_byte_size, udata(0))]);
+    let opaque = dwarf.add(root, gimli::DW_TAG_structure_type, vec![
+        (gimli::DW_AT_name, string("opaque")),
+        (gimli::DW_AT_declaration, gimli::write::AttributeValue::Flag(true)),
+    ]);
+    let pointer = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_byte_size, udata(8)), (gimli::DW_AT_type, unit_ref(opaque))]);
+    let handle = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("handle")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(handle, "p", pointer, 0);
+    let parser = dwarf.parser();
+
+    let empty = parser.get_struct("empty").unwrap();
+    assert_eq!((empty.size, empty.declared_size()), (0, Some(0)));
+    let handle = parser.get_struct("handle").unwrap();
+    assert_eq!(handle.declared_size(), Some(8));
+    let p = handle.members_by_offset(&parser).unwrap().remove(0).mb_type.unwrap();
+    match parser.get_type(p).unwrap() {
+        Some(Type::Struct(opaque)) => assert_eq!((opaque.size, opaque.declared_size()), (0, None)),
+        target => panic!("{:?}", target)
+    }
+}
+
+#[test]
+fn abstract_origins() {
+    // the members of `inst` take their name and type from those of
+    // `abstract`, `b` through a second origin and renamed on the way
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let root = dwarf.root();
+    let abstract_struct = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("abstract")), (gimli::DW_AT_byte_size, udata(8))]);
+    let x = dwarf.member(abstract_struct, "x", int, 0);
+    let y = dwarf.member(abstract_struct, "y", char_type, 4);
+    let inst = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("inst")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.add(inst, gimli::DW_TAG_member, vec![(gimli::DW_AT_abstract_origin, unit_ref(x)), (gimli::DW_AT_data_member_location, udata(4))]);
+    let middle = dwarf.add(abstract_struct, gimli::DW_TAG_member, vec![(gimli::DW_AT_name, string("b")), (gimli::DW_AT_abstract_origin, unit_ref(y))]);
+    dwarf.add(inst, gimli::DW_TAG_member, vec![(gimli::DW_AT_abstract_origin, unit_ref(middle)), (gimli::DW_AT_data_member_location, udata(0))]);
+    // two members that are each other's origin
+    let cycle = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("cycle")), (gimli::DW_AT_byte_size, udata(4))]);
+    let first = dwarf.add(cycle, gimli::DW_TAG_member, vec![]);
+    let second = dwarf.add(cycle, gimli::DW_TAG_member, vec![(gimli::DW_AT_abstract_origin, unit_ref(first))]);
+    dwarf.unit.unit.get_mut(first).set(gimli::DW_AT_abstract_origin, unit_ref(second));
+    let parser = dwarf.parser();
+
+    let members: Vec<_> = parser.get_struct("inst").unwrap().members_by_offset(&parser).unwrap().into_iter()
+        .map(|member| (member.name.unwrap(), member.offset, member.size))
+        .collect();
+    assert_eq!(members, [(String::from("b"), 0, 1), (String::from("x"), 4, 4)]);
+    assert!(parser.get_struct("cycle").unwrap().members_by_offset(&parser).is_err());
+}
+
+#[test]
+fn static_member_addresses() {
+    // a member located with DW_OP_addr, as some compilers write statics
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let globals = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("globals")), (gimli::DW_AT_byte_size, udata(4))]);
+    dwarf.member(globals, "value", int, 0);
+    let mut address = gimli::write::Expression::new();
+    address.op_addr(gimli::write::Address::Constant(0x404020));
+    dwarf.add(globals, gimli::DW_TAG_member, vec![
+        (gimli::DW_AT_name, string("count")),
+        (gimli::DW_AT_type, unit_ref(int)),
+        (gimli::DW_AT_data_member_location, gimli::write::AttributeValue::Exprloc(address)),
+    ]);
+    let parser = dwarf.parser();
+    let globals = parser.get_struct("globals").unwrap();
+    let members: Vec<_> = StructMemberIter::new(&globals, &parser).map(|member| {
+        let member = member.unwrap();
+        (member.name.unwrap(), member.is_static, member.static_address)
+    }).collect();
+    assert_eq!(members, [(String::from("value"), false, None), (String::from("count"), true, Some(0x404020))]);
+    // it takes no space in the struct
+    assert_eq!(globals.members_by_offset(&parser).unwrap().len(), 1);
+}
+
+#[test]
+fn read_values() {
+    let parser = parser();
+    let read = |name: &str, bytes: &[u8]| parser.get_struct(name).unwrap().read_value(&parser, bytes);
+
+    let mut holey = [0u8; 32];
+    holey[0] = b'x';
+    holey[8..16].copy_from_slice(&(-5i64).to_le_bytes());
+    holey[20..24].copy_from_slice(&7i32.to_le_bytes());
+    holey[24..26].copy_from_slice(&(-2i16).to_le_bytes());
+    assert_eq!(read("holey", &holey).unwrap().fields, [
+        (String::from("a"), Value::Signed(120)),
+        (String::from("b"), Value::Signed(-5)),
+        (String::from("c"), Value::Signed(0)),
+        (String::from("d"), Value::Signed(7)),
+        (String::from("e"), Value::Signed(-2)),
+    ]);
+    assert!(matches!(read("holey", &holey[..31]), Err(Error::BufferTooSmall(32))));
+
+    // valid 1, dirty 0, order 5, count 1000, skipping the unnamed :6
+    let word: u32 = 1 | 5 << 8 | 1000 << 12;
+    let mut bits = [0u8; 8];
+    bits[..4].copy_from_slice(&word.to_le_bytes());
+    bits[4] = 9;
+    assert_eq!(read("bits", &bits).unwrap().fields, [
+        (String::from("valid"), Value::Unsigned(1)),
+        (String::from("dirty"), Value::Unsigned(0)),
+        (String::from("order"), Value::Unsigned(5)),
+        (String::from("count"), Value::Unsigned(1000)),
+        (String::from("flags"), Value::Unsigned(9)),
+    ]);
+
+    // every member of a union from the same bytes, the anonymous struct's
+    // members as if they were the union's
+    let mut node = [0u8; 200];
+    node[144..152].copy_from_slice(&0x0000_0002_0000_0001u64.to_le_bytes());
+    let node = read("node", &node).unwrap();
+    assert_eq!(node.get("id"), Some(&Value::Union(StructValue { fields: vec![
+        (String::from("raw"), Value::Unsigned(0x0000_0002_0000_0001)),
+        (String::from("lo"), Value::Unsigned(1)),
+        (String::from("hi"), Value::Unsigned(2)),
+    ] })));
+    assert!(matches!(node.get("entries"), Some(Value::Array(entries)) if entries.len() == 8));
+    assert_eq!(node.get("parent"), Some(&Value::Pointer(0)));
+}
+
+#[test]
+fn read_big_endian_values() {
+    // a 5, b 17, c 'c' and d 9 in each byte order
+    for (endian, bytes) in [(RunTimeEndian::Little, [0x8d, b'c', 0x90, 0, 0, 0, 0, 0]), (RunTimeEndian::Big, [0xb1, b'c', 0x09, 0, 0, 0, 0, 0])] {
+        let parser = flags(endian);
+        let value = parser.get_struct("flags").unwrap().read_value(&parser, &bytes).unwrap();
+        assert_eq!(value.fields, [
+            (String::from("a"), Value::Unsigned(5)),
+            (String::from("b"), Value::Unsigned(17)),
+            (String::from("c"), Value::Signed(99)),
+            (String::from("d"), Value::Unsigned(9)),
+        ], "{:?}", endian);
+    }
+}
+
+// `opaque` and `list` are declared, only `list` is also defined, after
+fn declarations() -> Dwarf {
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    for name in ["opaque", "list"] {
+        dwarf.add(root, gimli::DW_TAG_structure_type, vec![
+            (gimli::DW_AT_name, string(name)),
+            (gimli::DW_AT_declaration, gimli::write::AttributeValue::Flag(true)),
+        ]);
+    }
+    let list = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("list")), (gimli::DW_AT_byte_size, udata(4))]);
+    dwarf.member(list, "len", int, 0);
+    dwarf
+}
+
+#[test]
+fn include_declarations() {
+    let parser = declarations().parser();
+    assert!(parser.get_struct("opaque").is_none());
+
+    let mut parser = declarations().builder().build().unwrap();
+    parser.set_include_declarations(true);
+    parser.load_structs().unwrap();
+    let opaque = parser.get_struct("opaque").unwrap();
+    assert!(opaque.is_declaration());
+    assert_eq!((opaque.size, opaque.declared_size()), (0, None));
+    // the definition replaces the declaration seen first
+    let list = parser.get_struct("list").unwrap();
+    assert!(!list.is_declaration());
+    assert_eq!(list.size, 4);
+}
+
+#[test]
+fn typedef_sizes() {
+    // typedefs take the size of what they name
+    let parser = parser();
+    let members = parser.get_struct("table").unwrap().members_by_offset(&parser).unwrap();
+    let nr_buckets = members.iter().find(|member| member.name.as_deref() == Some("nr_buckets")).unwrap();
+    match &nr_buckets.mb_type {
+        Some(Type::Typedef(typedef)) => assert_eq!((typedef.name.as_str(), typedef.size), ("u64", 8)),
+        mb_type => panic!("nr_buckets is a {:?}", mb_type)
+    }
+    assert_eq!(nr_buckets.size, 8);
+}
+
+#[test]
+fn typedef_cycles_are_errors() {
+    // typedef a b; typedef b a; struct cycle { a x; };
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    let a = dwarf.add(root, gimli::DW_TAG_typedef, vec![(gimli::DW_AT_name, string("a"))]);
+    let b = dwarf.add(root, gimli::DW_TAG_typedef, vec![(gimli::DW_AT_name, string("b")), (gimli::DW_AT_type, unit_ref(a))]);
+    dwarf.unit.unit.get_mut(a).set(gimli::DW_AT_type, unit_ref(b));
+    let cycle = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("cycle")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(cycle, "x", a, 0);
+    let parser = dwarf.parser();
+    assert!(parser.get_struct("cycle").unwrap().members_by_offset(&parser).is_err());
+}
+
+#[test]
+fn members_out_of_order() {
+    // struct reordered { char a; int b; char c; } with its member DIEs
+    // listed c, a, b
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let char_type = dwarf.base("char", 1, gimli::DW_ATE_signed_char);
+    let root = dwarf.root();
+    let reordered = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("reordered")), (gimli::DW_AT_byte_size, udata(12))]);
+    dwarf.member(reordered, "c", char_type, 8);
+    dwarf.member(reordered, "a", char_type, 0);
+    dwarf.member(reordered, "b", int, 4);
+    let parser = dwarf.parser();
+
+    let reordered = parser.get_struct("reordered").unwrap();
+    let names: Vec<_> = reordered.members_by_offset(&parser).unwrap().into_iter().map(|member| member.name.unwrap()).collect();
+    assert_eq!(names, ["a", "b", "c"]);
+    assert_eq!(describe(reordered.layout(&parser).unwrap()), ["a", "(1, 3, false)", "b", "c", "(9, 3, true)"]);
+}
+
+#[test]
+fn dwarf4_static_members() {
+    // before dwarf 5 a static member is a member declaration without a
+    // location
+    let mut dwarf = Dwarf::with_encoding(4, 8);
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let counter = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("counter")), (gimli::DW_AT_byte_size, udata(4))]);
+    dwarf.add(counter, gimli::DW_TAG_member, vec![
+        (gimli::DW_AT_name, string("total")),
+        (gimli::DW_AT_type, unit_ref(int)),
+        (gimli::DW_AT_external, gimli::write::AttributeValue::Flag(true)),
+        (gimli::DW_AT_declaration, gimli::write::AttributeValue::Flag(true)),
+    ]);
+    dwarf.member(counter, "value", int, 0);
+    let parser = dwarf.parser();
+
+    let counter = parser.get_struct("counter").unwrap();
+    let members = counter.members_by_offset(&parser).unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].name.as_deref(), Some("value"));
+    assert!(counter.holes(&parser).unwrap().is_empty());
+}
+
+// int[4] with `stride` on its subrange, or on the array DIE if `on_array`
+fn strided_array(stride: (gimli::DwAt, u64), on_array: bool) -> (Option<u64>, u64) {
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let mut array_attrs = vec![(gimli::DW_AT_type, unit_ref(int))];
+    let mut subrange_attrs = vec![(gimli::DW_AT_count, udata(4))];
+    if on_array {
+        array_attrs.push((stride.0, udata(stride.1)));
+    } else {
+        subrange_attrs.push((stride.0, udata(stride.1)));
+    }
+    let array = dwarf.add(root, gimli::DW_TAG_array_type, array_attrs);
+    dwarf.add(array, gimli::DW_TAG_subrange_type, subrange_attrs);
+    let strided = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("strided")), (gimli::DW_AT_byte_size, udata(64))]);
+    dwarf.member(strided, "a", array, 0);
+    let parser = dwarf.parser();
+
+    let member = parser.get_struct("strided").unwrap().members_by_offset(&parser).unwrap().remove(0);
+    match &member.mb_type {
+        Some(Type::Array(array)) => {
+            assert_eq!(array.byte_size(&parser).unwrap(), member.size);
+            (array.stride, member.size)
+        }
+        mb_type => panic!("not an array: {:?}", mb_type)
+    }
+}
+
+#[test]
+fn array_strides() {
+    assert_eq!(strided_array((gimli::DW_AT_byte_stride, 16), false), (Some(16), 64));
+    assert_eq!(strided_array((gimli::DW_AT_byte_stride, 8), true), (Some(8), 32));
+    // bit strides round up to whole bytes
+    assert_eq!(strided_array((gimli::DW_AT_bit_stride, 44), false), (Some(6), 24));
+}
+
+#[test]
+fn multi_dimensional_arrays() {
+    // struct matrix { int m[2][3]; }, one array DIE with two subranges
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let array = dwarf.add(root, gimli::DW_TAG_array_type, vec![(gimli::DW_AT_type, unit_ref(int))]);
+    dwarf.add(array, gimli::DW_TAG_subrange_type, vec![(gimli::DW_AT_count, udata(2))]);
+    dwarf.add(array, gimli::DW_TAG_subrange_type, vec![(gimli::DW_AT_upper_bound, udata(2))]);
+    let matrix = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("matrix")), (gimli::DW_AT_byte_size, udata(24))]);
+    dwarf.member(matrix, "m", array, 0);
+    let parser = dwarf.parser();
+
+    let member = parser.get_struct("matrix").unwrap().members_by_offset(&parser).unwrap().remove(0);
+    assert_eq!(member.size, 24);
+    let rows = match member.mb_type {
+        Some(Type::Array(rows)) => rows,
+        mb_type => panic!("not an array: {:?}", mb_type)
+    };
+    assert_eq!((rows.element_count, rows.byte_size(&parser).unwrap()), (2, 24));
+    match *rows.element {
+        Type::Array(row) => assert_eq!((row.element_count, row.byte_size(&parser).unwrap()), (3, 12)),
+        element => panic!("row is not an array: {:?}", element)
+    }
+    assert!(parser.get_struct("matrix").unwrap().to_c_decl(&parser).unwrap().contains("int m[2][3];"));
+}
+
+#[test]
+fn validate_layout() {
+    // compiler output is consistent, bitfields sharing a unit included
+    let parser = parser();
+    for (name, dw_struct) in parser.struct_dict.iter() {
+        let warnings = dw_struct.validate_layout(&parser).unwrap();
+        assert!(warnings.is_empty(), "{}: {:?}", name, warnings);
+    }
+
+    // struct broken { long a; int b; int c; } declared 12 bytes with b
+    // placed inside a
+    let mut dwarf = Dwarf::new();
+    let long = dwarf.base("long", 8, gimli::DW_ATE_signed);
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let broken = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("broken")), (gimli::DW_AT_byte_size, udata(12))]);
+    dwarf.member(broken, "a", long, 0);
+    dwarf.member(broken, "b", int, 4);
+    dwarf.member(broken, "c", int, 12);
+    let parser = dwarf.parser();
+
+    let warnings = parser.get_struct("broken").unwrap().validate_layout(&parser).unwrap();
+    assert!(matches!(&warnings[..], [
+        LayoutWarning::Overlap { first: Some(first), second: Some(second), offset: 4 },
+        LayoutWarning::SizeMismatch { declared: 12, computed: 16 },
+    ] if first == "a" && second == "b"), "{:?}", warnings);
+}
+
+// struct far { int *near; int *far; } with `far` carrying an explicit size
+fn pointer_sizes(address_size: u8) -> Vec<(u64, u64)> {
+    let mut dwarf = Dwarf::with_encoding(5, address_size);
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let near = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_type, unit_ref(int))]);
+    let far = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_type, unit_ref(int)), (gimli::DW_AT_byte_size, udata(2))]);
+    let pointers = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("pointers")), (gimli::DW_AT_byte_size, udata(16))]);
+    dwarf.member(pointers, "near", near, 0);
+    dwarf.member(pointers, "far", far, 8);
+    let parser = dwarf.parser();
+    parser.get_struct("pointers").unwrap().members_by_offset(&parser).unwrap().into_iter()
+        .map(|member| (member.size, member.mb_type.unwrap().size(&parser).unwrap()))
+        .collect()
+}
+
+#[test]
+fn pointer_byte_sizes() {
+    assert_eq!(pointer_sizes(8), [(8, 8), (2, 2)]);
+    assert_eq!(pointer_sizes(4), [(4, 4), (2, 2)]);
+}
+
+// struct located { int a; int b; } with b's location in a location list of
+// `entries` ranges that all say offset 4
+fn location_list(entries: u64) -> Parser {
+    use gimli::write::{Address, AttributeValue, Expression, Location, LocationList};
+
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let located = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("located")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(located, "a", int, 0);
+    let locations = (0..entries).map(|idx| {
+        let mut data = Expression::new();
+        data.op_plus_uconst(4);
+        Location::StartEnd { begin: Address::Constant(idx * 0x10), end: Address::Constant(idx * 0x10 + 0x10), data }
+    });
+    let list = dwarf.unit.unit.locations.add(LocationList(locations.collect()));
+    dwarf.add(located, gimli::DW_TAG_member, vec![
+        (gimli::DW_AT_name, string("b")),
+        (gimli::DW_AT_type, unit_ref(int)),
+        (gimli::DW_AT_data_member_location, AttributeValue::LocationListRef(list)),
+    ]);
+    dwarf.parser()
+}
+
+#[test]
+fn location_list_offsets() {
+    let parser = location_list(1);
+    let offsets: Vec<_> = parser.get_struct("located").unwrap().members_by_offset(&parser).unwrap().into_iter().map(|member| member.offset).collect();
+    assert_eq!(offsets, [0, 4]);
+
+    // the offset could depend on the pc, don't guess 0
+    let parser = location_list(2);
+    let located = parser.get_struct("located").unwrap();
+    assert_eq!(located.member_names(&parser).unwrap().len(), 2);
+    match located.members_by_offset(&parser) {
+        Err(Error::UnsupportedLocation(die_offset)) => assert!(die_offset > located.die_offset()),
+        result => panic!("{:?}", result.map(|members| members.len()))
+    }
+}
@@ -0,0 +1,83 @@
+// a parser over tests/data/types with tests/data/classes added as a second
+// object
+
+use std::fs::{self, File};
+
+use rshole::{Parser, Type};
+
+const TYPES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/types");
+const CLASSES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/classes");
+
+fn parser() -> Parser {
+    let data = fs::read(TYPES).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.add_object(File::open(CLASSES).unwrap()).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+fn struct_name(type_inst: Type) -> String {
+    match type_inst {
+        Type::Struct(dw_struct) => dw_struct.name,
+        type_inst => panic!("not a struct: {:?}", type_inst)
+    }
+}
+
+#[test]
+fn add_object() {
+    let parser = parser();
+    // a struct from each object, members of the second resolve in its units
+    assert!(parser.get_struct("qualifiers").is_some());
+    let child = parser.get_struct("Child").unwrap();
+    let members: Vec<_> = child.members_by_offset(&parser).unwrap().into_iter().map(|member| (member.name, member.offset)).collect();
+    assert_eq!(members, [(None, 0), (Some(String::from("b")), 4)]);
+    assert_eq!(child.to_c_decl(&parser).unwrap().lines().nth(1).unwrap(), "\t/* struct Plain <ancestor>; */ /*     0     2 */");
+
+    let producers = parser.producers().unwrap();
+    assert_eq!(producers.len(), 2);
+    assert!(producers[0].starts_with("GNU C1"), "{:?}", producers);
+    assert!(producers[1].starts_with("GNU C++"), "{:?}", producers);
+}
+
+#[test]
+fn add_object_twice() {
+    // structs defined in both keep their first definition
+    let mut parser = parser();
+    let count = parser.struct_dict.len();
+    let qualifiers = parser.get_struct("qualifiers").unwrap();
+    parser.add_object(File::open(TYPES).unwrap()).unwrap();
+    parser.load_structs().unwrap();
+    assert_eq!(parser.struct_dict.len(), count);
+    assert_eq!(parser.get_struct("qualifiers").unwrap(), qualifiers);
+}
+
+#[test]
+fn resolve_global() {
+    let parser = parser();
+    let qualifiers = parser.get_struct("qualifiers").unwrap();
+    let base = parser.get_struct("Base").unwrap();
+    let diamond = parser.get_struct("Diamond").unwrap();
+
+    assert_eq!(struct_name(parser.resolve_global(qualifiers.die_offset()).unwrap()), "qualifiers");
+    // past the end of the first object's .debug_info
+    assert_eq!(struct_name(parser.resolve_global(base.die_offset()).unwrap()), "Base");
+    assert_eq!(struct_name(parser.type_at_global(qualifiers.die_offset()).unwrap()), "qualifiers");
+    assert!(parser.type_at_global(base.die_offset()).is_err());
+
+    // an offset both objects have a unit at
+    assert_eq!(struct_name(parser.resolve_global_in(1, diamond.die_offset()).unwrap()), "Diamond");
+    assert!(parser.resolve_global_in(2, diamond.die_offset()).is_err());
+    assert!(parser.resolve_global(1 << 20).is_err());
+}
+
+#[test]
+fn type_at() {
+    // both objects have a single unit at the start of their .debug_info
+    let parser = parser();
+    let qualifiers = parser.get_struct("qualifiers").unwrap();
+    let diamond = parser.get_struct("Diamond").unwrap();
+    assert_eq!(struct_name(parser.type_at(0, qualifiers.die_offset()).unwrap()), "qualifiers");
+    assert_eq!(struct_name(parser.type_at(1, diamond.die_offset()).unwrap()), "Diamond");
+    assert!(parser.type_at(0, 1 << 20).is_err());
+    assert!(parser.type_at(2, qualifiers.die_offset()).is_err());
+}
@@ -0,0 +1,105 @@
+// compares the rendered layout of the structs in the test binaries with
+// pahole's output for them, see tests/data/pahole/ and the README on how to
+// regenerate the fixtures
+
+use std::fs;
+
+// binaries and the directory holding the pahole output for their structs
+const INPUTS: [(&str, &str); 3] = [
+    (concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs"), concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pahole/structs")),
+    (concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/types"), concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pahole/types")),
+    (concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/classes"), concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pahole/classes")),
+];
+
+// (name, declaration without whitespace, offset, size)
+type Member = (String, String, u64, u64);
+
+// what both outputs have in common: members by name, declaration, offset and
+// size, byte holes, trailing padding and the total size. Bit holes,
+// cacheline markers and pahole's summary lines are left out
+#[derive(Debug, Default, PartialEq)]
+struct Layout {
+    members: Vec<Member>,
+    holes: Vec<u64>,
+    padding: u64,
+    size: u64,
+}
+
+fn layout(text: &str) -> Layout {
+    let mut layout = Layout::default();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if let Some(hole) = line.strip_prefix("/* XXX ").and_then(|rest| rest.strip_suffix(" bytes hole, try to pack */")) {
+            layout.holes.push(hole.parse().unwrap());
+        } else if let Some(padding) = line.strip_prefix("/* padding: ") {
+            layout.padding = number(padding);
+        } else if let Some(size) = line.strip_prefix("/* size: ") {
+            layout.size = number(size);
+        } else if let Some((ancestor, comment)) = line.strip_prefix("/* ").and_then(|rest| rest.split_once(" <ancestor>; */ /*")) {
+            // base classes, `/* struct Base <ancestor>; */`
+            let (_, offset, size) = member(ancestor, comment);
+            layout.members.push((String::new(), strip_whitespace(ancestor), offset, size));
+        } else if let Some((decl, comment)) = line.split_once("; /*") {
+            let (name, offset, size) = member(decl, comment);
+            layout.members.push((name, strip_whitespace(decl), offset, size));
+        }
+    }
+    layout
+}
+
+// pahole separates the type from the declarator, `char * const p`
+fn strip_whitespace(decl: &str) -> String {
+    decl.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// leading digits of `text`
+fn number(text: &str) -> u64 {
+    let digits: String = text.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().unwrap()
+}
+
+// bitfields are `offset:bit size` in pahole, placed at the byte holding
+// their first bit like the crate does
+fn member(decl: &str, comment: &str) -> (String, u64, u64) {
+    let comment = comment.trim_end_matches("*/").replace(':', ": ");
+    let fields: Vec<&str> = comment.split_whitespace().collect();
+    let (offset, size) = match fields[..] {
+        [offset, bit, size] => (number(offset) + number(bit) / 8, number(size)),
+        [offset, size] => (number(offset), number(size)),
+        _ => panic!("unexpected member comment `{}`", comment)
+    };
+    (declared_name(decl), offset, size)
+}
+
+// the identifier being declared, `(*name)` for function pointers
+fn declared_name(decl: &str) -> String {
+    let name = match decl.rfind("(*") {
+        Some(idx) => &decl[idx + 2..],
+        None => {
+            let decl = decl.split(':').next().unwrap().trim_end();
+            let decl = decl.split('[').next().unwrap();
+            decl.rsplit([' ', '*']).next().unwrap()
+        }
+    };
+    name.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect()
+}
+
+#[test]
+fn matches_pahole() {
+    for (input, fixtures) in INPUTS {
+        let data = fs::read(input).unwrap();
+        let mut parser = rshole::Parser::from_bytes(&data).unwrap();
+        parser.load_structs().unwrap();
+
+        let mut fixtures: Vec<_> = fs::read_dir(fixtures).unwrap().map(|entry| entry.unwrap().path()).collect();
+        fixtures.sort();
+        assert!(!fixtures.is_empty());
+        for fixture in fixtures {
+            let name = fixture.file_stem().and_then(|stem| stem.to_str()).unwrap();
+            let dw_struct = parser.get_struct(name).unwrap_or_else(|| panic!("no struct {}", name));
+            let expected = layout(&fs::read_to_string(&fixture).unwrap());
+            let actual = layout(&dw_struct.to_c_decl(&parser).unwrap());
+            assert_eq!(actual, expected, "layout of struct {} differs from {}", name, fixture.display());
+        }
+    }
+}
@@ -0,0 +1,557 @@
+// Parser level queries, over tests/data/types and synthetic dwarf
+
+mod common;
+
+use std::error::Error as _;
+use std::fs;
+use std::io;
+use std::process::{self, Command};
+
+use object::{Object, ObjectSection, ObjectSymbol};
+
+use common::{string, udata, unit_ref, write_dwarf, Dwarf};
+use rshole::{EnumValue, Error, Parser, ParserBuilder, SectionLayout, Type};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/types");
+
+fn parser() -> Parser {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+// .debug_info offsets of the DIEs named `name`
+fn find_named(name: &str) -> Vec<u64> {
+    let data = fs::read(INPUT).unwrap();
+    let file = object::File::parse(&*data).unwrap();
+    let load = |section: &str| gimli::EndianSlice::new(file.section_by_name(section).unwrap().data().unwrap(), gimli::LittleEndian);
+    let dwarf = gimli::Dwarf::load(|id| -> Result<_, gimli::Error> {
+        Ok(file.section_by_name(id.name()).map(|_| load(id.name())).unwrap_or_default())
+    }).unwrap();
+    let mut found = Vec::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next().unwrap() {
+        let unit = dwarf.unit(header).unwrap();
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs().unwrap() {
+            if let Some(attr) = entry.attr_value(gimli::DW_AT_name).unwrap() {
+                if dwarf.attr_string(&unit, attr).unwrap().slice() == name.as_bytes() {
+                    found.push(entry.offset().to_debug_info_offset(&unit.header).unwrap().0 as u64);
+                }
+            }
+        }
+    }
+    found
+}
+
+#[test]
+fn die_offsets() {
+    let parser = parser();
+    let qualifiers = parser.get_struct("qualifiers").unwrap();
+    // the struct, then the variable of the same name
+    assert_eq!(qualifiers.die_offset(), find_named("qualifiers")[0]);
+    match parser.type_at_global(qualifiers.die_offset()).unwrap() {
+        Type::Struct(dw_struct) => assert_eq!(dw_struct, qualifiers),
+        type_inst => panic!("{:?}", type_inst)
+    }
+    let lock = &qualifiers.members_by_offset(&parser).unwrap()[0];
+    assert_eq!(lock.name.as_deref(), Some("lock"));
+    assert_eq!(vec![lock.die_offset()], find_named("lock"));
+}
+
+#[test]
+fn base_types() {
+    let mut parser = parser();
+    // int and char are in both objects
+    parser.add_object(fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/classes")).unwrap()).unwrap();
+    let base_types = parser.base_types().unwrap();
+    let mut names: Vec<_> = base_types.iter().map(|base| base.name.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), base_types.len(), "{:?}", names);
+
+    let base = |name: &str| base_types.iter().find(|base| base.name == name).map(|base| (base.size, base.encoding));
+    assert_eq!(base("int"), Some((4, gimli::DW_ATE_signed)));
+    assert_eq!(base("char"), Some((1, gimli::DW_ATE_signed_char)));
+    assert_eq!(base("short int"), Some((2, gimli::DW_ATE_signed)));
+    assert_eq!(base("unsigned char"), Some((1, gimli::DW_ATE_unsigned_char)));
+    // only in classes
+    assert_eq!(base("long int"), Some((8, gimli::DW_ATE_signed)));
+}
+
+#[test]
+fn base_type_bit_sizes() {
+    // a _BitInt(24) in 4 bytes and a type with only a bit size
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    let bitint = dwarf.base("_BitInt(24)", 4, gimli::DW_ATE_signed);
+    dwarf.unit.unit.get_mut(bitint).set(gimli::DW_AT_bit_size, udata(24));
+    dwarf.add(root, gimli::DW_TAG_base_type, vec![
+        (gimli::DW_AT_name, string("u12")),
+        (gimli::DW_AT_bit_size, udata(12)),
+        (gimli::DW_AT_encoding, gimli::write::AttributeValue::Encoding(gimli::DW_ATE_unsigned)),
+    ]);
+    dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let base_types = dwarf.parser().base_types().unwrap();
+    let sizes: Vec<_> = base_types.into_iter().map(|base| (base.name, base.size, base.bit_size)).collect();
+    assert_eq!(sizes, [
+        (String::from("_BitInt(24)"), 4, Some(24)),
+        (String::from("u12"), 2, Some(12)),
+        (String::from("int"), 4, None),
+    ]);
+}
+
+#[test]
+fn load_progress() {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    let mut calls = Vec::new();
+    parser.load_structs_with_progress(|done, total| calls.push((done, total))).unwrap();
+    assert_eq!(calls, [(1, 1)]);
+    assert!(parser.get_struct("qualifiers").is_some());
+
+    // only the units of a new object are walked
+    parser.add_object(fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/classes")).unwrap()).unwrap();
+    let total = parser.units().unwrap().len();
+    calls.clear();
+    parser.load_structs_with_progress(|done, total| calls.push((done, total))).unwrap();
+    let expected: Vec<_> = (2..=total).map(|done| (done, total)).collect();
+    assert_eq!(calls, expected);
+    assert!(parser.get_struct("Derived").is_some());
+}
+
+#[test]
+fn reload_structs() {
+    // nothing new to walk
+    let mut parser = parser();
+    let count = parser.struct_count();
+    let mut calls = Vec::new();
+    parser.load_structs_with_progress(|done, total| calls.push((done, total))).unwrap();
+    assert!(calls.is_empty(), "{:?}", calls);
+    assert_eq!(parser.struct_count(), count);
+
+    // the indexed load picks up after the full one, through the new
+    // object's .debug_pubtypes
+    parser.add_object(fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pubtypes")).unwrap()).unwrap();
+    parser.load_structs_indexed().unwrap();
+    assert_eq!(parser.struct_count(), count + 1);
+    assert!(parser.get_struct("listed").is_some());
+    assert!(parser.get_struct("local").is_none());
+}
+
+#[test]
+fn producers() {
+    let producers = parser().producers().unwrap();
+    assert_eq!(producers.len(), 1);
+    assert!(producers[0].starts_with("GNU C"), "{:?}", producers);
+}
+
+// struct bounds { int a[N]; } from `producer`, with a subrange that has both
+// DW_AT_upper_bound 3 and DW_AT_count 8
+fn bounds(producer: &str) -> u64 {
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    dwarf.unit.unit.get_mut(root).set(gimli::DW_AT_producer, string(producer));
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let array = dwarf.add(root, gimli::DW_TAG_array_type, vec![(gimli::DW_AT_type, unit_ref(int))]);
+    dwarf.add(array, gimli::DW_TAG_subrange_type, vec![
+        (gimli::DW_AT_upper_bound, udata(3)),
+        (gimli::DW_AT_count, udata(8)),
+    ]);
+    let bounds = dwarf.add(root, gimli::DW_TAG_structure_type, vec![
+        (gimli::DW_AT_name, string("bounds")),
+        (gimli::DW_AT_byte_size, udata(32)),
+    ]);
+    dwarf.member(bounds, "a", array, 0);
+
+    let parser = dwarf.parser();
+    assert_eq!(parser.producers().unwrap(), [producer]);
+    let members = parser.get_struct("bounds").unwrap().members_by_offset(&parser).unwrap();
+    match &members[0].mb_type {
+        Some(Type::Array(array)) => array.element_count,
+        mb_type => panic!("not an array: {:?}", mb_type)
+    }
+}
+
+#[test]
+fn array_bounds_by_producer() {
+    // gcc's DW_AT_upper_bound and llvm's DW_AT_count win for their own units
+    assert_eq!(bounds("GNU C17 12.2.0"), 4);
+    assert_eq!(bounds("clang version 16.0.0"), 8);
+    assert_eq!(bounds("rustc version 1.70.0"), 8);
+}
+
+// errors go through `?` into callers' own error types
+fn open(data: &[u8]) -> Result<Parser, Box<dyn std::error::Error>> {
+    Ok(Parser::from_bytes(data)?)
+}
+
+#[test]
+fn errors() {
+    let err = match Parser::from_bytes(b"not an object file") {
+        Ok(_) => panic!("parsed garbage"),
+        Err(err) => err
+    };
+    assert!(matches!(err, Error::Object(_)), "{:?}", err);
+    assert!(err.to_string().starts_with("object error: "), "{}", err);
+    assert!(err.source().is_some());
+    assert!(open(b"not an object file").is_err());
+
+    let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+    assert_eq!(err.to_string(), "io error: missing");
+    let err = Error::from(gimli::Error::TypeMismatch);
+    assert!(matches!(err, Error::Gimli(gimli::Error::TypeMismatch)));
+    assert!(Error::NoDebugInfo.source().is_none());
+}
+
+// struct with a name that isn't utf-8
+fn bad_name() -> Dwarf {
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    dwarf.add(root, gimli::DW_TAG_structure_type, vec![
+        (gimli::DW_AT_name, gimli::write::AttributeValue::String(b"bad\xff".to_vec())),
+        (gimli::DW_AT_byte_size, udata(4)),
+    ]);
+    dwarf
+}
+
+#[test]
+fn strict_names() {
+    let parser = bad_name().parser();
+    assert!(parser.get_struct("bad\u{fffd}").is_some());
+
+    let mut parser = bad_name().builder().build().unwrap();
+    parser.set_strict_names(true);
+    let err = parser.load_structs().unwrap_err();
+    assert!(matches!(err, Error::Gimli(gimli::Error::BadUtf8)), "{:?}", err);
+}
+
+#[test]
+fn no_debug_info() {
+    // abbreviations without any units
+    let abbrev = Dwarf::new().sections().into_iter().find(|(id, _)| *id == gimli::SectionId::DebugAbbrev).unwrap().1;
+    assert!(matches!(ParserBuilder::new().debug_abbrev(abbrev).build(), Err(Error::NoDebugInfo)));
+
+    let stripped = std::env::temp_dir().join(format!("rshole-stripped-{}", process::id()));
+    match Command::new("strip").args(["-g", "-o"]).arg(&stripped).arg(INPUT).status() {
+        Ok(status) => assert!(status.success()),
+        Err(err) => {
+            eprintln!("skipping, no strip: {}", err);
+            return;
+        }
+    }
+    let data = fs::read(&stripped).unwrap();
+    fs::remove_file(&stripped).unwrap();
+    assert!(matches!(Parser::from_bytes(&data), Err(Error::NoDebugInfo)));
+}
+
+// enum wide : __int128 { SMALL = 5, NEG = -1, HUGE = 1 << 100 } with the
+// values as 16 byte blocks in target order
+fn wide_enum(endian: gimli::RunTimeEndian) -> Vec<(String, EnumValue)> {
+    let mut dwarf = Dwarf::new().with_endian(endian);
+    let int128 = dwarf.base("__int128", 16, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    let wide = dwarf.add(root, gimli::DW_TAG_enumeration_type, vec![
+        (gimli::DW_AT_name, string("wide")),
+        (gimli::DW_AT_byte_size, udata(16)),
+        (gimli::DW_AT_type, unit_ref(int128)),
+    ]);
+    for (name, value) in [("SMALL", 5i128), ("NEG", -1), ("HUGE", 1 << 100)] {
+        let bytes = match endian {
+            gimli::RunTimeEndian::Little => value.to_le_bytes(),
+            gimli::RunTimeEndian::Big => value.to_be_bytes(),
+        };
+        dwarf.add(wide, gimli::DW_TAG_enumerator, vec![
+            (gimli::DW_AT_name, string(name)),
+            (gimli::DW_AT_const_value, gimli::write::AttributeValue::Block(bytes.to_vec())),
+        ]);
+    }
+    let holder = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("holder")), (gimli::DW_AT_byte_size, udata(16))]);
+    dwarf.member(holder, "w", wide, 0);
+    let parser = dwarf.parser();
+
+    match parser.get_struct("holder").unwrap().members_by_offset(&parser).unwrap().remove(0).mb_type {
+        Some(Type::Enum(wide)) => wide.enumerators(&parser).unwrap(),
+        mb_type => panic!("{:?}", mb_type)
+    }
+}
+
+#[test]
+fn wide_enumerators() {
+    for endian in [gimli::RunTimeEndian::Little, gimli::RunTimeEndian::Big] {
+        assert_eq!(wide_enum(endian), [
+            (String::from("SMALL"), EnumValue::Int(5)),
+            (String::from("NEG"), EnumValue::Int(-1)),
+            (String::from("HUGE"), EnumValue::Big((1i128 << 100).to_le_bytes().to_vec())),
+        ], "{:?}", endian);
+    }
+}
+
+// enumerators of an enum of `encoding`, with their values in `values`
+fn enumerators(encoding: gimli::DwAte, values: Vec<gimli::write::AttributeValue>) -> Result<Vec<(String, EnumValue)>, Error> {
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, encoding);
+    let root = dwarf.root();
+    let dw_enum = dwarf.add(root, gimli::DW_TAG_enumeration_type, vec![
+        (gimli::DW_AT_name, string("fixed")),
+        (gimli::DW_AT_byte_size, udata(4)),
+        (gimli::DW_AT_type, unit_ref(int)),
+    ]);
+    for (idx, value) in values.into_iter().enumerate() {
+        dwarf.add(dw_enum, gimli::DW_TAG_enumerator, vec![(gimli::DW_AT_name, string(&format!("E{}", idx))), (gimli::DW_AT_const_value, value)]);
+    }
+    let parser = dwarf.parser();
+    parser.enums().unwrap().remove(0).enumerators(&parser)
+}
+
+#[test]
+fn fixed_size_enumerators() {
+    use gimli::write::AttributeValue::{self, Data1, Data2, Data4};
+    // sign extended from the width of the form in a signed enum only
+    let values = |encoding| enumerators(encoding, vec![Data1(0xff), Data2(0x8000), Data4(0xffff_ffff), Data4(7)]).unwrap().into_iter().map(|(_, value)| value).collect::<Vec<_>>();
+    assert_eq!(values(gimli::DW_ATE_signed), [EnumValue::Int(-1), EnumValue::Int(-0x8000), EnumValue::Int(-1), EnumValue::Int(7)]);
+    assert_eq!(values(gimli::DW_ATE_unsigned), [EnumValue::Int(0xff), EnumValue::Int(0x8000), EnumValue::Int(0xffff_ffff), EnumValue::Int(7)]);
+    // not a constant
+    assert!(enumerators(gimli::DW_ATE_signed, vec![AttributeValue::String(b"one".to_vec())]).is_err());
+}
+
+#[test]
+fn other_vendor_extensions() {
+    // an apple tag is a vendor extension, but not one of gnu's
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    let property = dwarf.add(root, gimli::DW_TAG_APPLE_property, vec![]);
+    let holder = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("holder")), (gimli::DW_AT_byte_size, udata(0))]);
+    dwarf.member(holder, "p", property, 0);
+    let parser = dwarf.parser();
+    match parser.get_struct("holder").unwrap().members_by_offset(&parser).unwrap().remove(0).mb_type {
+        Some(Type::Unknown(unknown)) => assert!(unknown.is_vendor_extension() && !unknown.is_gnu_extension()),
+        mb_type => panic!("{:?}", mb_type)
+    }
+}
+
+#[test]
+fn enums() {
+    let parser = parser();
+    let enums = parser.enums().unwrap();
+    let ranges: Vec<_> = enums.iter().map(|dw_enum| (dw_enum.name.clone(), dw_enum.value_range(&parser).unwrap())).collect();
+    // in DIE order, color before the anonymous enum inside struct enums
+    assert_eq!(ranges, [
+        (Some(String::from("color")), Some((EnumValue::Int(-1), EnumValue::Int(0)))),
+        (None, Some((EnumValue::Int(1), EnumValue::Int(2)))),
+    ]);
+    assert_ne!(enums[0].die_offset(), enums[1].die_offset());
+
+    // enum empty {}; has no range
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    dwarf.add(root, gimli::DW_TAG_enumeration_type, vec![(gimli::DW_AT_name, string("empty")), (gimli::DW_AT_byte_size, udata(4)), (gimli::DW_AT_type, unit_ref(int))]);
+    let parser = dwarf.parser();
+    let enums = parser.enums().unwrap();
+    assert_eq!(enums.len(), 1);
+    assert_eq!(enums[0].value_range(&parser).unwrap(), None);
+}
+
+#[test]
+fn unit_ranges() {
+    // the only code is _start
+    let data = fs::read(INPUT).unwrap();
+    let file = object::File::parse(&*data).unwrap();
+    let start = file.symbols().find(|symbol| symbol.name() == Ok("_start")).unwrap();
+    let units = parser().units().unwrap();
+    assert_eq!(units.len(), 1);
+    assert_eq!(units[0].name.as_deref(), Some("types.c"));
+    assert_eq!(units[0].low_pc, Some(start.address()));
+    assert_eq!(units[0].high_pc, Some(start.address() + start.size()));
+}
+
+// a unit covering 0x1000..0x1040 with DW_AT_high_pc as `high_pc`
+fn unit_range(high_pc: gimli::write::AttributeValue) -> (Option<u64>, Option<u64>) {
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    let entry = dwarf.unit.unit.get_mut(root);
+    entry.set(gimli::DW_AT_low_pc, gimli::write::AttributeValue::Address(gimli::write::Address::Constant(0x1000)));
+    entry.set(gimli::DW_AT_high_pc, high_pc);
+    let units = dwarf.parser().units().unwrap();
+    (units[0].low_pc, units[0].high_pc)
+}
+
+#[test]
+fn high_pc_forms() {
+    // an offset from low_pc since dwarf 4, an address before
+    assert_eq!(unit_range(udata(0x40)), (Some(0x1000), Some(0x1040)));
+    assert_eq!(unit_range(gimli::write::AttributeValue::Address(gimli::write::Address::Constant(0x1040))), (Some(0x1000), Some(0x1040)));
+}
+
+#[test]
+fn cross_unit_references() {
+    // struct pair { int a; int b; } in the second unit, with the type of
+    // `a` in the first one, as dwz leaves it
+    let encoding = gimli::Encoding { format: gimli::Format::Dwarf32, version: 4, address_size: 8 };
+    let mut dwarf = gimli::write::Dwarf::new();
+    let first = dwarf.units.add(gimli::write::Unit::new(encoding, gimli::write::LineProgram::none()));
+    let second = dwarf.units.add(gimli::write::Unit::new(encoding, gimli::write::LineProgram::none()));
+    let add = |unit: &mut gimli::write::Unit, parent, tag, attrs: Vec<(gimli::DwAt, gimli::write::AttributeValue)>| {
+        let id = unit.add(parent, tag);
+        for (name, value) in attrs {
+            unit.get_mut(id).set(name, value);
+        }
+        id
+    };
+    let base = |name: &str| vec![
+        (gimli::DW_AT_name, string(name)),
+        (gimli::DW_AT_byte_size, udata(4)),
+        (gimli::DW_AT_encoding, gimli::write::AttributeValue::Encoding(gimli::DW_ATE_signed)),
+    ];
+
+    let unit = dwarf.units.get_mut(first);
+    let root = unit.root();
+    let shared = add(unit, root, gimli::DW_TAG_base_type, base("shared"));
+    let unit = dwarf.units.get_mut(second);
+    let root = unit.root();
+    let int = add(unit, root, gimli::DW_TAG_base_type, base("int"));
+    let pair = add(unit, root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("pair")), (gimli::DW_AT_byte_size, udata(8))]);
+    add(unit, pair, gimli::DW_TAG_member, vec![
+        (gimli::DW_AT_name, string("a")),
+        (gimli::DW_AT_type, gimli::write::AttributeValue::DebugInfoRef(gimli::write::Reference::Entry(first, shared))),
+        (gimli::DW_AT_data_member_location, udata(0)),
+    ]);
+    add(unit, pair, gimli::DW_TAG_member, vec![
+        (gimli::DW_AT_name, string("b")),
+        (gimli::DW_AT_type, unit_ref(int)),
+        (gimli::DW_AT_data_member_location, udata(4)),
+    ]);
+
+    let mut parser = write_dwarf(&mut dwarf).build().unwrap();
+    parser.load_structs().unwrap();
+    let pair = parser.get_struct("pair").unwrap();
+    let members: Vec<_> = pair.members_by_offset(&parser).unwrap().into_iter().map(|member| {
+        let name = match member.mb_type {
+            Some(Type::Base(base)) => base.name,
+            mb_type => panic!("{:?}", mb_type)
+        };
+        (name, member.offset, member.size)
+    }).collect();
+    assert_eq!(members, [(String::from("shared"), 0, 4), (String::from("int"), 4, 4)]);
+    assert!(pair.holes(&parser).unwrap().is_empty());
+}
+
+// the debug sections of `data` by file offset, as they would be mapped
+fn section_layout(data: &[u8]) -> SectionLayout {
+    let file = object::File::parse(data).unwrap();
+    let mut layout = SectionLayout::new();
+    for id in [
+        gimli::SectionId::DebugAbbrev,
+        gimli::SectionId::DebugInfo,
+        gimli::SectionId::DebugLine,
+        gimli::SectionId::DebugLineStr,
+        gimli::SectionId::DebugStr,
+        gimli::SectionId::DebugStrOffsets,
+    ] {
+        if let Some((offset, size)) = file.section_by_name(id.name()).and_then(|section| section.file_range()) {
+            layout = layout.section(id, offset, size);
+        }
+    }
+    layout
+}
+
+#[test]
+fn from_memory() {
+    let data = fs::read(INPUT).unwrap();
+    let parser = parser();
+    let expected = parser.get_struct("qualifiers").unwrap();
+    // a buffer indexed by address and a file read at offsets like
+    // /proc/<pid>/mem
+    for mut memory in [
+        Parser::from_memory(data.clone(), section_layout(&data)).unwrap(),
+        Parser::from_memory(fs::File::open(INPUT).unwrap(), section_layout(&data)).unwrap(),
+    ] {
+        memory.load_structs().unwrap();
+        let qualifiers = memory.get_struct("qualifiers").unwrap();
+        assert_eq!(qualifiers, expected);
+        assert_eq!(qualifiers.to_c_decl(&memory).unwrap(), expected.to_c_decl(&parser).unwrap());
+    }
+
+    // a section past the end of memory
+    let layout = SectionLayout::new().section(gimli::SectionId::DebugInfo, data.len() as u64 - 4, 8);
+    match Parser::from_memory(data.as_slice(), layout) {
+        Ok(_) => panic!("read past the end"),
+        Err(err) => assert!(matches!(err, Error::Io(_)), "{:?}", err)
+    }
+}
+
+#[test]
+fn load_structs_indexed() {
+    let data = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pubtypes")).unwrap();
+    let names = |indexed: bool| {
+        let mut parser = Parser::from_bytes(&data).unwrap();
+        if indexed {
+            parser.load_structs_indexed().unwrap();
+        } else {
+            parser.load_structs().unwrap();
+        }
+        let mut names: Vec<_> = parser.struct_dict.into_keys().collect();
+        names.sort_unstable();
+        names
+    };
+    assert_eq!(names(false), ["listed", "local"]);
+    // .debug_pubtypes has no types declared in functions
+    assert_eq!(names(true), ["listed"]);
+
+    // without an index every unit is walked
+    let mut parser = Parser::from_bytes(&fs::read(INPUT).unwrap()).unwrap();
+    parser.load_structs_indexed().unwrap();
+    assert_eq!(parser.struct_count(), self::parser().struct_count());
+}
+
+// a .debug_names index of the unit at 0 with one struct entry, whose
+// DW_IDX_die_offset is in `form`
+fn debug_names(version: u16, form: u8) -> Vec<u8> {
+    let abbrevs = [1, 0x13, 3, form, 0, 0, 0];
+    let mut index = Vec::new();
+    index.extend(version.to_le_bytes());
+    index.extend(0u16.to_le_bytes());
+    // one compile unit, no type units or hash table and one name
+    for value in [1u32, 0, 0, 0, 1, abbrevs.len() as u32, 0] {
+        index.extend(value.to_le_bytes());
+    }
+    // the unit offset, the string offset and the entry offset
+    for value in [0u32, 0, 0] {
+        index.extend(value.to_le_bytes());
+    }
+    index.extend(abbrevs);
+    index.extend([1, 0x20, 0]);
+    let mut section = (index.len() as u32).to_le_bytes().to_vec();
+    section.extend(index);
+    section
+}
+
+#[test]
+fn unreadable_name_index() {
+    // an index in an unknown version or with a form the reader doesn't know
+    // is ignored, and the unit it describes walked
+    for (version, form) in [(4, gimli::DW_FORM_data1.0 as u8), (5, gimli::DW_FORM_strx1.0 as u8)] {
+        let mut dwarf = Dwarf::new();
+        let root = dwarf.root();
+        dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("walked")), (gimli::DW_AT_byte_size, udata(0))]);
+        let mut parser = dwarf.builder().debug_names(debug_names(version, form)).build().unwrap();
+        parser.load_structs_indexed().unwrap();
+        assert!(parser.get_struct("walked").is_some(), "version {} form {:#x}", version, form);
+    }
+}
+
+#[test]
+fn lazy_lookups() {
+    let loaded = parser();
+    let parser = Parser::new_lazy(fs::File::open(INPUT).unwrap()).unwrap();
+    // found without indexing anything, twice from what was remembered
+    for _ in 0..2 {
+        assert_eq!(parser.get_struct("qualifiers"), loaded.get_struct("qualifiers"));
+        assert!(parser.get_struct("missing").is_none());
+    }
+    assert_eq!(parser.struct_count(), 0);
+    let shape = parser.get_struct("shape").unwrap();
+    assert_eq!(shape.to_c_decl(&parser).unwrap(), loaded.get_struct("shape").unwrap().to_c_decl(&loaded).unwrap());
+}
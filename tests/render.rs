@@ -0,0 +1,328 @@
+// rendering of the types in tests/data/types, see tests/data/types.c, and
+// benches/data/structs
+
+mod common;
+
+use std::fs;
+
+use common::{string, udata, unit_ref, Dwarf};
+use rshole::{canonical_type_name, EnumValue, FlexibleArrayStyle, Parser, RenderOptions, Type};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/types");
+const STRUCTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs");
+
+fn parser() -> Parser {
+    load(INPUT)
+}
+
+fn load(path: &str) -> Parser {
+    let data = fs::read(path).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+#[test]
+fn offset_and_size_comments() {
+    let parser = load(STRUCTS);
+    let decl = parser.get_struct("holey").unwrap().to_c_decl(&parser).unwrap();
+    assert_eq!(decl, "\
+struct holey {
+\tchar a;      /*     0     1 */
+\t/* XXX 7 bytes hole, try to pack */
+\tlong int b;  /*     8     8 */
+\tchar c;      /*    16     1 */
+\t/* XXX 3 bytes hole, try to pack */
+\tint d;       /*    20     4 */
+\tshort int e; /*    24     2 */
+\t/* padding: 6 */
+
+\t/* size: 32 */
+};
+");
+}
+
+#[test]
+fn bitfield_widths() {
+    let parser = load(STRUCTS);
+    let decl = parser.get_struct("bits").unwrap().to_c_decl(&parser).unwrap();
+    assert_eq!(decl, "\
+struct bits {
+\tu32 valid : 1;  /*     0     4 */
+\tu32 dirty : 1;  /*     0     4 */
+\tu32 order : 4;  /*     1     4 */
+\tu32 count : 20; /*     1     4 */
+\tu8 flags;       /*     4     1 */
+\t/* padding: 3 */
+
+\t/* size: 8 */
+};
+");
+}
+
+#[test]
+fn canonical_names() {
+    let parser = load(STRUCTS);
+    let member_type = |struct_name: &str, path: &str| {
+        let member = parser.get_struct(struct_name).unwrap().resolve_field_path(&parser, path).unwrap().unwrap().0;
+        member.mb_type.unwrap()
+    };
+    let name = |type_inst: &Type| canonical_type_name(type_inst, &parser);
+
+    // two references to the same struct
+    assert_eq!(name(&member_type("node", "list")), "struct list_head");
+    assert_eq!(name(&member_type("task", "tasks")), "struct list_head");
+    assert_eq!(name(&member_type("table", "nr_buckets")), "u64");
+    assert_eq!(name(&member_type("table", "buckets")), "struct node **");
+    assert_eq!(name(&member_type("node", "state")), "enum state");
+    assert_eq!(name(&member_type("node", "entries")), "struct inner[8]");
+
+    // anonymous types are named after their DIE, the same one every time
+    let id = member_type("node", "id");
+    let id_name = name(&id);
+    assert!(id_name.starts_with("union <anon 0x"), "{}", id_name);
+    assert_eq!(name(&member_type("task", "root.id")), id_name);
+    let anon = match &id {
+        Type::Union(id) => id.members(&parser).unwrap().remove(1).mb_type.unwrap(),
+        id => panic!("{:?}", id)
+    };
+    assert!(name(&anon).starts_with("struct <anon 0x"), "{}", name(&anon));
+    assert_ne!(name(&anon)["struct ".len()..], id_name["union ".len()..]);
+}
+
+#[test]
+fn qualifiers() {
+    let parser = parser();
+    let decl = parser.get_struct("qualifiers").unwrap().to_c_decl(&parser).unwrap();
+    for expected in [
+        "volatile int lock;",
+        "char *restrict r;",
+        "_Atomic int counter;",
+        "char *const p;",
+        "const int *const *cpp;",
+        "volatile unsigned char *volatile io;",
+        "void (*const fp)(void);",
+    ] {
+        assert!(decl.contains(expected), "{} not in {}", expected, decl);
+    }
+    assert!(!decl.contains("DW_TAG"), "{}", decl);
+}
+
+#[test]
+fn qualified_canonical_names() {
+    let parser = parser();
+    let dw_struct = parser.get_struct("qualifiers").unwrap();
+    let names: Vec<_> = dw_struct.members_by_offset(&parser).unwrap().into_iter()
+        .map(|member| canonical_type_name(&member.mb_type.unwrap(), &parser))
+        .collect();
+    assert_eq!(names[..6], [
+        "volatile int",
+        "char *restrict",
+        "_Atomic int",
+        "char *const",
+        "const int *const *",
+        "volatile unsigned char *volatile",
+    ]);
+}
+
+#[test]
+fn function_pointers() {
+    let parser = parser();
+    let callbacks = parser.get_struct("callbacks").unwrap();
+    let decl = callbacks.to_c_decl(&parser).unwrap();
+    for expected in [
+        "handler_t handler;",
+        "handler_t table[2];",
+        "int (*compare)(const void *, const void *);",
+        "int (*log)(const char *, ...);",
+    ] {
+        assert!(decl.contains(expected), "{} not in {}", expected, decl);
+    }
+
+    // handler_t is a typedef of a pointer to void (int)
+    let members = callbacks.members_by_offset(&parser).unwrap();
+    let handler = members[0].mb_type.clone().unwrap();
+    assert!(matches!(handler, Type::Typedef(_)), "{:?}", handler);
+    let pointer = parser.get_type(handler).unwrap().unwrap();
+    let subroutine = match parser.get_type(pointer).unwrap() {
+        Some(Type::Subroutine(subroutine)) => subroutine,
+        target => panic!("handler_t points to {:?}", target)
+    };
+    assert!(subroutine.return_type(&parser).unwrap().is_none());
+    let params: Vec<_> = subroutine.params(&parser).unwrap().iter().map(|param| canonical_type_name(param, &parser)).collect();
+    assert_eq!(params, ["int"]);
+    assert!(!subroutine.is_variadic(&parser).unwrap());
+}
+
+#[test]
+fn enums() {
+    let parser = parser();
+    let enums = parser.get_struct("enums").unwrap();
+    assert_eq!(enums.to_c_decl(&parser).unwrap(), "\
+struct enums {
+\tenum { RED = 1, GREEN = 2 } anon; /*     0     4 */
+\tenum color named;                 /*     4     4 */
+
+\t/* size: 8 */
+};
+");
+    let color = match enums.members_by_offset(&parser).unwrap().remove(1).mb_type {
+        Some(Type::Enum(color)) => color,
+        mb_type => panic!("{:?}", mb_type)
+    };
+    assert_eq!(color.enumerators(&parser).unwrap(), [(String::from("BLUE"), EnumValue::Int(0)), (String::from("BLACK"), EnumValue::Int(-1))]);
+}
+
+#[test]
+fn flexible_arrays() {
+    let parser = parser();
+    let args = parser.get_struct("args").unwrap();
+    let render = |style| args.to_c_decl_with(&parser, &RenderOptions::new().flexible_array_style(style)).unwrap();
+    assert_eq!(args.to_c_decl(&parser).unwrap(), render(FlexibleArrayStyle::Empty));
+    assert!(render(FlexibleArrayStyle::Empty).contains("const char *argv[];"), "{}", render(FlexibleArrayStyle::Empty));
+    assert!(render(FlexibleArrayStyle::Zero).contains("const char *argv[0];"), "{}", render(FlexibleArrayStyle::Zero));
+    let omitted = render(FlexibleArrayStyle::Omit);
+    assert!(!omitted.contains("argv"), "{}", omitted);
+    assert!(omitted.contains("int argc;"), "{}", omitted);
+}
+
+#[test]
+fn typedef_sizes() {
+    let options = RenderOptions::new().typedef_sizes(true);
+    let parser = load(STRUCTS);
+    let decl = parser.get_struct("table").unwrap().to_c_decl_with(&parser, &options).unwrap();
+    assert!(decl.contains("u64 nr_buckets; /* 8 bytes */"), "{}", decl);
+    assert!(decl.contains("struct node **buckets;  "), "{}", decl);
+    assert!(!parser.get_struct("table").unwrap().to_c_decl(&parser).unwrap().contains("bytes */"));
+
+    // one element of an array
+    let parser = load(INPUT);
+    let decl = parser.get_struct("callbacks").unwrap().to_c_decl_with(&parser, &options).unwrap();
+    assert!(decl.contains("handler_t table[2]; /* 8 bytes */"), "{}", decl);
+}
+
+#[test]
+fn jsonl() {
+    let parser = load(STRUCTS);
+    let mut out = Vec::new();
+    parser.write_jsonl(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(lines.len(), parser.struct_dict.len());
+
+    // sorted by name
+    let names: Vec<_> = lines.iter().map(|line| line.split('"').nth(3).unwrap()).collect();
+    let mut sorted = names.clone();
+    sorted.sort_unstable();
+    assert_eq!(names, sorted);
+
+    assert!(lines.contains(&concat!(
+        r#"{"name":"packed_hdr","size":5,"members":["#,
+        r#"{"name":"kind","offset":0,"size":1,"type":"u8"},"#,
+        r#"{"name":"len","offset":1,"size":4,"type":"u32"}]}"#,
+    )), "{}", out);
+    let bits = lines.iter().find(|line| line.starts_with(r#"{"name":"bits","#)).unwrap();
+    assert!(bits.contains(r#"{"name":"order","offset":1,"size":4,"type":"u32","bit_size":4,"bit_position":8}"#), "{}", bits);
+}
+
+// struct deep { int ****...p; } with `levels` pointers, all behind a chain of
+// as many typedefs
+fn deep(levels: usize) -> Parser {
+    let mut dwarf = Dwarf::new();
+    let mut target = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let root = dwarf.root();
+    for _ in 0..levels {
+        target = dwarf.add(root, gimli::DW_TAG_pointer_type, vec![(gimli::DW_AT_type, unit_ref(target))]);
+    }
+    let mut typedef = target;
+    for idx in 0..levels {
+        typedef = dwarf.add(root, gimli::DW_TAG_typedef, vec![(gimli::DW_AT_name, string(&format!("t{}", idx))), (gimli::DW_AT_type, unit_ref(typedef))]);
+    }
+    let deep = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("deep")), (gimli::DW_AT_byte_size, udata(16))]);
+    dwarf.member(deep, "p", target, 0);
+    dwarf.member(deep, "t", typedef, 8);
+    dwarf.parser()
+}
+
+#[test]
+fn depth_limits() {
+    let mut parser = deep(20);
+    let dw_struct = parser.get_struct("deep").unwrap();
+    let decl = dw_struct.to_c_decl(&parser).unwrap();
+    assert!(decl.contains(&format!("int {}p;", "*".repeat(20))), "{}", decl);
+    assert!(!decl.contains("..."), "{}", decl);
+
+    // the rest of the declarator is cut off
+    let decl = dw_struct.to_c_decl_with(&parser, &RenderOptions::new().max_depth(4)).unwrap();
+    assert!(decl.contains("... *****p;"), "{}", decl);
+
+    // resolving t's size follows all 20 typedefs
+    assert!(dw_struct.members_by_offset(&parser).is_ok());
+    parser.set_max_depth(10);
+    assert!(dw_struct.members_by_offset(&parser).is_err());
+}
+
+// struct outer { int tag; union { int i; float f; }; } as clang emits it,
+// with DW_AT_export_symbols on the union when `exported`
+fn anonymous_union(exported: bool) -> Parser {
+    let mut dwarf = Dwarf::new();
+    let int = dwarf.base("int", 4, gimli::DW_ATE_signed);
+    let float = dwarf.base("float", 4, gimli::DW_ATE_float);
+    let root = dwarf.root();
+    let outer = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("outer")), (gimli::DW_AT_byte_size, udata(8))]);
+    dwarf.member(outer, "tag", int, 0);
+    let union = dwarf.add(outer, gimli::DW_TAG_union_type, vec![(gimli::DW_AT_byte_size, udata(4))]);
+    if exported {
+        dwarf.unit.unit.get_mut(union).set(gimli::DW_AT_export_symbols, gimli::write::AttributeValue::Flag(true));
+    }
+    dwarf.member(union, "i", int, 0);
+    dwarf.member(union, "f", float, 0);
+    dwarf.add(outer, gimli::DW_TAG_member, vec![(gimli::DW_AT_type, unit_ref(union)), (gimli::DW_AT_data_member_location, udata(4))]);
+    dwarf.parser()
+}
+
+#[test]
+fn exported_symbols() {
+    for exported in [true, false] {
+        let parser = anonymous_union(exported);
+        let outer = parser.get_struct("outer").unwrap();
+        let union = match outer.members_by_offset(&parser).unwrap().remove(1).mb_type {
+            Some(Type::Union(union)) => union,
+            mb_type => panic!("{:?}", mb_type)
+        };
+        assert_eq!(union.exports_symbols(&parser).unwrap(), exported);
+        assert!(!outer.exports_symbols(&parser).unwrap());
+        // the members of an exported union are shown with the parent's
+        let decl = outer.to_c_decl(&parser).unwrap();
+        let expected = if exported { "union { int i; float f; };" } else { "union {...};" };
+        assert!(decl.contains(expected), "{} not in {}", expected, decl);
+    }
+}
+
+#[test]
+fn fortran_strings() {
+    // a character(len=8) and one whose length is only known at run time
+    let mut dwarf = Dwarf::new();
+    let root = dwarf.root();
+    let fixed = dwarf.add(root, gimli::DW_TAG_string_type, vec![(gimli::DW_AT_byte_size, udata(8))]);
+    let mut length = gimli::write::Expression::new();
+    length.op_fbreg(-8);
+    let deferred = dwarf.add(root, gimli::DW_TAG_string_type, vec![(gimli::DW_AT_string_length, gimli::write::AttributeValue::Exprloc(length))]);
+    let record = dwarf.add(root, gimli::DW_TAG_structure_type, vec![(gimli::DW_AT_name, string("record")), (gimli::DW_AT_byte_size, udata(16))]);
+    dwarf.member(record, "name", fixed, 0);
+    dwarf.member(record, "note", deferred, 8);
+    let parser = dwarf.parser();
+
+    let record = parser.get_struct("record").unwrap();
+    let members = record.members_by_offset(&parser).unwrap();
+    let lengths: Vec<_> = members.iter().map(|member| match &member.mb_type {
+        Some(Type::FortranString(string_type)) => (string_type.length, member.size),
+        mb_type => panic!("{:?}", mb_type)
+    }).collect();
+    assert_eq!(lengths, [(Some(8), 8), (None, 0)]);
+    let names: Vec<_> = members.into_iter().map(|member| canonical_type_name(&member.mb_type.unwrap(), &parser)).collect();
+    assert_eq!(names, ["character(len=8)", "character(len=*)"]);
+    let decl = record.to_c_decl(&parser).unwrap();
+    assert!(decl.contains("character(len=8) name;"), "{}", decl);
+    assert!(decl.contains("character(len=*) note;"), "{}", decl);
+}
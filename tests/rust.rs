@@ -0,0 +1,69 @@
+// compiles the rust structs Struct::to_rust_decl writes with assertions that
+// their size and field offsets match the dwarf they came from
+
+use std::fs;
+use std::process::{self, Command};
+
+use rshole::Parser;
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/data/structs");
+
+fn parser() -> Parser {
+    let data = fs::read(INPUT).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+#[test]
+fn rust_decl() {
+    let parser = parser();
+    assert_eq!(parser.get_struct("holey").unwrap().to_rust_decl(&parser).unwrap(), "\
+#[repr(C)]
+pub struct holey {
+    pub a: core::ffi::c_char,
+    pub b: core::ffi::c_long,
+    pub c: core::ffi::c_char,
+    pub d: core::ffi::c_int,
+    pub e: core::ffi::c_short,
+}
+");
+    // the bitfields become bytes and the struct keeps their alignment
+    let bits = parser.get_struct("bits").unwrap().to_rust_decl(&parser).unwrap();
+    assert!(bits.starts_with("#[repr(C, align(4))]\n"), "{}", bits);
+    assert!(bits.contains("    // valid: 1, dirty: 1, order: 4, count: 20\n    pub _bitfield0: [u8; 4],\n"), "{}", bits);
+    assert!(parser.get_struct("packed_hdr").unwrap().to_rust_decl(&parser).unwrap().starts_with("#[repr(C, packed)]\n"));
+}
+
+#[test]
+fn rust_decl_round_trips() {
+    let parser = parser();
+    let mut names: Vec<_> = parser.struct_dict.keys().cloned().collect();
+    names.sort_unstable();
+
+    let mut source = String::from("#![allow(non_camel_case_types, dead_code)]\n");
+    for name in &names {
+        let dw_struct = parser.get_struct(name).unwrap();
+        source.push_str(&dw_struct.to_rust_decl(&parser).unwrap());
+        source.push_str(&format!("const _: () = assert!(core::mem::size_of::<{}>() == {});\n", name, dw_struct.size));
+        for member in dw_struct.members_by_offset(&parser).unwrap() {
+            if let (Some(field), None) = (member.name, member.bit_size) {
+                source.push_str(&format!("const _: () = assert!(core::mem::offset_of!({}, {}) == {});\n", name, field, member.offset));
+            }
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("rshole-rust-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source_path = dir.join("structs.rs");
+    fs::write(&source_path, &source).unwrap();
+    let output = match Command::new("rustc").args(["--crate-type", "lib", "--edition", "2021", "--out-dir"]).arg(&dir).arg(&source_path).output() {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("skipping, no rustc: {}", err);
+            return;
+        }
+    };
+    assert!(output.status.success(), "{}\n{}", String::from_utf8_lossy(&output.stderr), source);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,211 @@
+// parsing of the types in tests/data/types, see tests/data/types.c
+
+use std::fs;
+
+use object::{Object, ObjectSection};
+use rshole::{canonical_type_name, Declarator, Parser, Qualifier, StructMember, StructMemberIter, Type};
+
+const INPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/types");
+// the same types in .debug_types and in dwarf 5 type units
+const TYPE_UNITS: [&str; 2] = [
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/typeunits4"),
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/typeunits5"),
+];
+
+fn parser() -> Parser {
+    load(INPUT)
+}
+
+fn load(path: &str) -> Parser {
+    let data = fs::read(path).unwrap();
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    parser
+}
+
+fn member(parser: &Parser, struct_name: &str, name: &str) -> StructMember {
+    let dw_struct = parser.get_struct(struct_name).unwrap_or_else(|| panic!("no struct {}", struct_name));
+    dw_struct.members_by_offset(parser).unwrap().into_iter()
+        .find(|member| member.name.as_deref() == Some(name))
+        .unwrap_or_else(|| panic!("no member {}.{}", struct_name, name))
+}
+
+// file offset of the DW_AT_type of the DIE at `die_offset` in .debug_info
+fn type_attr_offset(data: &[u8], die_offset: u64) -> usize {
+    let file = object::File::parse(data).unwrap();
+    let section = file.section_by_name(".debug_info").unwrap();
+    let (section_offset, _) = section.file_range().unwrap();
+    let debug_info = gimli::DebugInfo::new(section.data().unwrap(), gimli::LittleEndian);
+    let debug_abbrev = gimli::DebugAbbrev::new(file.section_by_name(".debug_abbrev").unwrap().data().unwrap(), gimli::LittleEndian);
+    let mut units = debug_info.units();
+    while let Some(header) = units.next().unwrap() {
+        let offset = match gimli::DebugInfoOffset(die_offset as usize).to_unit_offset(&header) {
+            Some(offset) => offset,
+            None => continue
+        };
+        let unit_offset = header.offset().as_debug_info_offset().unwrap().0;
+        let abbreviations = header.abbreviations(&debug_abbrev).unwrap();
+        let mut entries = header.entries_raw(&abbreviations, Some(offset)).unwrap();
+        let abbreviation = entries.read_abbreviation().unwrap().unwrap();
+        for spec in abbreviation.attributes() {
+            let position = entries.next_offset().0;
+            if entries.read_attribute(*spec).unwrap().name() == gimli::DW_AT_type {
+                assert_eq!(spec.form(), gimli::DW_FORM_ref4);
+                return section_offset as usize + unit_offset + position;
+            }
+        }
+    }
+    panic!("no DW_AT_type at {:#x}", die_offset)
+}
+
+#[test]
+fn array_bounds() {
+    let parser = parser();
+    // gcc writes the upper bound 255 as data1 0xff and 65535 as data2 0xffff
+    for (name, count, size) in [("small", 4, 4), ("buf", 256, 256), ("wide", 65536, 131072), ("empty", 0, 0), ("tail", 0, 0)] {
+        let array = match member(&parser, "arrays", name).mb_type {
+            Some(Type::Array(array)) => array,
+            mb_type => panic!("{} is not an array: {:?}", name, mb_type)
+        };
+        assert_eq!(array.element_count, count, "element count of {}", name);
+        assert_eq!(array.byte_size(&parser).unwrap(), size, "size of {}", name);
+    }
+    let decl = parser.get_struct("arrays").unwrap().to_c_decl(&parser).unwrap();
+    assert!(decl.contains("char buf[256];"), "{}", decl);
+    assert!(decl.contains("short int wide[65536];"), "{}", decl);
+    assert!(!decl.contains("hole"), "{}", decl);
+}
+
+#[test]
+fn array_elements() {
+    // flexible and zero length arrays keep their element type
+    let parser = parser();
+    for (name, element) in [("small", "char"), ("wide", "short int"), ("empty", "int"), ("tail", "char")] {
+        match member(&parser, "arrays", name).mb_type {
+            Some(Type::Array(array)) => assert_eq!(canonical_type_name(&array.element, &parser), element, "element of {}", name),
+            mb_type => panic!("{} is not an array: {:?}", name, mb_type)
+        }
+    }
+    let decl = parser.get_struct("arrays").unwrap().to_c_decl(&parser).unwrap();
+    assert!(decl.contains("char tail[];"), "{}", decl);
+}
+
+#[test]
+fn array_sizes() {
+    let parser = parser();
+    for (name, element_count, byte_size) in [("small", 4, 4), ("buf", 256, 256), ("wide", 65536, 131072), ("empty", 0, 0), ("tail", 0, 0)] {
+        match member(&parser, "arrays", name).mb_type {
+            Some(Type::Array(array)) => {
+                assert_eq!(array.element_count, element_count, "{}", name);
+                assert_eq!(array.byte_size(&parser).unwrap(), byte_size, "{}", name);
+            }
+            mb_type => panic!("{} is not an array: {:?}", name, mb_type)
+        }
+    }
+}
+
+#[test]
+fn clone_members_and_types() {
+    let parser = parser();
+    let cpp = member(&parser, "qualifiers", "cpp");
+    let copy = cpp.clone();
+    assert_eq!(format!("{:?}", copy), format!("{:?}", cpp));
+    // a cloned type resolves like the original
+    let mb_type: Type = cpp.mb_type.unwrap();
+    let target = parser.get_type(mb_type.clone()).unwrap().unwrap();
+    assert_eq!(format!("{:?}", parser.get_type(mb_type).unwrap().unwrap()), format!("{:?}", target));
+    assert_eq!(canonical_type_name(&target.clone(), &parser), canonical_type_name(&target, &parser));
+}
+
+#[test]
+fn declarators() {
+    let parser = parser();
+    let declarator = |struct_name: &str, name: &str| member(&parser, struct_name, name).declarator(&parser).unwrap();
+    let expected = |base: &str, pointer_levels, array_dims: &[u64], qualifiers: &[Qualifier]| Declarator {
+        base: String::from(base),
+        pointer_levels,
+        array_dims: array_dims.to_vec(),
+        qualifiers: qualifiers.to_vec(),
+    };
+    assert_eq!(declarator("args", "argv"), expected("char", 1, &[0], &[Qualifier::Const]));
+    assert_eq!(declarator("args", "argc"), expected("int", 0, &[], &[]));
+    assert_eq!(declarator("qualifiers", "cpp"), expected("int", 2, &[], &[Qualifier::Const, Qualifier::Const]));
+    assert_eq!(declarator("qualifiers", "io"), expected("unsigned char", 1, &[], &[Qualifier::Volatile, Qualifier::Volatile]));
+    assert_eq!(declarator("qualifiers", "r"), expected("char", 1, &[], &[Qualifier::Restrict]));
+    assert_eq!(declarator("qualifiers", "counter"), expected("int", 0, &[], &[Qualifier::Atomic]));
+    assert_eq!(declarator("arrays", "wide"), expected("short int", 0, &[65536], &[]));
+    // typedefs end the walk
+    assert_eq!(declarator("callbacks", "table"), expected("handler_t", 0, &[2], &[]));
+}
+
+#[test]
+fn typedef_members() {
+    let parser = parser();
+    let members = |name: &str| match member(&parser, "shape", name).mb_type {
+        Some(Type::Typedef(typedef)) => typedef.members(&parser).unwrap().map(|members| {
+            members.into_iter().map(|member| (member.name.unwrap(), member.offset)).collect::<Vec<_>>()
+        }),
+        mb_type => panic!("{} is a {:?}", name, mb_type)
+    };
+    assert_eq!(members("origin"), Some(vec![(String::from("x"), 0), (String::from("y"), 4)]));
+    assert_eq!(members("scale"), Some(vec![(String::from("i"), 0), (String::from("f"), 0)]));
+    // not an aggregate
+    match member(&parser, "callbacks", "handler").mb_type {
+        Some(Type::Typedef(typedef)) => assert!(typedef.members(&parser).unwrap().is_none()),
+        mb_type => panic!("{:?}", mb_type)
+    }
+}
+
+#[test]
+fn corrupt_member_is_an_error() {
+    // point the type of qualifiers.lock past the end of its unit
+    let mut data = fs::read(INPUT).unwrap();
+    let lock = member(&parser(), "qualifiers", "lock");
+    let position = type_attr_offset(&data, lock.die_offset());
+    data[position..position + 4].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+
+    let mut parser = Parser::from_bytes(&data).unwrap();
+    parser.load_structs().unwrap();
+    let qualifiers = parser.get_struct("qualifiers").unwrap();
+    let members: Vec<_> = StructMemberIter::new(&qualifiers, &parser).collect();
+    assert_eq!(members.len(), 1, "the error ends the iteration");
+    assert!(members[0].is_err());
+    assert!(qualifiers.members_by_offset(&parser).is_err());
+    assert!(qualifiers.to_c_decl(&parser).is_err());
+}
+
+#[test]
+fn truncated_debug_info_is_an_error() {
+    // a first unit longer than .debug_info
+    let mut data = fs::read(INPUT).unwrap();
+    let file = object::File::parse(&*data).unwrap();
+    let (section_offset, section_size) = file.section_by_name(".debug_info").unwrap().file_range().unwrap();
+    let unit_length = (section_offset as usize)..(section_offset as usize + 4);
+    data[unit_length].copy_from_slice(&(section_size as u32 + 16).to_le_bytes());
+
+    let result = Parser::from_bytes(&data).and_then(|mut parser| parser.load_structs());
+    assert!(result.is_err());
+}
+
+#[test]
+fn type_units() {
+    let parser = parser();
+    for path in TYPE_UNITS {
+        let split = load(path);
+        let mut names: Vec<_> = split.struct_dict.keys().collect();
+        names.sort_unstable();
+        let mut expected: Vec<_> = parser.struct_dict.keys().collect();
+        expected.sort_unstable();
+        assert_eq!(names, expected, "{}", path);
+
+        // members in the unit of `shape` point through DW_AT_signature stubs
+        for name in expected {
+            // dwarf 4 has no DW_TAG_atomic_type
+            if path.ends_with('4') && name == "qualifiers" {
+                continue;
+            }
+            let decl = split.get_struct(name).unwrap().to_c_decl(&split).unwrap();
+            assert_eq!(decl, parser.get_struct(name).unwrap().to_c_decl(&parser).unwrap(), "{}", path);
+        }
+    }
+}